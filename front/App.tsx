@@ -19,6 +19,7 @@ import {
   getCommitment,
   verifyClue,
   verifyCommitment,
+  verifyRejection,
 } from "./src/api";
 import { Keyboard } from "./src/Keyboard";
 import { ResultView } from "./src/ResultView";
@@ -185,7 +186,14 @@ export default function App() {
       getClue(text, commitment.word_id).then((response) => {
         if (response.type === "error") {
           if (response.error === "word does not exist") {
-            setIsInvalidGuess(true);
+            // the word is on the local list, so the server has to prove it isn't a word
+            verifyRejection(text, response.absence).then((valid) => {
+              if (valid) {
+                setIsInvalidGuess(true);
+              } else {
+                setIsInvalidProof(true);
+              }
+            });
           } else {
             setIsCommitmentOld(true);
           }
@@ -1,9 +1,12 @@
 import "@expo/browser-polyfill";
 import {
   Color,
+  type AbsenceProof,
   type Clue,
   type ClueResponse,
   type Commitment,
+  type DictionaryResponse,
+  type ErrorResponse,
   type Proof,
   type Result,
   type SerializedProof,
@@ -11,8 +14,11 @@ import {
 } from "./types";
 import { groth16, type Groth16Proof } from "snarkjs";
 import { vk_clue, vk_membership } from "./keys";
+import { DICTIONARY_ROOT, verifyAbsence } from "./dictionary";
 
 const ADDRESS = "http://localhost:4000";
+// identifies this tab to the server's rate limiter
+const SESSION_ID = Math.random().toString(36).slice(2);
 
 function sanitizeProof(proof: SerializedProof): Proof {
   return {
@@ -35,11 +41,23 @@ export async function getClue(
     body: JSON.stringify({ guess: guess.toLowerCase(), word_id }),
     headers: {
       "Content-Type": "application/json",
+      "X-Session-Id": SESSION_ID,
     },
   });
   if (!res.ok) {
-    const text = await res.text();
-    return { type: "error", error: text };
+    // a JSON string, or an object with the error and the proof of a rejected word
+    const body = (await res.json().catch(() => null)) as
+      | string
+      | ErrorResponse
+      | null;
+    if (typeof body === "string") {
+      return { type: "error", error: body };
+    }
+    return {
+      type: "error",
+      error: body?.error ?? res.statusText,
+      absence: body?.absence,
+    };
   }
   const { colors, proof } = (await res.json()) as ClueResponse;
 
@@ -111,7 +129,7 @@ export async function getCommitment(): Promise<Commitment> {
 }
 
 const rootHash =
-  "4521038097998819656385597678445593990587736616960091096304104088305796197427";
+  "10424035767147034679344862651791158482343389586877949351482144581424056564321";
 
 export async function verifyCommitment(
   commitment: Commitment
@@ -127,3 +145,21 @@ export async function verifyCommitment(
     console
   );
 }
+
+// Checks that a rejected guess really isn't in the dictionary, with the proof the server sent
+// along, against the root it serves, which has to be the published one.
+export async function verifyRejection(
+  guess: string,
+  absence: AbsenceProof | undefined
+): Promise<boolean> {
+  if (absence === undefined) {
+    return false;
+  }
+  const res = await fetch(`${ADDRESS}/dictionary`);
+  const { root } = (await res.json()) as DictionaryResponse;
+
+  return (
+    root === DICTIONARY_ROOT &&
+    verifyAbsence(guess.toLowerCase(), absence, root)
+  );
+}
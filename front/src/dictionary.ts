@@ -0,0 +1,67 @@
+import { poseidon } from "./poseidon";
+import type { AbsenceProof, PathEntry } from "./types";
+
+// the hash parameters of the server (HASH_PARAMS in server/src/proofs.rs); from version 2 on,
+// every hash takes its domain tag as the first input
+const HASH_VERSION: number = 1;
+const DOMAIN_LEAF = 1;
+const DOMAIN_NODE = 2;
+
+// root of the dictionary, the sorted merkle tree over all acceptable guesses, as published in the
+// README; the root served by the server is only trusted if it's this one
+export const DICTIONARY_ROOT =
+  "5218044997700184954803699218973511299292424004075201938258657522667259364369";
+
+function tagged(tag: number, inputs: bigint[]): bigint[] {
+  return HASH_VERSION >= 2 ? [BigInt(tag), ...inputs] : inputs;
+}
+
+// the leaf of a word of 5 lowercase letters, the hash of its letters encoded by a=0, b=1, ...
+export function wordLeaf(word: string): bigint {
+  const letters = word.split("").map((ch) => BigInt(ch.charCodeAt(0) - 97));
+  return poseidon(tagged(DOMAIN_LEAF, letters));
+}
+
+function verifyPath(leaf: bigint, path: PathEntry[], root: bigint): boolean {
+  let node = leaf;
+  for (const entry of path) {
+    const left = BigInt(entry.left);
+    const right = BigInt(entry.right);
+    if ((entry.on_path === 0 ? left : right) !== node) {
+      return false;
+    }
+    node = poseidon(tagged(DOMAIN_NODE, [left, right]));
+  }
+  return node === root;
+}
+
+// index of the leaf the path starts from, its directions from the leaf up are the index bits
+function pathIndex(path: PathEntry[]): number {
+  return path.reduce(
+    (idx, entry, level) => (entry.on_path === 1 ? idx + 2 ** level : idx),
+    0
+  );
+}
+
+// Checks the proof that the word is not in the dictionary with the root: the word's leaf falls
+// strictly between two adjacent leaves of the sorted tree.
+export function verifyAbsence(
+  word: string,
+  absence: AbsenceProof,
+  root: string
+): boolean {
+  if (!/^[a-z]{5}$/.test(word)) {
+    return false;
+  }
+  const leaf = wordLeaf(word);
+  const low = BigInt(absence.low);
+  const high = BigInt(absence.high);
+  return (
+    low < leaf &&
+    leaf < high &&
+    absence.low_path.length === absence.high_path.length &&
+    pathIndex(absence.low_path) + 1 === pathIndex(absence.high_path) &&
+    verifyPath(low, absence.low_path, BigInt(root)) &&
+    verifyPath(high, absence.high_path, BigInt(root))
+  );
+}
@@ -0,0 +1,56 @@
+import { poseidonConstants } from "./poseidonConstants";
+
+// the scalar field of bn254, the one the circuits and the merkle trees are over
+const FIELD_MODULUS = BigInt(
+  "21888242871839275222246405745257275088548364400416034343698204186575808495617"
+);
+const FULL_ROUNDS = 8;
+
+type Constants = { partialRounds: number; c: bigint[]; m: bigint[][] };
+
+// parsed on first use, by the number of inputs
+const parsed = new Map<number, Constants>();
+
+function constants(inputs: number): Constants {
+  let found = parsed.get(inputs);
+  if (found === undefined) {
+    const raw = poseidonConstants[inputs];
+    if (raw === undefined) {
+      throw new Error(`no poseidon constants for ${inputs} inputs`);
+    }
+    found = {
+      partialRounds: raw.partialRounds,
+      c: raw.c.map((x) => BigInt(x)),
+      m: raw.m.map((row) => row.map((x) => BigInt(x))),
+    };
+    parsed.set(inputs, found);
+  }
+  return found;
+}
+
+function pow5(x: bigint): bigint {
+  const x2 = (x * x) % FIELD_MODULUS;
+  return (((x2 * x2) % FIELD_MODULUS) * x) % FIELD_MODULUS;
+}
+
+// Poseidon hash of the inputs, the same as circomlib's Poseidon(inputs.length) and poseidon-rs.
+export function poseidon(inputs: bigint[]): bigint {
+  const { partialRounds, c, m } = constants(inputs.length);
+  const t = inputs.length + 1;
+
+  let state = [BigInt(0), ...inputs.map((x) => x % FIELD_MODULUS)];
+  for (let round = 0; round < FULL_ROUNDS + partialRounds; round++) {
+    state = state.map((x, i) => (x + c[round * t + i]) % FIELD_MODULUS);
+    // the partial rounds only raise the first element
+    const full =
+      round < FULL_ROUNDS / 2 || round >= FULL_ROUNDS / 2 + partialRounds;
+    state = state.map((x, i) => (full || i === 0 ? pow5(x) : x));
+    state = m.map((row) =>
+      row.reduce(
+        (acc, mij, j) => (acc + mij * state[j]) % FIELD_MODULUS,
+        BigInt(0)
+      )
+    );
+  }
+  return state[0];
+}
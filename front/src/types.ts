@@ -45,6 +45,31 @@ export type Commitment = {
   word_id: string;
 };
 
+export type PathEntry = {
+  left: string;
+  right: string;
+  // 0 if the left node is on the path, 1 if the right one
+  on_path: number;
+};
+
+// proof that a guess is not in the dictionary: the adjacent leaves around its hash
+export type AbsenceProof = {
+  low: string;
+  low_path: PathEntry[];
+  high: string;
+  high_path: PathEntry[];
+};
+
+export type ErrorResponse = {
+  error: string;
+  absence?: AbsenceProof;
+};
+
+export type DictionaryResponse = {
+  root: string;
+  words: number;
+};
+
 export type Result<T> =
   | { type: "ok"; value: T }
-  | { type: "error"; error: string };
+  | { type: "error"; error: string; absence?: AbsenceProof };
@@ -0,0 +1,51 @@
+//! Time to build `MerkleTree` over dictionaries of 2k up to 1M words, run with and without the
+//! `parallel` feature to compare:
+//!
+//! `cargo bench` and `cargo bench --features parallel`
+//!
+//! `BENCH_MAX_LEAVES` skips the larger sizes.
+
+use std::time::Instant;
+
+use merkle::MerkleTree;
+
+const SIZES: [usize; 5] = [2_048, 16_384, 131_072, 524_288, 1_048_576];
+const RUNS: usize = 3;
+
+// the i-th 5 letter word, in base 26
+fn word(mut i: usize) -> String {
+    (0..5)
+        .map(|_| {
+            let letter = (b'a' + (i % 26) as u8) as char;
+            i /= 26;
+            letter
+        })
+        .collect()
+}
+
+fn main() {
+    let max_leaves = std::env::var("BENCH_MAX_LEAVES")
+        .ok()
+        .and_then(|max| max.parse().ok())
+        .unwrap_or(usize::MAX);
+    println!(
+        "merkle tree build, {}",
+        if cfg!(feature = "parallel") {
+            "parallel"
+        } else {
+            "sequential"
+        }
+    );
+
+    for n in SIZES.into_iter().filter(|&n| n <= max_leaves) {
+        let words: Vec<_> = (0..n).map(word).collect();
+        let mut best = f64::MAX;
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            let tree = MerkleTree::new(&words).expect("tree creation should succeed");
+            best = best.min(start.elapsed().as_secs_f64());
+            assert_eq!(tree.leaf_count(), n);
+        }
+        println!("{:>9} leaves: {:>9.3} s (best of {})", n, best, RUNS);
+    }
+}
@@ -0,0 +1,327 @@
+use num::BigUint;
+use poseidon_rs::{Fr, Poseidon};
+
+use crate::{
+    biguint_to_fr, fr_to_biguint, zero_hashes, Error, FrMerklePathEntry, HashParams,
+    MerklePathEntry, NodeType,
+};
+
+/// What changed the root of an incremental tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TreeChange {
+    Created,
+    Inserted(usize),
+    Updated(usize),
+    Appended(usize),
+    // the capacity was doubled, the new depth is given
+    Grown(usize),
+}
+
+/// A root the tree had, and the change which produced it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RootRecord {
+    pub root: BigUint,
+    pub change: TreeChange,
+}
+
+/// Merkle tree which is changed in place, recomputing only the path of the changed leaf. It has
+/// a fixed capacity of `2^depth` leaves, the slots without a leaf hold zero. The capacity is only
+/// doubled on request, as that changes the length of the paths (and circuits are built for one
+/// depth). Every root the tree had is kept, with the change which produced it, so that proofs
+/// against an older root can still be explained.
+///
+/// Leaves are field elements, as in `MerkleTree::from_leaves`, and paths are verified with
+/// `verify_path`. The padding and the hashes (`HashParams::V1`) are the same as in `MerkleTree`, so
+/// for the same leaves and depth the roots are the same.
+pub struct IncrementalMerkleTree {
+    depth: usize,
+    // levels[0] are the leaves, levels[depth] is the root
+    levels: Vec<Vec<Fr>>,
+    occupied: Vec<bool>,
+    // zeros[l] is the hash of an empty subtree of height l
+    zeros: Vec<Fr>,
+    history: Vec<RootRecord>,
+    poseidon: Poseidon,
+}
+
+impl IncrementalMerkleTree {
+    /// Creates an empty tree with room for `2^depth` leaves.
+    pub fn new(depth: usize) -> Result<Self, Error> {
+        let poseidon = Poseidon::new();
+        let zeros = zero_hashes(depth, 2, HashParams::V1, &poseidon)?;
+        let levels = (0..=depth)
+            .map(|l| vec![zeros[l]; 1 << (depth - l)])
+            .collect();
+
+        let mut tree = IncrementalMerkleTree {
+            depth,
+            levels,
+            occupied: vec![false; 1 << depth],
+            zeros,
+            history: Vec::new(),
+            poseidon,
+        };
+        tree.record(TreeChange::Created);
+        Ok(tree)
+    }
+
+    /// Creates a tree with room for `2^depth` leaves, holding the leaves from the first slot on.
+    pub fn with_leaves(leaves: &[BigUint], depth: usize) -> Result<Self, Error> {
+        if leaves.len() > 1 << depth {
+            return Err(Error::TreeFull);
+        }
+        let mut tree = Self::new(depth)?;
+        for (idx, leaf) in leaves.iter().enumerate() {
+            tree.levels[0][idx] = biguint_to_fr(leaf)?;
+            tree.occupied[idx] = true;
+        }
+        for l in 1..=depth {
+            for i in 0..tree.levels[l].len() {
+                let (left, right) = (tree.levels[l - 1][2 * i], tree.levels[l - 1][2 * i + 1]);
+                tree.levels[l][i] = HashParams::V1.node_hash(&[left, right], &tree.poseidon)?;
+            }
+        }
+        // the history starts with the filled tree
+        tree.history.clear();
+        tree.record(TreeChange::Created);
+        Ok(tree)
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn capacity(&self) -> usize {
+        1 << self.depth
+    }
+
+    /// One past the last occupied slot, which is where `append` puts the next leaf.
+    pub fn len(&self) -> usize {
+        self.occupied
+            .iter()
+            .rposition(|&occupied| occupied)
+            .map_or(0, |idx| idx + 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Puts the leaf into the free slot.
+    pub fn insert(&mut self, idx: usize, leaf: &BigUint) -> Result<(), Error> {
+        match self.occupied.get(idx) {
+            None => return Err(Error::OutOfBounds),
+            Some(true) => return Err(Error::LeafOccupied),
+            Some(false) => {}
+        }
+        self.set(idx, biguint_to_fr(leaf)?)?;
+        self.occupied[idx] = true;
+        self.record(TreeChange::Inserted(idx));
+        Ok(())
+    }
+
+    /// Replaces the leaf in the occupied slot.
+    pub fn update(&mut self, idx: usize, leaf: &BigUint) -> Result<(), Error> {
+        match self.occupied.get(idx) {
+            None => return Err(Error::OutOfBounds),
+            Some(false) => return Err(Error::LeafMissing),
+            Some(true) => {}
+        }
+        self.set(idx, biguint_to_fr(leaf)?)?;
+        self.record(TreeChange::Updated(idx));
+        Ok(())
+    }
+
+    /// Puts the leaf after the last occupied slot, and returns its index. Fails once the last
+    /// slot is taken, the capacity has to be doubled first.
+    pub fn append(&mut self, leaf: &BigUint) -> Result<usize, Error> {
+        let idx = self.len();
+        if idx == self.capacity() {
+            return Err(Error::TreeFull);
+        }
+        self.set(idx, biguint_to_fr(leaf)?)?;
+        self.occupied[idx] = true;
+        self.record(TreeChange::Appended(idx));
+        Ok(idx)
+    }
+
+    /// Doubles the capacity. The current tree becomes the left subtree of the new root, so the
+    /// leaves keep their indices, and only the new root is hashed.
+    pub fn double_capacity(&mut self) -> Result<(), Error> {
+        let zero_subtree = self.zeros[self.depth];
+        let new_root = HashParams::V1
+            .node_hash(&[self.levels[self.depth][0], zero_subtree], &self.poseidon)?;
+        self.zeros
+            .push(HashParams::V1.node_hash(&[zero_subtree, zero_subtree], &self.poseidon)?);
+        for (l, level) in self.levels.iter_mut().enumerate() {
+            let len = level.len();
+            level.resize(2 * len, self.zeros[l]);
+        }
+        self.levels.push(vec![new_root]);
+        self.occupied.resize(2 * self.occupied.len(), false);
+        self.depth += 1;
+        self.record(TreeChange::Grown(self.depth));
+        Ok(())
+    }
+
+    pub fn get_path(&self, idx: usize) -> Result<Vec<MerklePathEntry>, Error> {
+        if idx >= self.capacity() {
+            return Err(Error::OutOfBounds);
+        }
+        let mut path = Vec::with_capacity(self.depth);
+        let mut i = idx;
+        for level in &self.levels[..self.depth] {
+            let entry = if i % 2 == 0 {
+                FrMerklePathEntry {
+                    left: level[i],
+                    right: level[i + 1],
+                    on_path: NodeType::Left,
+                }
+            } else {
+                FrMerklePathEntry {
+                    left: level[i - 1],
+                    right: level[i],
+                    on_path: NodeType::Right,
+                }
+            };
+            path.push(entry.into());
+            i /= 2;
+        }
+        Ok(path)
+    }
+
+    pub fn root_hash(&self) -> BigUint {
+        fr_to_biguint(self.levels[self.depth][0])
+    }
+
+    /// Every root the tree had, oldest first, the last one is the current root.
+    pub fn root_history(&self) -> &[RootRecord] {
+        &self.history
+    }
+
+    /// Whether the tree ever had the root.
+    pub fn had_root(&self, root: &BigUint) -> bool {
+        self.history.iter().any(|record| &record.root == root)
+    }
+
+    // sets the leaf and recomputes its path up to the root
+    fn set(&mut self, idx: usize, leaf: Fr) -> Result<(), Error> {
+        self.levels[0][idx] = leaf;
+        let mut i = idx;
+        for l in 1..=self.depth {
+            i /= 2;
+            let (left, right) = (self.levels[l - 1][2 * i], self.levels[l - 1][2 * i + 1]);
+            self.levels[l][i] = HashParams::V1.node_hash(&[left, right], &self.poseidon)?;
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, change: TreeChange) {
+        self.history.push(RootRecord {
+            root: self.root_hash(),
+            change,
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num::BigUint;
+
+    use super::{IncrementalMerkleTree, TreeChange};
+    use crate::{verify_path, Error, MerkleTree};
+
+    fn leaves(n: u32) -> Vec<BigUint> {
+        (1..=n).map(BigUint::from).collect()
+    }
+
+    #[test]
+    fn full_tree_matches_merkle_tree() {
+        let leaves = leaves(4);
+        let expected = MerkleTree::from_leaves(&leaves).unwrap().root_hash();
+
+        let mut appended = IncrementalMerkleTree::new(2).unwrap();
+        for leaf in &leaves {
+            appended.append(leaf).unwrap();
+        }
+        let built = IncrementalMerkleTree::with_leaves(&leaves, 2).unwrap();
+
+        assert_eq!(appended.root_hash(), expected);
+        assert_eq!(built.root_hash(), expected);
+        assert_eq!(appended.append(&BigUint::from(5u32)), Err(Error::TreeFull));
+    }
+
+    #[test]
+    fn changes_keep_paths_valid() {
+        let mut tree = IncrementalMerkleTree::with_leaves(&leaves(3), 2).unwrap();
+        assert_eq!(tree.len(), 3);
+
+        tree.update(1, &BigUint::from(7u32)).unwrap();
+        assert_eq!(
+            tree.update(3, &BigUint::from(7u32)),
+            Err(Error::LeafMissing)
+        );
+        tree.insert(3, &BigUint::from(8u32)).unwrap();
+        assert_eq!(
+            tree.insert(3, &BigUint::from(9u32)),
+            Err(Error::LeafOccupied)
+        );
+
+        let expected = [1u32, 7, 3, 8].map(BigUint::from);
+        let root = tree.root_hash();
+        for (idx, leaf) in expected.iter().enumerate() {
+            assert!(verify_path(leaf, &tree.get_path(idx).unwrap(), &root).unwrap());
+        }
+        assert_eq!(
+            root,
+            MerkleTree::from_leaves(&expected).unwrap().root_hash()
+        );
+    }
+
+    #[test]
+    fn doubling_keeps_leaves() {
+        let mut tree = IncrementalMerkleTree::with_leaves(&leaves(2), 1).unwrap();
+        assert_eq!(tree.append(&BigUint::from(3u32)), Err(Error::TreeFull));
+
+        tree.double_capacity().unwrap();
+        assert_eq!((tree.depth(), tree.capacity()), (2, 4));
+        assert_eq!(tree.append(&BigUint::from(3u32)), Ok(2));
+
+        // the zero leaf fills the free slot
+        let expected = [1u32, 2, 3, 0].map(BigUint::from);
+        assert_eq!(
+            tree.root_hash(),
+            MerkleTree::from_leaves(&expected).unwrap().root_hash()
+        );
+        let path = tree.get_path(0).unwrap();
+        assert_eq!(path.len(), 2);
+        assert!(verify_path(&expected[0], &path, &tree.root_hash()).unwrap());
+    }
+
+    #[test]
+    fn root_history_recorded() {
+        let mut tree = IncrementalMerkleTree::new(1).unwrap();
+        tree.append(&BigUint::from(1u32)).unwrap();
+        let old_root = tree.root_hash();
+        tree.update(0, &BigUint::from(2u32)).unwrap();
+        tree.double_capacity().unwrap();
+
+        let changes: Vec<_> = tree
+            .root_history()
+            .iter()
+            .map(|record| record.change.clone())
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                TreeChange::Created,
+                TreeChange::Appended(0),
+                TreeChange::Updated(0),
+                TreeChange::Grown(2)
+            ]
+        );
+        assert_eq!(tree.root_history().last().unwrap().root, tree.root_hash());
+        assert!(tree.had_root(&old_root));
+        assert!(!tree.had_root(&BigUint::from(5u32)));
+    }
+}
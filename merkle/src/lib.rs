@@ -1,6 +1,21 @@
 use ff::{Field, PrimeField};
 use num::{BigUint, Num};
 use poseidon_rs::{Fr, Poseidon};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+mod incremental;
+mod params;
+mod sorted;
+mod storage;
+
+pub use incremental::{IncrementalMerkleTree, RootRecord, TreeChange};
+pub use params::{HashParams, DOMAIN_COMMITMENT, DOMAIN_LEAF, DOMAIN_NODE};
+pub use sorted::{path_index, NonMembershipWitness, SortedMerkleTree};
+pub use storage::RootCheck;
+
+/// Most inputs of a hash, so children of a node, as circomlib's poseidon hashes at most 16.
+pub const MAX_ARITY: usize = 16;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Error {
@@ -10,6 +25,21 @@ pub enum Error {
     OutOfBounds,
     WordHashFail,
     SaltedWordHashFail,
+    // no free slot left, the capacity has to be doubled
+    TreeFull,
+    LeafOccupied,
+    LeafMissing,
+    // a serialized tree is truncated, or its header doesn't match the nodes
+    BadTreeFormat,
+    UnsupportedTreeVersion(u8),
+    UnknownHashFunction(u8),
+    // the stored root (or a node) differs from the one hashed from the leaves
+    RootMismatch,
+    // the arity is out of 2..=max_arity of the parameters, or a binary path was asked of another
+    // arity
+    UnsupportedArity(usize),
+    // the sentinels of a sorted tree can't be values
+    ReservedLeaf,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -42,43 +72,165 @@ impl From<FrMerklePathEntry> for MerklePathEntry {
     }
 }
 
+/// Path entry of a tree of any arity: all the children of a node on the path, and the position
+/// of the one which is on the path.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NaryPathEntry {
+    pub children: Vec<BigUint>,
+    pub position: usize,
+}
+
+impl From<MerklePathEntry> for NaryPathEntry {
+    fn from(x: MerklePathEntry) -> Self {
+        let position = match x.on_path {
+            NodeType::Left => 0,
+            NodeType::Right => 1,
+        };
+        NaryPathEntry {
+            children: vec![x.left, x.right],
+            position,
+        }
+    }
+}
+
+/// Merkle tree over a list of leaves, in which every node has `arity` children (2 unless created
+/// with another arity). The leaves are padded with empty leaves up to the next power of the arity
+/// (a single leaf is a tree of depth 0, whose root is the leaf). An empty leaf is zero, which no
+/// word hashes to, so the padding can't be mistaken for a word. A subtree of only empty leaves
+/// hashes to the zero hash of its height, the same as in `IncrementalMerkleTree`. The hashes are
+/// `HashParams::V1` unless created with other parameters.
+#[derive(Debug, PartialEq, Eq)]
 pub struct MerkleTree {
-    m: usize,
-    hashes: Vec<Fr>,
+    arity: usize,
+    params: HashParams,
+    // number of leaves, without the padding
+    n: usize,
+    // levels[0] are the padded leaves, levels[depth] is the root
+    levels: Vec<Vec<Fr>>,
 }
 
 impl MerkleTree {
-    #[allow(clippy::needless_range_loop)]
+    /// Creates the binary tree over the hashes of the words.
     pub fn new(words: &[String]) -> Result<Self, Error> {
-        // get the last word which will be used to fill the bottom level of the tree
-        // so that its size is a power of 2
-        let last_word = words.last().ok_or(Error::EmptyWordList)?;
+        Self::with_arity(words, 2)
+    }
+
+    /// Creates the tree with `arity` children per node over the hashes of the words.
+    pub fn with_arity(words: &[String], arity: usize) -> Result<Self, Error> {
+        Self::with_params(words, arity, HashParams::V1)
+    }
+
+    /// Creates the tree with `arity` children per node over the hashes of the words, all hashed
+    /// with the parameters.
+    pub fn with_params(words: &[String], arity: usize, params: HashParams) -> Result<Self, Error> {
+        let p = Poseidon::new();
+        let leaves = hash_words(words, params, &p, THREADS)?;
+        Self::build(&leaves, arity, params, &p)
+    }
+
+    /// Creates the binary tree over arbitrary field elements as leaves, instead of word hashes.
+    pub fn from_leaves(leaves: &[BigUint]) -> Result<Self, Error> {
+        Self::from_leaves_with_arity(leaves, 2)
+    }
+
+    /// Creates the tree with `arity` children per node over arbitrary field elements as leaves.
+    pub fn from_leaves_with_arity(leaves: &[BigUint], arity: usize) -> Result<Self, Error> {
+        Self::from_leaves_with_params(leaves, arity, HashParams::V1)
+    }
+
+    /// Creates the tree with `arity` children per node over arbitrary field elements as leaves,
+    /// with the nodes hashed with the parameters.
+    pub fn from_leaves_with_params(
+        leaves: &[BigUint],
+        arity: usize,
+        params: HashParams,
+    ) -> Result<Self, Error> {
+        let leaves = leaves
+            .iter()
+            .map(biguint_to_fr)
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::build(&leaves, arity, params, &Poseidon::new())
+    }
 
-        // get the size of the hashes array
-        let n = words.len();
-        let mut m = 1;
+    fn build(leaves: &[Fr], arity: usize, params: HashParams, p: &Poseidon) -> Result<Self, Error> {
+        Self::build_with(leaves, arity, params, p, THREADS)
+    }
+
+    fn build_with(
+        leaves: &[Fr],
+        arity: usize,
+        params: HashParams,
+        p: &Poseidon,
+        threads: Threads,
+    ) -> Result<Self, Error> {
+        let n = leaves.len();
+        if n == 0 {
+            return Err(Error::EmptyWordList);
+        }
+        if !(2..=params.max_arity()).contains(&arity) {
+            return Err(Error::UnsupportedArity(arity));
+        }
+        let (mut depth, mut m) = (0, 1);
         while m < n {
-            m *= 2;
+            depth += 1;
+            m *= arity;
+        }
+        let zeros = zero_hashes(depth, arity, params, p)?;
+
+        let mut padded = leaves.to_vec();
+        padded.resize(m, Fr::zero());
+        let mut levels = vec![padded];
+        // nodes with at least one leaf below, the rest are empty subtrees
+        let mut filled = n;
+        // level by level, the nodes of a level only depend on the level below
+        for &zero in &zeros[1..] {
+            filled = filled.div_ceil(arity);
+            let below = levels.last().expect("there should be the leaves");
+            let mut level = vec![zero; below.len() / arity];
+            // the levels near the root are too small to be worth spreading
+            let threads = if filled < PARALLEL_MIN_NODES {
+                Threads::Single
+            } else {
+                threads
+            };
+            hash_groups(
+                &mut level[..filled],
+                &below[..filled * arity],
+                params,
+                p,
+                threads,
+            )?;
+            levels.push(level);
         }
 
-        // initialize the hashes array with dummy 0 values
-        let mut hashes = vec![Fr::zero(); 2 * m];
+        Ok(MerkleTree {
+            arity,
+            params,
+            n,
+            levels,
+        })
+    }
 
-        // get the bottom level hashes of words
-        let p = poseidon_rs::Poseidon::new();
-        for i in m..2 * m {
-            let word = words.get(i - m).unwrap_or(last_word);
-            hashes[i] = word_hash(word, &p)?;
-        }
+    /// Number of children of every node.
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
 
-        // fill the upper levels
-        for i in (1..m - 1).rev() {
-            hashes[i] = merkle_hash(hashes[2 * i], hashes[2 * i + 1], &p)?;
-        }
+    pub fn params(&self) -> HashParams {
+        self.params
+    }
 
-        Ok(MerkleTree { m, hashes })
+    /// Number of levels above the leaves, which is the length of every path.
+    pub fn depth(&self) -> usize {
+        self.levels.len() - 1
     }
 
+    /// Number of leaves the tree was created over, without the padding.
+    pub fn leaf_count(&self) -> usize {
+        self.n
+    }
+
+    /// Path of a binary tree.
     pub fn get_path(&self, idx: usize) -> Result<Vec<MerklePathEntry>, Error> {
         Ok(self
             .get_path_inner(idx)?
@@ -88,30 +240,54 @@ impl MerkleTree {
     }
 
     fn get_path_inner(&self, idx: usize) -> Result<Vec<FrMerklePathEntry>, Error> {
-        if idx >= self.m {
+        if self.arity != 2 {
+            return Err(Error::UnsupportedArity(self.arity));
+        }
+        if idx >= self.levels[0].len() {
             return Err(Error::OutOfBounds);
         }
-        let mut tree_idx = self.m + idx;
 
         // complete the path entries
-        let mut path = Vec::new();
-        while tree_idx > 1 {
-            if tree_idx % 2 == 0 {
+        let mut path = Vec::with_capacity(self.depth());
+        let mut i = idx;
+        for level in &self.levels[..self.depth()] {
+            if i % 2 == 0 {
                 // the selected node is of type left
                 path.push(FrMerklePathEntry {
-                    left: self.hashes[tree_idx],
-                    right: self.hashes[tree_idx + 1],
+                    left: level[i],
+                    right: level[i + 1],
                     on_path: NodeType::Left,
                 })
             } else {
                 // the selected node is of type right
                 path.push(FrMerklePathEntry {
-                    left: self.hashes[tree_idx - 1],
-                    right: self.hashes[tree_idx],
+                    left: level[i - 1],
+                    right: level[i],
                     on_path: NodeType::Right,
                 })
             }
-            tree_idx /= 2;
+            i /= 2;
+        }
+        Ok(path)
+    }
+
+    /// Path of a tree of any arity, binary ones included.
+    pub fn get_nary_path(&self, idx: usize) -> Result<Vec<NaryPathEntry>, Error> {
+        if idx >= self.levels[0].len() {
+            return Err(Error::OutOfBounds);
+        }
+        let mut path = Vec::with_capacity(self.depth());
+        let mut i = idx;
+        for level in &self.levels[..self.depth()] {
+            let first = i - i % self.arity;
+            path.push(NaryPathEntry {
+                children: level[first..first + self.arity]
+                    .iter()
+                    .map(|&child| fr_to_biguint(child))
+                    .collect(),
+                position: i % self.arity,
+            });
+            i /= self.arity;
         }
         Ok(path)
     }
@@ -121,38 +297,106 @@ impl MerkleTree {
     }
 
     fn root_hash_inner(&self) -> Fr {
-        self.hashes[1]
+        self.levels[self.depth()][0]
     }
 }
 
+/// Checks that the path leads from the leaf up to the root, with `HashParams::V1`.
+pub fn verify_path(
+    leaf: &BigUint,
+    path: &[MerklePathEntry],
+    root: &BigUint,
+) -> Result<bool, Error> {
+    HashParams::V1.verify_path(leaf, path, root)
+}
+
+/// Checks that the path of a tree of any arity leads from the leaf up to the root, with
+/// `HashParams::V1`.
+pub fn verify_nary_path(
+    leaf: &BigUint,
+    path: &[NaryPathEntry],
+    root: &BigUint,
+) -> Result<bool, Error> {
+    HashParams::V1.verify_nary_path(leaf, path, root)
+}
+
+/// Commitment to the word with the salt, with `HashParams::V1`.
 pub fn hash_word_with_salt(word: &str, salt: &BigUint) -> Result<BigUint, Error> {
-    let mut input = Vec::with_capacity(6);
-    for c in word.bytes() {
-        let letter_id = c - 97;
-        input.push(
-            Fr::from_str(&letter_id.to_string()).expect("string should be a correct decimal"),
-        );
-    }
-    input.push(Fr::from_str(&salt.to_string()).expect("string should be a correct decimal"));
+    HashParams::V1.commitment(word, salt)
+}
+
+/// Whether the hashing is spread across threads, with the `parallel` feature. The trees are
+/// the same either way.
+#[derive(Clone, Copy, Debug)]
+enum Threads {
+    Single,
+    #[cfg(feature = "parallel")]
+    Many,
+}
 
-    let p = Poseidon::new();
-    let hash = p.hash(input).map_err(|_| Error::SaltedWordHashFail)?;
-    Ok(fr_to_biguint(hash))
+#[cfg(feature = "parallel")]
+const THREADS: Threads = Threads::Many;
+#[cfg(not(feature = "parallel"))]
+const THREADS: Threads = Threads::Single;
+// levels with fewer nodes than this are hashed on a single thread
+const PARALLEL_MIN_NODES: usize = 32;
+
+fn hash_words(
+    words: &[String],
+    params: HashParams,
+    p: &Poseidon,
+    threads: Threads,
+) -> Result<Vec<Fr>, Error> {
+    let hash = |word: &String| params.word_hash(word, p);
+    match threads {
+        Threads::Single => words.iter().map(hash).collect(),
+        #[cfg(feature = "parallel")]
+        Threads::Many => words.par_iter().map(hash).collect(),
+    }
 }
 
-fn merkle_hash(a: Fr, b: Fr, p: &Poseidon) -> Result<Fr, Error> {
-    let input = vec![a, b];
-    p.hash(input).map_err(|_| Error::MerkleHashFail)
+// hashes every group of `arity` children into the node above it
+fn hash_groups(
+    nodes: &mut [Fr],
+    children: &[Fr],
+    params: HashParams,
+    p: &Poseidon,
+    threads: Threads,
+) -> Result<(), Error> {
+    let arity = children.len() / nodes.len();
+    let hash = |(node, group): (&mut Fr, &[Fr])| {
+        *node = params.node_hash(group, p)?;
+        Ok(())
+    };
+    match threads {
+        Threads::Single => nodes
+            .iter_mut()
+            .zip(children.chunks_exact(arity))
+            .try_for_each(hash),
+        #[cfg(feature = "parallel")]
+        Threads::Many => nodes
+            .par_iter_mut()
+            .zip(children.par_chunks_exact(arity))
+            .try_for_each(hash),
+    }
 }
 
-fn word_hash(word: &str, p: &Poseidon) -> Result<Fr, Error> {
-    let mut letter_ids = Vec::with_capacity(word.len());
-    for b in word.bytes() {
-        let num = b - 97; // assumes alpha string in lowercase
-        let fr = Fr::from_str(&num.to_string()).ok_or(Error::FrCreateFail)?;
-        letter_ids.push(fr);
+/// `zeros[h]` is the root of a subtree of height `h` with only empty leaves.
+fn zero_hashes(
+    depth: usize,
+    arity: usize,
+    params: HashParams,
+    p: &Poseidon,
+) -> Result<Vec<Fr>, Error> {
+    let mut zeros = vec![Fr::zero()];
+    for h in 0..depth {
+        zeros.push(params.node_hash(&vec![zeros[h]; arity], p)?);
     }
-    p.hash(letter_ids).map_err(|_| Error::WordHashFail)
+    Ok(zeros)
+}
+
+fn biguint_to_fr(n: &BigUint) -> Result<Fr, Error> {
+    Fr::from_str(&n.to_string()).ok_or(Error::FrCreateFail)
 }
 
 fn fr_to_biguint(fr: Fr) -> BigUint {
@@ -169,7 +413,12 @@ mod test {
     use num::BigUint;
     use poseidon_rs::{Fr, Poseidon};
 
-    use crate::{fr_to_biguint, word_hash, Error, MerkleTree, NodeType};
+    use crate::{
+        biguint_to_fr, fr_to_biguint, verify_nary_path, verify_path, Error, HashParams,
+        IncrementalMerkleTree, MerkleTree, NaryPathEntry, NodeType,
+    };
+    #[cfg(feature = "parallel")]
+    use crate::{hash_words, Threads};
 
     #[test]
     fn word_hash_correct() {
@@ -181,7 +430,7 @@ mod test {
         let p = Poseidon::new();
 
         let expected_hash = p.hash(word_repr).unwrap();
-        let actual_hash = word_hash(word, &p).unwrap();
+        let actual_hash = HashParams::V1.word_hash(word, &p).unwrap();
 
         assert_eq!(actual_hash, expected_hash);
     }
@@ -243,6 +492,145 @@ mod test {
         }
     }
 
+    #[test]
+    fn leaf_paths_verify() {
+        let leaves: Vec<_> = (1..=5u32).map(BigUint::from).collect();
+        let tree = MerkleTree::from_leaves(&leaves).expect("tree creation should succeed");
+
+        // the padding is the empty leaf
+        let padded = leaves.iter().cloned().chain(vec![BigUint::from(0u32); 3]);
+        for (idx, leaf) in padded.enumerate() {
+            let path = tree.get_path(idx).expect("path should exist");
+            assert_eq!(path.len(), 3);
+            assert!(verify_path(&leaf, &path, &tree.root_hash()).unwrap());
+            assert!(!verify_path(&BigUint::from(9u32), &path, &tree.root_hash()).unwrap());
+        }
+
+        let single = MerkleTree::from_leaves(&leaves[..1]).unwrap();
+        assert_eq!(single.root_hash(), leaves[0]);
+        assert!(verify_path(&leaves[0], &single.get_path(0).unwrap(), &leaves[0]).unwrap());
+    }
+
+    #[test]
+    fn every_size_consistent() {
+        let p = Poseidon::new();
+        for n in [1usize, 2, 3, 7, 8, 9, 15, 16, 17] {
+            let leaves: Vec<_> = (1..=n as u32).map(BigUint::from).collect();
+            let tree = MerkleTree::from_leaves(&leaves).unwrap();
+            let depth = n.next_power_of_two().trailing_zeros() as usize;
+            assert_eq!(tree.depth(), depth, "size {}", n);
+
+            for (idx, leaf) in leaves.iter().enumerate() {
+                let path = tree.get_path_inner(idx).unwrap();
+                assert_eq!(path.len(), depth, "size {}", n);
+                // verify_path, without creating the hasher for every leaf
+                let mut node = biguint_to_fr(leaf).unwrap();
+                for entry in path {
+                    let on_path = match entry.on_path {
+                        NodeType::Left => entry.left,
+                        NodeType::Right => entry.right,
+                    };
+                    assert_eq!(on_path, node, "size {}", n);
+                    node = HashParams::V1
+                        .node_hash(&[entry.left, entry.right], &p)
+                        .unwrap();
+                }
+                assert_eq!(node, tree.root_hash_inner(), "size {}", n);
+            }
+            // hashing the empty subtrees gives the same root
+            let hashed = IncrementalMerkleTree::with_leaves(&leaves, depth).unwrap();
+            assert_eq!(tree.root_hash(), hashed.root_hash(), "size {}", n);
+        }
+    }
+
+    #[test]
+    fn nary_paths_verify() {
+        let p = Poseidon::new();
+        let leaves: Vec<_> = (1..=10u32).map(BigUint::from).collect();
+        for (arity, depth) in [(3, 3), (4, 2), (8, 2), (16, 1)] {
+            let tree = MerkleTree::from_leaves_with_arity(&leaves, arity).unwrap();
+            assert_eq!(tree.depth(), depth, "arity {}", arity);
+
+            for (idx, leaf) in leaves.iter().enumerate() {
+                let path = tree.get_nary_path(idx).unwrap();
+                assert_eq!(path.len(), depth, "arity {}", arity);
+                // verify_nary_path, without creating the hasher for every leaf
+                let mut node = biguint_to_fr(leaf).unwrap();
+                for entry in path {
+                    assert_eq!(entry.children.len(), arity);
+                    let children: Vec<_> = entry
+                        .children
+                        .iter()
+                        .map(|child| biguint_to_fr(child).unwrap())
+                        .collect();
+                    assert_eq!(children[entry.position], node, "arity {}", arity);
+                    node = HashParams::V1.node_hash(&children, &p).unwrap();
+                }
+                assert_eq!(node, tree.root_hash_inner(), "arity {}", arity);
+            }
+            assert_eq!(
+                tree.get_path(0).unwrap_err(),
+                Error::UnsupportedArity(arity)
+            );
+        }
+
+        let tree = MerkleTree::from_leaves_with_arity(&leaves, 4).unwrap();
+        let path = tree.get_nary_path(9).unwrap();
+        assert!(verify_nary_path(&leaves[9], &path, &tree.root_hash()).unwrap());
+        assert!(!verify_nary_path(&leaves[8], &path, &tree.root_hash()).unwrap());
+
+        // the binary path is the arity 2 one
+        let binary = MerkleTree::from_leaves(&leaves).unwrap();
+        let converted: Vec<NaryPathEntry> = binary
+            .get_path(6)
+            .unwrap()
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        assert_eq!(converted, binary.get_nary_path(6).unwrap());
+        assert!(MerkleTree::from_leaves_with_arity(&leaves, 17).is_err());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_build_identical() {
+        let p = Poseidon::new();
+        let words: Vec<String> = (0..250u8)
+            .map(|i| {
+                String::from_utf8(vec![b'a' + i % 26, b'a' + i / 26, b'x', b'y', b'z']).unwrap()
+            })
+            .collect();
+        let sequential = hash_words(&words, HashParams::V1, &p, Threads::Single).unwrap();
+        assert_eq!(
+            hash_words(&words, HashParams::V1, &p, Threads::Many).unwrap(),
+            sequential
+        );
+
+        for n in [1, 2, 17, 64, 65, 250] {
+            let leaves = &sequential[..n];
+            assert_eq!(
+                MerkleTree::build_with(leaves, 2, HashParams::V1, &p, Threads::Many).unwrap(),
+                MerkleTree::build_with(leaves, 2, HashParams::V1, &p, Threads::Single).unwrap(),
+                "size {}",
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn word_paths_verify() {
+        let words: Vec<String> = ["aaaaa", "bbbbb", "ccccc"].map(String::from).into();
+        let tree = MerkleTree::new(&words).unwrap();
+        let p = Poseidon::new();
+
+        for (idx, word) in words.iter().enumerate() {
+            let leaf = fr_to_biguint(HashParams::V1.word_hash(word, &p).unwrap());
+            let path = tree.get_path(idx).unwrap();
+            assert!(verify_path(&leaf, &path, &tree.root_hash()).unwrap());
+        }
+        assert!(MerkleTree::new(&[]).is_err());
+    }
+
     #[test]
     fn fr_to_biguint_correct() {
         let p = Poseidon::new();
@@ -0,0 +1,189 @@
+use ff::PrimeField;
+use num::BigUint;
+use poseidon_rs::{Fr, Poseidon};
+
+use crate::{
+    biguint_to_fr, fr_to_biguint, Error, MerklePathEntry, NaryPathEntry, NodeType, MAX_ARITY,
+};
+
+/// Domain tag of the leaf hashes, that is of the words, in `HashParams::V2`.
+pub const DOMAIN_LEAF: u64 = 1;
+/// Domain tag of the internal node hashes, in `HashParams::V2`.
+pub const DOMAIN_NODE: u64 = 2;
+/// Domain tag of the commitments to a word and its salt, in `HashParams::V2`.
+pub const DOMAIN_COMMITMENT: u64 = 3;
+
+/// Parameter set of the poseidon hashes, which has to be the one of the circuits. A tree (and its
+/// root) only makes sense together with its parameter set, so both sets can be used side by side
+/// while migrating from one to the other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashParams {
+    /// The hashes are only told apart by the number of inputs: 5 letters for a word, `arity`
+    /// children for a node, and 5 letters with the salt for a commitment.
+    V1,
+    /// Every hash takes its domain tag (`DOMAIN_*`) as the first input, so that a leaf, a node
+    /// and a commitment can't collide whatever their widths. The tag takes one of the inputs, so
+    /// a node has at most 15 children.
+    V2,
+}
+
+impl HashParams {
+    /// Id of the parameter set, the hash function id of serialized trees.
+    pub fn id(self) -> u8 {
+        match self {
+            HashParams::V1 => 1,
+            HashParams::V2 => 2,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(HashParams::V1),
+            2 => Some(HashParams::V2),
+            _ => None,
+        }
+    }
+
+    /// Most children a node can have.
+    pub fn max_arity(self) -> usize {
+        match self {
+            HashParams::V1 => MAX_ARITY,
+            HashParams::V2 => MAX_ARITY - 1,
+        }
+    }
+
+    /// Commitment to the word with the salt.
+    pub fn commitment(self, word: &str, salt: &BigUint) -> Result<BigUint, Error> {
+        let mut input = letter_ids(word)?;
+        input.push(biguint_to_fr(salt)?);
+
+        let p = Poseidon::new();
+        let hash = p
+            .hash(self.tagged(DOMAIN_COMMITMENT, input))
+            .map_err(|_| Error::SaltedWordHashFail)?;
+        Ok(fr_to_biguint(hash))
+    }
+
+    /// Leaf of the word, its hash.
+    pub fn word_leaf(self, word: &str) -> Result<BigUint, Error> {
+        Ok(fr_to_biguint(self.word_hash(word, &Poseidon::new())?))
+    }
+
+    /// Checks that the path leads from the leaf up to the root.
+    pub fn verify_path(
+        self,
+        leaf: &BigUint,
+        path: &[MerklePathEntry],
+        root: &BigUint,
+    ) -> Result<bool, Error> {
+        let p = Poseidon::new();
+        let mut node = biguint_to_fr(leaf)?;
+        for entry in path {
+            let left = biguint_to_fr(&entry.left)?;
+            let right = biguint_to_fr(&entry.right)?;
+            let on_path = match entry.on_path {
+                NodeType::Left => left,
+                NodeType::Right => right,
+            };
+            if on_path != node {
+                return Ok(false);
+            }
+            node = self.node_hash(&[left, right], &p)?;
+        }
+        Ok(node == biguint_to_fr(root)?)
+    }
+
+    /// Checks that the path of a tree of any arity leads from the leaf up to the root.
+    pub fn verify_nary_path(
+        self,
+        leaf: &BigUint,
+        path: &[NaryPathEntry],
+        root: &BigUint,
+    ) -> Result<bool, Error> {
+        let p = Poseidon::new();
+        let mut node = biguint_to_fr(leaf)?;
+        for entry in path {
+            let children = entry
+                .children
+                .iter()
+                .map(biguint_to_fr)
+                .collect::<Result<Vec<_>, _>>()?;
+            if children.get(entry.position) != Some(&node) {
+                return Ok(false);
+            }
+            node = self.node_hash(&children, &p)?;
+        }
+        Ok(node == biguint_to_fr(root)?)
+    }
+
+    pub(crate) fn word_hash(self, word: &str, p: &Poseidon) -> Result<Fr, Error> {
+        let input = letter_ids(word)?;
+        p.hash(self.tagged(DOMAIN_LEAF, input))
+            .map_err(|_| Error::WordHashFail)
+    }
+
+    pub(crate) fn node_hash(self, children: &[Fr], p: &Poseidon) -> Result<Fr, Error> {
+        p.hash(self.tagged(DOMAIN_NODE, children.to_vec()))
+            .map_err(|_| Error::MerkleHashFail)
+    }
+
+    // prepends the domain tag, if the parameter set has them
+    fn tagged(self, tag: u64, mut input: Vec<Fr>) -> Vec<Fr> {
+        if self == HashParams::V2 {
+            let tag = Fr::from_str(&tag.to_string()).expect("tag should be a correct decimal");
+            input.insert(0, tag);
+        }
+        input
+    }
+}
+
+fn letter_ids(word: &str) -> Result<Vec<Fr>, Error> {
+    let mut letter_ids = Vec::with_capacity(word.len() + 2);
+    for b in word.bytes() {
+        let num = b - 97; // assumes alpha string in lowercase
+        let fr = Fr::from_str(&num.to_string()).ok_or(Error::FrCreateFail)?;
+        letter_ids.push(fr);
+    }
+    Ok(letter_ids)
+}
+
+#[cfg(test)]
+mod test {
+    use num::BigUint;
+    use poseidon_rs::Poseidon;
+
+    use super::HashParams;
+    use crate::{fr_to_biguint, MerkleTree};
+
+    #[test]
+    fn tags_change_every_hash() {
+        let p = Poseidon::new();
+        let salt = BigUint::from(7u32);
+        let (v1, v2) = (HashParams::V1, HashParams::V2);
+
+        assert_ne!(
+            v1.commitment("crane", &salt).unwrap(),
+            v2.commitment("crane", &salt).unwrap()
+        );
+        assert_ne!(
+            v1.word_hash("crane", &p).unwrap(),
+            v2.word_hash("crane", &p).unwrap()
+        );
+
+        let words: Vec<String> = ["crane", "slate", "trace"].map(String::from).into();
+        let tree = MerkleTree::with_params(&words, 2, v2).unwrap();
+        assert_ne!(
+            tree.root_hash(),
+            MerkleTree::new(&words).unwrap().root_hash()
+        );
+        assert_eq!(tree.params(), v2);
+
+        let leaf = fr_to_biguint(v2.word_hash("slate", &p).unwrap());
+        let path = tree.get_path(1).unwrap();
+        assert!(v2.verify_path(&leaf, &path, &tree.root_hash()).unwrap());
+        assert!(!v1.verify_path(&leaf, &path, &tree.root_hash()).unwrap());
+
+        // the tag takes one of the inputs
+        assert!(MerkleTree::with_params(&words, 16, v2).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use ff::Field;
+use num::BigUint;
+use poseidon_rs::{Fr, Poseidon};
+
+use crate::{
+    fr_to_biguint, hash_words, Error, HashParams, MerklePathEntry, MerkleTree, NodeType, THREADS,
+};
+
+/// Binary merkle tree over a set of values, with the leaves in increasing order, so that a value
+/// which isn't in the set falls between two adjacent leaves. The leaves are framed by two
+/// sentinels: zero before the values, and the largest field element after them, which also pads
+/// the leaves up to the next power of 2. So every value in between has a lower and a higher
+/// neighbour, and the sentinels themselves can't be values.
+///
+/// The order is the one of the field elements as integers below the modulus. A circuit checking a
+/// non-membership witness has to compare them over the full 254 bits, circomlib's `LessThan` takes
+/// at most 252.
+#[derive(Debug)]
+pub struct SortedMerkleTree {
+    tree: MerkleTree,
+    // the sorted leaves, with the sentinels and the padding
+    leaves: Vec<BigUint>,
+}
+
+/// Shows that a value isn't in a `SortedMerkleTree`: the adjacent leaves around it, with their
+/// paths. The paths are the ones of `MerkleTree::get_path`, and their indices (the `on_path` of
+/// every entry, from the leaves up) have to be consecutive.
+#[derive(Clone, Debug)]
+pub struct NonMembershipWitness {
+    pub low: BigUint,
+    pub low_path: Vec<MerklePathEntry>,
+    pub high: BigUint,
+    pub high_path: Vec<MerklePathEntry>,
+}
+
+impl SortedMerkleTree {
+    /// Creates the tree over the hashes of the words, hashed with the parameters.
+    pub fn new(words: &[String], params: HashParams) -> Result<Self, Error> {
+        let leaves = hash_words(words, params, &Poseidon::new(), THREADS)?;
+        Self::from_leaves(
+            &leaves.into_iter().map(fr_to_biguint).collect::<Vec<_>>(),
+            params,
+        )
+    }
+
+    /// Creates the tree over arbitrary field elements. They're sorted, and repeated ones are kept
+    /// once. Zero and the largest field element are the sentinels, and are refused.
+    pub fn from_leaves(values: &[BigUint], params: HashParams) -> Result<Self, Error> {
+        let (min, max) = sentinels();
+        let mut leaves = values.to_vec();
+        leaves.sort();
+        leaves.dedup();
+        if leaves.first() == Some(&min) || leaves.last() >= Some(&max) {
+            return Err(Error::ReservedLeaf);
+        }
+        leaves.insert(0, min);
+        leaves.resize((leaves.len() + 1).next_power_of_two(), max);
+
+        let tree = MerkleTree::from_leaves_with_params(&leaves, 2, params)?;
+        Ok(SortedMerkleTree { tree, leaves })
+    }
+
+    pub fn params(&self) -> HashParams {
+        self.tree.params()
+    }
+
+    /// Number of levels above the leaves, which is the length of every path.
+    pub fn depth(&self) -> usize {
+        self.tree.depth()
+    }
+
+    /// Number of distinct values, without the sentinels and the padding.
+    pub fn len(&self) -> usize {
+        self.leaves.partition_point(|leaf| leaf < &sentinels().1) - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn root_hash(&self) -> BigUint {
+        self.tree.root_hash()
+    }
+
+    pub fn contains(&self, value: &BigUint) -> bool {
+        self.search(value).is_ok_and(|found| found.is_ok())
+    }
+
+    /// Index and path of the value, which has to be in the tree.
+    pub fn get_path(&self, value: &BigUint) -> Result<(usize, Vec<MerklePathEntry>), Error> {
+        match self.search(value)? {
+            Ok(idx) => Ok((idx, self.tree.get_path(idx)?)),
+            Err(_) => Err(Error::LeafMissing),
+        }
+    }
+
+    /// Witness that the value isn't in the tree.
+    pub fn non_membership_witness(&self, value: &BigUint) -> Result<NonMembershipWitness, Error> {
+        // the low sentinel is before any value, and the high one after
+        let high = match self.search(value)? {
+            Ok(_) => return Err(Error::LeafOccupied),
+            Err(high) => high,
+        };
+        Ok(NonMembershipWitness {
+            low: self.leaves[high - 1].clone(),
+            low_path: self.tree.get_path(high - 1)?,
+            high: self.leaves[high].clone(),
+            high_path: self.tree.get_path(high)?,
+        })
+    }
+
+    // where the value is, or the index of the leaf after it
+    fn search(&self, value: &BigUint) -> Result<Result<usize, usize>, Error> {
+        let (min, max) = sentinels();
+        if value == &min || value >= &max {
+            return Err(Error::ReservedLeaf);
+        }
+        Ok(self.leaves.binary_search(value))
+    }
+}
+
+impl NonMembershipWitness {
+    /// Checks that the value is strictly between two adjacent leaves of the tree with the root.
+    pub fn verify(
+        &self,
+        value: &BigUint,
+        root: &BigUint,
+        params: HashParams,
+    ) -> Result<bool, Error> {
+        if !(&self.low < value && value < &self.high) {
+            return Ok(false);
+        }
+        if self.low_path.len() != self.high_path.len()
+            || path_index(&self.low_path) + 1 != path_index(&self.high_path)
+        {
+            return Ok(false);
+        }
+        Ok(params.verify_path(&self.low, &self.low_path, root)?
+            && params.verify_path(&self.high, &self.high_path, root)?)
+    }
+}
+
+/// Index of the leaf a path starts from.
+pub fn path_index(path: &[MerklePathEntry]) -> usize {
+    path.iter()
+        .enumerate()
+        .filter(|(_, entry)| entry.on_path == NodeType::Right)
+        .map(|(level, _)| 1 << level)
+        .sum()
+}
+
+// the lowest and the highest leaf
+fn sentinels() -> (BigUint, BigUint) {
+    let mut max = Fr::zero();
+    max.sub_assign(&Fr::one());
+    (BigUint::from(0u32), fr_to_biguint(max))
+}
+
+#[cfg(test)]
+mod test {
+    use num::BigUint;
+
+    use super::{path_index, SortedMerkleTree};
+    use crate::{Error, HashParams};
+
+    #[test]
+    fn non_membership_witnessed() {
+        let values = [40u32, 10, 30, 20, 30].map(BigUint::from);
+        let tree = SortedMerkleTree::from_leaves(&values, HashParams::V1).unwrap();
+        let root = tree.root_hash();
+        // the sentinels and 4 values, padded to 8
+        assert_eq!((tree.len(), tree.depth()), (4, 3));
+
+        let (idx, path) = tree.get_path(&BigUint::from(30u32)).unwrap();
+        assert_eq!((idx, path_index(&path)), (3, 3));
+        assert!(tree.contains(&BigUint::from(20u32)));
+        assert_eq!(
+            tree.non_membership_witness(&BigUint::from(20u32)).err(),
+            Some(Error::LeafOccupied)
+        );
+        assert_eq!(
+            tree.get_path(&BigUint::from(25u32)).err(),
+            Some(Error::LeafMissing)
+        );
+        assert_eq!(
+            tree.non_membership_witness(&BigUint::from(0u32)).err(),
+            Some(Error::ReservedLeaf)
+        );
+
+        // between two values, and after the last one, against the high sentinel
+        let inner = BigUint::from(25u32);
+        let witness = tree.non_membership_witness(&inner).unwrap();
+        assert_eq!((&witness.low, &witness.high), (&values[3], &values[2]));
+        assert!(witness.verify(&inner, &root, HashParams::V1).unwrap());
+        let outer = BigUint::from(50u32);
+        assert!(tree
+            .non_membership_witness(&outer)
+            .unwrap()
+            .verify(&outer, &root, HashParams::V1)
+            .unwrap());
+
+        // the value has to be between the leaves, and the leaves adjacent
+        assert!(!witness
+            .verify(&BigUint::from(35u32), &root, HashParams::V1)
+            .unwrap());
+        let mut gap = witness.clone();
+        gap.high = values[0].clone();
+        gap.high_path = tree.get_path(&values[0]).unwrap().1;
+        assert!(!gap.verify(&inner, &root, HashParams::V1).unwrap());
+    }
+}
@@ -0,0 +1,200 @@
+use ff::{PrimeField, PrimeFieldRepr};
+use poseidon_rs::{Fr, FrRepr, Poseidon};
+
+use crate::{Error, HashParams, MerkleTree};
+
+// identifies the format, the version follows
+const MAGIC: &[u8; 4] = b"ZKMT";
+const FORMAT_VERSION: u8 = 1;
+const FR_SIZE: usize = 32;
+// magic, version, hash function id, arity, depth, leaf count (u64) and the root
+const HEADER_SIZE: usize = 4 + 1 + 1 + 1 + 1 + 8 + FR_SIZE;
+
+/// How much of a deserialized tree is checked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RootCheck {
+    /// Only that the root in the header is the root of the nodes. Loading is then just copying,
+    /// and the full check can be done later with `MerkleTree::verify`.
+    Header,
+    /// Every internal node is hashed again from the leaves, which costs as much as building.
+    Recompute,
+}
+
+impl MerkleTree {
+    /// Serializes the tree: the header (magic, format version, hash function id, arity, depth, leaf
+    /// count as u64 little endian, and the root), then every node from the root down to the
+    /// leaves, level by level. Field elements are 32 bytes big endian. The hash function is
+    /// circomlib's poseidon over bn254, its id is the one of the `HashParams`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let nodes: usize = self.levels.iter().map(Vec::len).sum();
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + nodes * FR_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.push(self.params.id());
+        bytes.push(self.arity as u8);
+        bytes.push(self.depth() as u8);
+        bytes.extend_from_slice(&(self.n as u64).to_le_bytes());
+        // the root, and then again as the first node
+        let root = self.root_hash_inner();
+        for node in std::iter::once(&root).chain(self.levels.iter().rev().flatten()) {
+            node.into_repr()
+                .write_be(&mut bytes)
+                .expect("writing to a vec should succeed");
+        }
+        bytes
+    }
+
+    /// Deserializes a tree written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8], check: RootCheck) -> Result<Self, Error> {
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            return Err(Error::BadTreeFormat);
+        }
+        if bytes[4] != FORMAT_VERSION {
+            return Err(Error::UnsupportedTreeVersion(bytes[4]));
+        }
+        let arity = bytes[6] as usize;
+        let rest = &bytes[7..];
+        let params = HashParams::from_id(bytes[5]).ok_or(Error::UnknownHashFunction(bytes[5]))?;
+        if !(2..=params.max_arity()).contains(&arity) {
+            return Err(Error::UnsupportedArity(arity));
+        }
+        let depth = rest[0] as u32;
+        let n = u64::from_le_bytes(rest[1..9].try_into().expect("slice should be 8 bytes"));
+        let n = usize::try_from(n).map_err(|_| Error::BadTreeFormat)?;
+        let m = arity.checked_pow(depth).ok_or(Error::BadTreeFormat)?;
+        // the padding fills at most the last node above the leaves
+        if n == 0 || n > m || (depth > 0 && n <= m / arity) {
+            return Err(Error::BadTreeFormat);
+        }
+
+        let root = read_fr(&rest[9..9 + FR_SIZE])?;
+        let mut chunks = rest[9 + FR_SIZE..].chunks_exact(FR_SIZE);
+        if !chunks.remainder().is_empty() {
+            return Err(Error::BadTreeFormat);
+        }
+        // the levels from the root down to the leaves
+        let mut levels = Vec::with_capacity(depth as usize + 1);
+        for height in (0..=depth).rev() {
+            let width = arity.pow(depth - height);
+            let level = chunks
+                .by_ref()
+                .take(width)
+                .map(read_fr)
+                .collect::<Result<Vec<_>, _>>()?;
+            if level.len() != width {
+                return Err(Error::BadTreeFormat);
+            }
+            levels.push(level);
+        }
+        if chunks.next().is_some() {
+            return Err(Error::BadTreeFormat);
+        }
+        levels.reverse();
+        if levels[depth as usize][0] != root {
+            return Err(Error::RootMismatch);
+        }
+
+        let tree = MerkleTree {
+            arity,
+            params,
+            n,
+            levels,
+        };
+        if check == RootCheck::Recompute {
+            tree.verify()?;
+        }
+        Ok(tree)
+    }
+
+    /// Hashes every internal node again from the leaves, and checks that the tree has the same
+    /// nodes. The padding has to be the empty leaf.
+    pub fn verify(&self) -> Result<(), Error> {
+        let rebuilt = Self::build(
+            &self.levels[0][..self.n],
+            self.arity,
+            self.params,
+            &Poseidon::new(),
+        )?;
+        if rebuilt != *self {
+            return Err(Error::RootMismatch);
+        }
+        Ok(())
+    }
+}
+
+fn read_fr(chunk: &[u8]) -> Result<Fr, Error> {
+    let mut repr = FrRepr::default();
+    repr.read_be(chunk).map_err(|_| Error::BadTreeFormat)?;
+    Fr::from_repr(repr).map_err(|_| Error::BadTreeFormat)
+}
+
+#[cfg(test)]
+mod test {
+    use num::BigUint;
+
+    use super::{RootCheck, FR_SIZE, HEADER_SIZE};
+    use crate::{Error, MerkleTree, MAX_ARITY};
+
+    #[test]
+    fn round_trip() {
+        let leaves: Vec<_> = (1..=5u32).map(BigUint::from).collect();
+        let tree = MerkleTree::from_leaves(&leaves).unwrap();
+        let bytes = tree.to_bytes();
+        assert_eq!(bytes.len(), HEADER_SIZE + 15 * FR_SIZE);
+
+        let loaded = MerkleTree::from_bytes(&bytes, RootCheck::Recompute).unwrap();
+        assert_eq!(loaded, tree);
+        assert_eq!(loaded.leaf_count(), 5);
+        assert_eq!(loaded.root_hash(), tree.root_hash());
+
+        let tree = MerkleTree::from_leaves_with_arity(&leaves, 4).unwrap();
+        let bytes = tree.to_bytes();
+        assert_eq!(bytes.len(), HEADER_SIZE + (1 + 4 + 16) * FR_SIZE);
+        assert_eq!(
+            MerkleTree::from_bytes(&bytes, RootCheck::Recompute).unwrap(),
+            tree
+        );
+    }
+
+    #[test]
+    fn corrupted_rejected() {
+        let leaves: Vec<_> = (1..=3u32).map(BigUint::from).collect();
+        let bytes = MerkleTree::from_leaves(&leaves).unwrap().to_bytes();
+
+        let mut version = bytes.clone();
+        version[4] = 2;
+        assert_eq!(
+            MerkleTree::from_bytes(&version, RootCheck::Header),
+            Err(Error::UnsupportedTreeVersion(2))
+        );
+        let mut arity = bytes.clone();
+        arity[6] = MAX_ARITY as u8 + 1;
+        assert_eq!(
+            MerkleTree::from_bytes(&arity, RootCheck::Header),
+            Err(Error::UnsupportedArity(MAX_ARITY + 1))
+        );
+        assert_eq!(
+            MerkleTree::from_bytes(&bytes[..bytes.len() - 1], RootCheck::Header),
+            Err(Error::BadTreeFormat)
+        );
+
+        // a changed leaf goes unnoticed until the nodes are hashed again
+        let mut leaf = bytes.clone();
+        // the first leaf is the fourth node
+        leaf[HEADER_SIZE + 4 * FR_SIZE - 1] ^= 1;
+        let loaded = MerkleTree::from_bytes(&leaf, RootCheck::Header).unwrap();
+        assert_eq!(loaded.verify(), Err(Error::RootMismatch));
+        assert_eq!(
+            MerkleTree::from_bytes(&leaf, RootCheck::Recompute),
+            Err(Error::RootMismatch)
+        );
+
+        // the root in the header differs from the nodes
+        let mut root = bytes;
+        root[HEADER_SIZE - 1] ^= 1;
+        assert_eq!(
+            MerkleTree::from_bytes(&root, RootCheck::Header),
+            Err(Error::RootMismatch)
+        );
+    }
+}
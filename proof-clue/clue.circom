@@ -1,5 +1,6 @@
 pragma circom 2.1.9;
 include "../circomlib/poseidon.circom";
+include "../proof-common/domain.circom";
 
 template IsZero() {
     signal input in;
@@ -69,12 +70,12 @@ template GetGreens(){
     }
 }
 
-template CheckCommit(){
+template CheckCommit(version){
     signal input word[5];
     signal input salt;
     signal input commit;
 
-    component poseidon = Poseidon(6);
+    component poseidon = TaggedPoseidon(6, version, DOMAIN_COMMITMENT());
     
     for (var i=0; i<5; i++){
         poseidon.inputs[i] <== word[i];
@@ -167,7 +168,8 @@ template ContainsAndDec(){
     contains <== sum[25];
 }
 
-template Clue() {
+// version is the one of the hash parameters, see domain.circom
+template Clue(version) {
     signal input word[5];
     signal input guess[5];
     signal input commit;
@@ -181,7 +183,7 @@ template Clue() {
 
     signal output clue[5];
     
-    component checkCommit = CheckCommit();
+    component checkCommit = CheckCommit(version);
     checkCommit.word <== word;
     checkCommit.salt <== salt;
     checkCommit.commit <== commit;
@@ -228,4 +230,4 @@ template Clue() {
     
 }
 
-component main {public [guess, commit]} = Clue();
\ No newline at end of file
+component main {public [guess, commit]} = Clue(1);
\ No newline at end of file
@@ -0,0 +1,26 @@
+pragma circom 2.1.9;
+include "../circomlib/poseidon.circom";
+
+// Hash parameters, merkle::HashParams in the server. Version 1 hashes the inputs as they are, so
+// the hashes are only told apart by their widths. From version 2 on, every hash takes the domain
+// tag of what it hashes as the first input (merkle::DOMAIN_* in the server).
+function DOMAIN_LEAF() { return 1; }
+function DOMAIN_NODE() { return 2; }
+function DOMAIN_COMMITMENT() { return 3; }
+
+// poseidon of the inputs, preceded by the domain tag from version 2 on
+template TaggedPoseidon(nInputs, version, tag) {
+    signal input inputs[nInputs];
+    signal output out;
+
+    var tagged = version >= 2 ? 1 : 0;
+    component poseidon = Poseidon(nInputs + tagged);
+    if (tagged == 1) {
+        poseidon.inputs[0] <== tag;
+    }
+    for (var i=0; i<nInputs; i++){
+        poseidon.inputs[i + tagged] <== inputs[i];
+    }
+
+    out <== poseidon.out;
+}
@@ -0,0 +1,4 @@
+pragma circom 2.1.9;
+include "freshness_templates.circom";
+
+component main {public [cm, prevCm, active]} = Freshness(48, 1); // FRESHNESS_ROUNDS in the server
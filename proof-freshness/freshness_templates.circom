@@ -0,0 +1,79 @@
+pragma circom 2.1.9;
+include "../circomlib/poseidon.circom";
+include "../proof-common/domain.circom";
+
+template CheckCommitment(version){
+    signal input word[5];
+    signal input salt;
+    signal output cm;
+
+    component poseidon = TaggedPoseidon(6, version, DOMAIN_COMMITMENT());
+
+    for (var i=0; i<5; i++){
+        poseidon.inputs[i] <== word[i];
+    }
+    poseidon.inputs[5] <== salt;
+
+    cm <== poseidon.out;
+}
+
+// word encoded as a single number in base 26, which is injective for the words of the merkle tree
+template Encode() {
+    signal input word[5];
+    signal output out;
+
+    var acc = 0;
+    var base = 1;
+    for (var i=0; i<5; i++){
+        acc += word[i] * base;
+        base *= 26;
+    }
+    out <== acc;
+}
+
+// proves that the word committed in cm is none of the words committed in the active prevCm
+// version is the one of the hash parameters, see domain.circom
+template Freshness(n, version) {
+    // the round's word encoded by a=0, b=1, ..., and its salt
+    signal input word[5];
+    signal input salt;
+    signal input cm;
+
+    // commitments of the previous rounds, active[i] == 0 marks an unused slot
+    // (fewer than n rounds were played)
+    signal input prevCm[n];
+    signal input active[n];
+    signal input prevWord[n][5];
+    signal input prevSalt[n];
+
+    component checkCm = CheckCommitment(version);
+    checkCm.word <== word;
+    checkCm.salt <== salt;
+    checkCm.cm === cm;
+
+    component encode = Encode();
+    encode.word <== word;
+
+    component checkPrevCm[n];
+    component encodePrev[n];
+    signal diff[n];
+    signal inv[n];
+
+    for (var i=0; i<n; i++){
+        active[i] * (1 - active[i]) === 0;
+
+        // the previous word opens its commitment, if the slot is active
+        checkPrevCm[i] = CheckCommitment(version);
+        checkPrevCm[i].word <== prevWord[i];
+        checkPrevCm[i].salt <== prevSalt[i];
+        active[i] * (checkPrevCm[i].cm - prevCm[i]) === 0;
+
+        // and differs from the word, as the difference has an inverse
+        encodePrev[i] = Encode();
+        encodePrev[i].word <== prevWord[i];
+        diff[i] <== encode.out - encodePrev[i].out;
+        // unused slots are padded with any word, their difference needs no inverse
+        inv[i] <-- active[i] != 0 && diff[i] != 0 ? 1 / diff[i] : 0;
+        diff[i] * inv[i] === active[i];
+    }
+}
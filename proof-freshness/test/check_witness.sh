@@ -0,0 +1,20 @@
+#!/bin/sh
+# Compiles a small instance of the freshness circuit, and checks that a witness is generated for
+# the valid_*.json inputs, and not for the invalid_*.json ones, whose constraints don't hold.
+# The commitments in the inputs are version 1 ones, from `merkle::HashParams::V1.commitment`.
+set -e
+cd "$(dirname "$0")"
+mkdir -p build
+circom freshness_test.circom --wasm -o build
+wasm=build/freshness_test_js/freshness_test.wasm
+for input in valid_*.json; do
+    node build/freshness_test_js/generate_witness.js "$wasm" "$input" build/witness.wtns \
+        || { echo "FAIL: $input has no witness"; exit 1; }
+done
+for input in invalid_*.json; do
+    if node build/freshness_test_js/generate_witness.js "$wasm" "$input" build/witness.wtns 2>/dev/null; then
+        echo "FAIL: $input has a witness"
+        exit 1
+    fi
+done
+echo "OK"
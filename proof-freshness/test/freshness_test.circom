@@ -0,0 +1,5 @@
+pragma circom 2.1.9;
+include "../freshness_templates.circom";
+
+// the freshness circuit with 4 slots, enough for the test inputs
+component main {public [cm, prevCm, active]} = Freshness(4, 1);
@@ -0,0 +1,26 @@
+#!/bin/sh
+# Compiles the 4-ary and 8-ary membership circuits, and prints the constraint count and the
+# proving time of each, next to the binary circuit's (compiled and keyed in the repository).
+# The keys made here are only for timing, from a single setup without contributions.
+# Usage: compare_arity.sh <powersOfTau.ptau>, with at least 2^12 powers.
+set -e
+ptau=$(realpath "$1")
+cd "$(dirname "$0")"
+mkdir -p build
+# arity:circuit, the 8-ary one is membership_arity.circom
+circuits="4:membership_arity_4 8:membership_arity"
+for circuit in $circuits; do
+    name=${circuit#*:}
+    circom "$name.circom" --r1cs --wasm -o build
+    snarkjs groth16 setup "build/$name.r1cs" "$ptau" "build/$name.zkey"
+    snarkjs zkey export verificationkey "build/$name.zkey" "build/$name.json"
+done
+
+cd ../server
+cargo run --release --bin tools -- membership bench --arity 2
+for circuit in $circuits; do
+    name=${circuit#*:}
+    build=../proof-membership/build/$name
+    cargo run --release --bin tools -- membership bench --arity "${circuit%%:*}" \
+        --circuit "$build" --zkey "$build.zkey" --verification-key "$build.json"
+done
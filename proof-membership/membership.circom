@@ -1,12 +1,13 @@
 pragma circom 2.1.9;
 include "../circomlib/poseidon.circom";
+include "../proof-common/domain.circom";
 
-template CheckCommitment(){
+template CheckCommitment(version){
     signal input word[5];
     signal input salt;
     signal input cm;
 
-    component poseidon = Poseidon(6);
+    component poseidon = TaggedPoseidon(6, version, DOMAIN_COMMITMENT());
     
     for (var i=0; i<5; i++){
         poseidon.inputs[i] <== word[i];
@@ -25,7 +26,8 @@ template Select() {
     out <== left + (right - left) * selector;
 }
 
-template Membership(numLevels) {
+// version is the one of the hash parameters, see domain.circom
+template Membership(numLevels, version) {
     // the solution word encoded by a=0, b=1, ...
     signal input word[5];
     signal input salt;
@@ -43,7 +45,7 @@ template Membership(numLevels) {
     signal output rootHash;
 
     // check that cm == cm(word, salt)
-    component checkCm = CheckCommitment();
+    component checkCm = CheckCommitment(version);
     checkCm.word <== word;
     checkCm.salt <== salt;
     checkCm.cm <== cm;
@@ -56,7 +58,7 @@ template Membership(numLevels) {
     component select[numLevels];
 
     // compute poseidon of the word
-    poseidon[0] = Poseidon(5);
+    poseidon[0] = TaggedPoseidon(5, version, DOMAIN_LEAF());
     for (var i=0; i<5; i++){
         poseidon[0].inputs[i] <== word[i];
     }
@@ -75,7 +77,7 @@ template Membership(numLevels) {
         poseidon[i].out === select[i].out;
 
         // compute the next poseidon
-        poseidon[i + 1] = Poseidon(2);
+        poseidon[i + 1] = TaggedPoseidon(2, version, DOMAIN_NODE());
         poseidon[i + 1].inputs[0] <== hashes[i][0];
         poseidon[i + 1].inputs[1] <== hashes[i][1];
     }
@@ -84,4 +86,4 @@ template Membership(numLevels) {
     rootHash <== poseidon[numLevels].out;
 }
 
-component main {public [cm]} = Membership(12); // our wordle-merkle has 12 levels edge-wise
\ No newline at end of file
+component main {public [cm]} = Membership(12, 1); // our wordle-merkle has 12 levels edge-wise
\ No newline at end of file
@@ -0,0 +1,5 @@
+pragma circom 2.1.9;
+include "membership_arity_templates.circom";
+
+// 8^4 = 4096 leaves fit the solution words, see the README for the comparison with Membership(12)
+component main {public [cm]} = MembershipArity(4, 8, 1);
@@ -0,0 +1,5 @@
+pragma circom 2.1.9;
+include "membership_arity_templates.circom";
+
+// 4^6 = 4096 leaves, only compiled to compare with the 8-ary circuit, see compare_arity.sh
+component main {public [cm]} = MembershipArity(6, 4, 1);
@@ -0,0 +1,84 @@
+pragma circom 2.1.9;
+include "../circomlib/poseidon.circom";
+include "../proof-common/domain.circom";
+
+template CheckCommitment(version){
+    signal input word[5];
+    signal input salt;
+    signal input cm;
+
+    component poseidon = TaggedPoseidon(6, version, DOMAIN_COMMITMENT());
+
+    for (var i=0; i<5; i++){
+        poseidon.inputs[i] <== word[i];
+    }
+    poseidon.inputs[5] <== salt;
+
+    poseidon.out === cm;
+}
+
+// Membership with a merkle tree in which every node has `arity` children (merkle::MerkleTree
+// with_arity), so the tree is shallower than the binary one, at the cost of a wider poseidon
+// per level. The inputs are named as in Membership, which is the arity 2 case:
+// hashes[i] are all the children of the node on level i, and pathIndicators[i] is the position
+// of the one on the path, from 0 to arity - 1.
+// version is the one of the hash parameters, see domain.circom (from version 2 on, arity is at
+// most 15, as the tag takes one of poseidon's inputs)
+template MembershipArity(numLevels, arity, version) {
+    // the solution word encoded by a=0, b=1, ...
+    signal input word[5];
+    signal input salt;
+    signal input cm;
+
+    signal input hashes[numLevels][arity];
+    signal input pathIndicators[numLevels];
+
+    // root hash of the merkle tree
+    signal output rootHash;
+
+    // check that cm == cm(word, salt)
+    component checkCm = CheckCommitment(version);
+    checkCm.word <== word;
+    checkCm.salt <== salt;
+    checkCm.cm <== cm;
+
+    // poseidon[0] is hash(word), poseidon[i + 1] is hash(hashes[i])
+    component poseidon[numLevels + 1];
+    poseidon[0] = TaggedPoseidon(5, version, DOMAIN_LEAF());
+    for (var i=0; i<5; i++){
+        poseidon[0].inputs[i] <== word[i];
+    }
+
+    // isPosition[i][j] == 1 iff pathIndicators[i] == j
+    signal isPosition[numLevels][arity];
+    // hashes[i][j] if it's on the path, 0 otherwise
+    signal selected[numLevels][arity];
+
+    for (var i = 0; i < numLevels; i++) {
+        var count = 0;
+        var position = 0;
+        var onPath = 0;
+        for (var j = 0; j < arity; j++) {
+            isPosition[i][j] <-- pathIndicators[i] == j ? 1 : 0;
+            isPosition[i][j] * (1 - isPosition[i][j]) === 0;
+            count += isPosition[i][j];
+            position += j * isPosition[i][j];
+
+            selected[i][j] <== isPosition[i][j] * hashes[i][j];
+            onPath += selected[i][j];
+        }
+        // exactly one child is on the path, the one at pathIndicators[i]
+        count === 1;
+        position === pathIndicators[i];
+
+        // and it's the hash computed on the level below
+        poseidon[i].out === onPath;
+
+        poseidon[i + 1] = TaggedPoseidon(arity, version, DOMAIN_NODE());
+        for (var j = 0; j < arity; j++) {
+            poseidon[i + 1].inputs[j] <== hashes[i][j];
+        }
+    }
+
+    rootHash <== poseidon[numLevels].out;
+}
@@ -0,0 +1,114 @@
+//! Offline auditor of game transcripts. Re-verifies every proof in the transcript against the
+//! verification keys and the merkle root of the solution words, and every rejected guess against
+//! the dictionary of all acceptable guesses, without talking to the server.
+//!
+//! Usage: `cargo run --release --bin audit -- <transcript.json>`
+
+use std::{path::Path, process::ExitCode};
+
+use server::{
+    dictionary::Dictionary,
+    transcript::{audit, audit_rejections, Transcript},
+    verifier::{
+        Verifier, CLUE_VERIFICATION_KEY_PATH, FRESHNESS_VERIFICATION_KEY_PATH,
+        MEMBERSHIP_VERIFICATION_KEY_PATH,
+    },
+    word_bank::WordBank,
+};
+
+fn main() -> ExitCode {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: audit <transcript.json>");
+        return ExitCode::FAILURE;
+    };
+
+    let transcript: Transcript = match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(transcript) => transcript,
+        Err(e) => {
+            eprintln!("Failed to read the transcript {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let verifiers = Verifier::from_file(CLUE_VERIFICATION_KEY_PATH).and_then(|clue| {
+        Verifier::from_file(MEMBERSHIP_VERIFICATION_KEY_PATH).map(|membership| (clue, membership))
+    });
+    let (clue_verifier, membership_verifier) = match verifiers {
+        Ok(verifiers) => verifiers,
+        Err(e) => {
+            eprintln!("Failed to load the verification keys: {:?}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // the freshness circuit has no keys until they're generated, the rounds aren't proven fresh then
+    let freshness_verifier = match Path::new(FRESHNESS_VERIFICATION_KEY_PATH).exists() {
+        true => match Verifier::from_file(FRESHNESS_VERIFICATION_KEY_PATH) {
+            Ok(verifier) => Some(verifier),
+            Err(e) => {
+                eprintln!("Failed to load the freshness verification key: {:?}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+        false => None,
+    };
+
+    // the expected root is recomputed from the published word list, not taken from the server
+    let word_bank = match WordBank::new() {
+        Ok(word_bank) => word_bank,
+        Err(e) => {
+            eprintln!("Failed to create the word bank: {:?}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    // a prebuilt tree isn't trusted either
+    if let Err(e) = word_bank.verify_tree() {
+        eprintln!("Failed to verify the merkle tree: {:?}", e);
+        return ExitCode::FAILURE;
+    }
+
+    let dictionary = match Dictionary::new(word_bank.all_words()) {
+        Ok(dictionary) => dictionary,
+        Err(e) => {
+            eprintln!("Failed to create the dictionary: {:?}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let audited = audit(
+        &transcript,
+        &word_bank.root_hash(),
+        word_bank.solution_words(),
+        &clue_verifier,
+        &membership_verifier,
+        freshness_verifier.as_ref(),
+    )
+    .and_then(|report| {
+        audit_rejections(&transcript, &dictionary.root_hash())
+            .map(|rejections| (report, rejections))
+    });
+    match audited {
+        Ok((report, rejections)) => {
+            println!(
+                "OK: round {}, {} guesses verified, {} rejections verified, solved: {}, \
+                 solution revealed: {}, selection verified: {}, scheduled: {}, fresh: {}",
+                report.word_id,
+                report.guesses_verified,
+                rejections,
+                report.solved,
+                report.revealed,
+                report.selection_verified,
+                report.scheduled,
+                report.fresh
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            println!("FAILED: {:?}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,476 @@
+//! Load generator which runs many simulated players against a running server. Each player
+//! starts a game and sends random valid dictionary guesses, over and over, until the time is up.
+//! Latencies, throughput, errors and the server's prover queue depth are written as a JSON report.
+//! Every player has its own session, but they all share the IP of the load generator, so it stops
+//! early when most guesses are rate limited.
+//!
+//! Usage: `cargo run --release --bin loadgen -- --players 16 --duration 60 --output report.json`
+
+use std::{
+    collections::BTreeMap,
+    process::ExitCode,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use parking_lot::Mutex;
+use rand::{seq::SliceRandom, Rng};
+use serde::Serialize;
+use server::{
+    http_service::SESSION_ID_HEADER,
+    request_response::{
+        GuessRequest, GuessResponse, NoSuchWordResponse, StartResponse, StatusResponse,
+    },
+    word_bank::WordBank,
+};
+use tracing::{error, info};
+
+const GUESSES_PER_GAME: usize = 6;
+const STATUS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+// keeps the players from spinning when the server is down
+const ERROR_BACKOFF: Duration = Duration::from_millis(100);
+// guess responses seen before the share of rate limited ones is judged
+const RATE_LIMIT_SAMPLE: usize = 50;
+
+#[derive(Parser, Serialize)]
+struct Args {
+    /// Address of the server
+    #[arg(long, default_value = "http://localhost:4000")]
+    server: String,
+    /// Number of concurrent players
+    #[arg(long, default_value_t = 8)]
+    players: usize,
+    /// How long to generate the load for, in seconds
+    #[arg(long, default_value_t = 60)]
+    duration: u64,
+    /// Request timeout, in seconds
+    #[arg(long, default_value_t = 120)]
+    timeout: u64,
+    /// Where to write the JSON report
+    #[arg(long, default_value = "loadgen_report.json")]
+    output: String,
+    /// Make every guess of the run a different word (until the dictionary runs out), so that no
+    /// clue proof comes from the server's cache and the latencies are of real proofs
+    #[arg(long)]
+    distinct_guesses: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Route {
+    Start,
+    Guess,
+}
+
+/// Everything recorded by the players.
+#[derive(Default)]
+struct Recorder {
+    latencies: BTreeMap<&'static str, Vec<Duration>>,
+    errors: BTreeMap<String, usize>,
+    queue_depths: Vec<usize>,
+    // responses to guesses, and how many of them were 429
+    guess_responses: usize,
+    rate_limited: usize,
+}
+
+#[derive(Serialize)]
+struct Report {
+    config: Args,
+    elapsed_secs: f64,
+    routes: BTreeMap<&'static str, RouteReport>,
+    // successful guesses (that is, clue proofs) per second
+    guess_throughput: f64,
+    errors: BTreeMap<String, usize>,
+    error_rate: f64,
+    queue_depth: QueueDepthReport,
+}
+
+#[derive(Serialize)]
+struct RouteReport {
+    count: usize,
+    p50_ms: f64,
+    p95_ms: f64,
+    p99_ms: f64,
+    max_ms: f64,
+}
+
+#[derive(Serialize)]
+struct QueueDepthReport {
+    samples: usize,
+    mean: f64,
+    max: usize,
+}
+
+impl Route {
+    fn name(self) -> &'static str {
+        match self {
+            Route::Start => "start",
+            Route::Guess => "guess",
+        }
+    }
+}
+
+impl Recorder {
+    fn record(&mut self, route: Route, latency: Duration) {
+        self.latencies
+            .entry(route.name())
+            .or_default()
+            .push(latency);
+    }
+
+    fn record_error(&mut self, route: Route, kind: String) {
+        *self
+            .errors
+            .entry(format!("{}: {}", route.name(), kind))
+            .or_default() += 1;
+    }
+
+    fn record_guess_status(&mut self, status: reqwest::StatusCode) {
+        self.guess_responses += 1;
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            self.rate_limited += 1;
+        }
+    }
+
+    /// Whether more than half of the guesses got 429, once there are enough of them to tell.
+    fn mostly_rate_limited(&self) -> bool {
+        self.guess_responses >= RATE_LIMIT_SAMPLE && self.rate_limited * 2 > self.guess_responses
+    }
+}
+
+struct Player {
+    http: reqwest::Client,
+    server: String,
+    session_id: String,
+    words: Arc<Vec<String>>,
+    // with --distinct-guesses, the players take the shuffled words in turn
+    next_word: Option<Arc<AtomicUsize>>,
+    recorder: Arc<Mutex<Recorder>>,
+}
+
+impl Player {
+    async fn run(self, deadline: Instant) {
+        while Instant::now() < deadline && !self.recorder.lock().mostly_rate_limited() {
+            let Some(word_id) = self.start().await else {
+                tokio::time::sleep(ERROR_BACKOFF).await;
+                continue;
+            };
+            for _ in 0..GUESSES_PER_GAME {
+                if Instant::now() >= deadline || !self.guess(word_id).await {
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn start(&self) -> Option<u32> {
+        let started = Instant::now();
+        let result = async {
+            let response = self
+                .http
+                .get(format!("{}/start", self.server))
+                .send()
+                .await?
+                .error_for_status()?;
+            response.json::<StartResponse>().await
+        }
+        .await;
+
+        match result {
+            Ok(start) => {
+                self.recorder.lock().record(Route::Start, started.elapsed());
+                Some(start.word_id)
+            }
+            Err(e) => {
+                self.recorder
+                    .lock()
+                    .record_error(Route::Start, error_kind(&e));
+                None
+            }
+        }
+    }
+
+    // Returns whether the game can go on.
+    async fn guess(&self, word_id: u32) -> bool {
+        let guess = match &self.next_word {
+            Some(next) => {
+                self.words[next.fetch_add(1, Ordering::Relaxed) % self.words.len()].clone()
+            }
+            None => self
+                .words
+                .choose(&mut rand::thread_rng())
+                .expect("word list should not be empty")
+                .clone(),
+        };
+
+        let started = Instant::now();
+        let response = match self
+            .http
+            .post(format!("{}/guess", self.server))
+            .header(SESSION_ID_HEADER, &self.session_id)
+            .json(&GuessRequest { word_id, guess })
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(e) => {
+                self.recorder
+                    .lock()
+                    .record_error(Route::Guess, error_kind(&e));
+                return false;
+            }
+        };
+
+        let status = response.status();
+        self.recorder.lock().record_guess_status(status);
+        if !status.is_success() {
+            // counted by the status and the error message, e.g. "400 bad word id" after the
+            // round rotated
+            let text = response.text().await.unwrap_or_default();
+            self.recorder.lock().record_error(
+                Route::Guess,
+                format!("{} {}", status.as_u16(), error_message(&text)),
+            );
+            return false;
+        }
+
+        match response.json::<GuessResponse>().await {
+            Ok(guess) => {
+                self.recorder.lock().record(Route::Guess, started.elapsed());
+                guess.colors != [2; 5]
+            }
+            Err(e) => {
+                self.recorder
+                    .lock()
+                    .record_error(Route::Guess, error_kind(&e));
+                false
+            }
+        }
+    }
+}
+
+// error bodies are the message as a JSON string, or an object with the message in `error`, except
+// for the plain text rate limit rejections
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<String>(body)
+        .or_else(|_| serde_json::from_str::<NoSuchWordResponse>(body).map(|r| r.error))
+        .unwrap_or_else(|_| body.to_owned())
+}
+
+fn error_kind(e: &reqwest::Error) -> String {
+    if e.is_timeout() {
+        "timeout".into()
+    } else if e.is_connect() {
+        "connect".into()
+    } else if let Some(status) = e.status() {
+        status.as_u16().to_string()
+    } else if e.is_decode() {
+        "decode".into()
+    } else {
+        "other".into()
+    }
+}
+
+async fn poll_status(
+    http: reqwest::Client,
+    server: String,
+    recorder: Arc<Mutex<Recorder>>,
+    deadline: Instant,
+) {
+    while Instant::now() < deadline {
+        let status = async {
+            http.get(format!("{}/status", server))
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<StatusResponse>()
+                .await
+        }
+        .await;
+        if let Ok(status) = status {
+            recorder.lock().queue_depths.push(status.prover_queue_depth);
+        }
+        tokio::time::sleep(STATUS_POLL_INTERVAL).await;
+    }
+}
+
+/// Nearest-rank percentile of the sorted latencies, in milliseconds.
+fn percentile_ms(sorted: &[Duration], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1].as_secs_f64() * 1000.0
+}
+
+fn build_report(config: Args, recorder: Recorder, elapsed: Duration) -> Report {
+    let mut routes = BTreeMap::new();
+    let mut successes = 0;
+    for (route, mut latencies) in recorder.latencies {
+        latencies.sort();
+        successes += latencies.len();
+        routes.insert(
+            route,
+            RouteReport {
+                count: latencies.len(),
+                p50_ms: percentile_ms(&latencies, 50.0),
+                p95_ms: percentile_ms(&latencies, 95.0),
+                p99_ms: percentile_ms(&latencies, 99.0),
+                max_ms: percentile_ms(&latencies, 100.0),
+            },
+        );
+    }
+
+    let guesses = routes.get(Route::Guess.name()).map_or(0, |r| r.count);
+    let failures: usize = recorder.errors.values().sum();
+    let depths = &recorder.queue_depths;
+
+    Report {
+        config,
+        elapsed_secs: elapsed.as_secs_f64(),
+        routes,
+        guess_throughput: guesses as f64 / elapsed.as_secs_f64(),
+        errors: recorder.errors,
+        error_rate: if successes + failures > 0 {
+            failures as f64 / (successes + failures) as f64
+        } else {
+            0.0
+        },
+        queue_depth: QueueDepthReport {
+            samples: depths.len(),
+            mean: if depths.is_empty() {
+                0.0
+            } else {
+                depths.iter().sum::<usize>() as f64 / depths.len() as f64
+            },
+            max: depths.iter().copied().max().unwrap_or(0),
+        },
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt::init();
+    let args = Args::parse();
+
+    let word_bank = match WordBank::new() {
+        Ok(word_bank) => word_bank,
+        Err(e) => {
+            error!("Failed to create the word bank: {:?}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut words = word_bank.all_words().cloned().collect::<Vec<_>>();
+    let next_word = args.distinct_guesses.then(|| {
+        words.shuffle(&mut rand::thread_rng());
+        Arc::new(AtomicUsize::new(0))
+    });
+    let words = Arc::new(words);
+
+    let http = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(args.timeout))
+        .build()
+    {
+        Ok(http) => http,
+        Err(e) => {
+            error!("Failed to create the HTTP client: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let server: String = args.server.trim_end_matches('/').into();
+    let recorder = Arc::new(Mutex::new(Recorder::default()));
+
+    info!(
+        "Running {} players for {} seconds against {}",
+        args.players, args.duration, server
+    );
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(args.duration);
+
+    let status_handle = tokio::spawn(poll_status(
+        http.clone(),
+        server.clone(),
+        recorder.clone(),
+        deadline,
+    ));
+    // tells this run's sessions apart from the ones of earlier runs, which may still be limited
+    let run_id: u32 = rand::thread_rng().gen();
+    let player_handles: Vec<_> = (0..args.players)
+        .map(|i| {
+            let player = Player {
+                http: http.clone(),
+                server: server.clone(),
+                session_id: format!("loadgen-{:08x}-{}", run_id, i),
+                words: words.clone(),
+                next_word: next_word.clone(),
+                recorder: recorder.clone(),
+            };
+            tokio::spawn(player.run(deadline))
+        })
+        .collect();
+
+    for handle in player_handles {
+        let _ = handle.await;
+    }
+    let _ = status_handle.await;
+    let elapsed = started.elapsed();
+
+    let recorder = std::mem::take(&mut *recorder.lock());
+    if recorder.mostly_rate_limited() {
+        error!(
+            "Stopped early: {} of {} guesses were rate limited (429), so the report would measure \
+             the rate limiter; add the load generator's IP to RATE_LIMIT_ALLOWLIST on the server",
+            recorder.rate_limited, recorder.guess_responses
+        );
+        return ExitCode::FAILURE;
+    }
+    let output = args.output.clone();
+    let report = build_report(args, recorder, elapsed);
+
+    let json = serde_json::to_string_pretty(&report).expect("report should serialize");
+    println!("{}", json);
+    if let Err(e) = std::fs::write(&output, json) {
+        error!("Failed to write the report to {}: {}", output, e);
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{percentile_ms, Recorder, RATE_LIMIT_SAMPLE};
+
+    #[test]
+    fn percentiles_nearest_rank() {
+        let latencies: Vec<_> = (1..=100).map(Duration::from_millis).collect();
+
+        assert_eq!(percentile_ms(&latencies, 50.0), 50.0);
+        assert_eq!(percentile_ms(&latencies, 95.0), 95.0);
+        assert_eq!(percentile_ms(&latencies, 99.0), 99.0);
+        assert_eq!(percentile_ms(&latencies, 100.0), 100.0);
+        assert_eq!(percentile_ms(&[], 50.0), 0.0);
+    }
+    #[test]
+    fn rate_limited_once_most_guesses_are() {
+        let mut recorder = Recorder::default();
+        for _ in 0..RATE_LIMIT_SAMPLE - 1 {
+            recorder.record_guess_status(reqwest::StatusCode::TOO_MANY_REQUESTS);
+        }
+        // too few responses to tell
+        assert!(!recorder.mostly_rate_limited());
+
+        recorder.record_guess_status(reqwest::StatusCode::OK);
+        assert!(recorder.mostly_rate_limited());
+
+        for _ in 0..RATE_LIMIT_SAMPLE {
+            recorder.record_guess_status(reqwest::StatusCode::OK);
+        }
+        assert!(!recorder.mostly_rate_limited());
+    }
+}
@@ -0,0 +1,356 @@
+//! Solver bot which plays against a running server through the HTTP API. Every clue proof is
+//! verified before the clue is used to narrow down the candidates, so running out of candidates
+//! means that the server (or the clue circuit) is inconsistent.
+//!
+//! All games played within one round have the same solution, so the bot plays one game per
+//! round, and waits for the next round before starting another one. With `--mode practice` every
+//! game is a practice session against a random word of its own instead, so that a soak run gets a
+//! solve rate over many words without waiting for the rounds.
+//!
+//! Usage: `cargo run --release --bin solver -- --server http://localhost:4000 --games 10`
+
+use std::{process::ExitCode, str::FromStr, time::Duration};
+
+use ark_bn254::Bn254;
+use ark_groth16::Proof;
+use clap::{Parser, ValueEnum};
+use num_bigint::BigUint;
+use server::{
+    http_service::SESSION_ID_HEADER,
+    request_response::{
+        GuessRequest, GuessResponse, PracticeResponse, PrivateGuessRequest, ProofSerializable,
+        StartResponse, StatusResponse,
+    },
+    solver::{Solver, Strategy},
+    verifier::{
+        clue_public_signals, membership_public_signals, Verifier, CLUE_VERIFICATION_KEY_PATH,
+        MEMBERSHIP_VERIFICATION_KEY_PATH,
+    },
+    word_bank::WordBank,
+};
+use tracing::{error, info, warn};
+
+const MAX_GUESSES: usize = 6;
+const ROUND_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Parser)]
+struct Args {
+    /// Address of the server
+    #[arg(long, default_value = "http://localhost:4000")]
+    server: String,
+    /// Number of games to play, one per round
+    #[arg(long, default_value_t = 1)]
+    games: usize,
+    /// How to pick the guesses
+    #[arg(long, value_enum, default_value_t = StrategyArg::Entropy)]
+    strategy: StrategyArg,
+    /// Which games to play
+    #[arg(long, value_enum, default_value_t = Mode::Round)]
+    mode: Mode,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Mode {
+    /// The round's game, one per round
+    Round,
+    /// A practice session per game, against a random word
+    Practice,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StrategyArg {
+    Entropy,
+    Minimax,
+}
+
+impl From<StrategyArg> for Strategy {
+    fn from(s: StrategyArg) -> Self {
+        match s {
+            StrategyArg::Entropy => Strategy::Entropy,
+            StrategyArg::Minimax => Strategy::Minimax,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum GameError {
+    Http(reqwest::Error),
+    // the server refused the guess, e.g. because the round changed in the meantime
+    Rejected(String),
+    BadResponse,
+    InvalidMembershipProof,
+    InvalidClueProof(String),
+    // no solution word is consistent with the verified clues
+    Inconsistent(Vec<(String, [u8; 5])>),
+}
+
+enum GameResult {
+    Solved(usize),
+    Failed,
+}
+
+/// A game to play: the round's, or a practice session.
+enum Game {
+    Round(StartResponse),
+    Practice(PracticeResponse),
+}
+
+impl Game {
+    fn commitment(&self) -> &str {
+        match self {
+            Game::Round(start) => &start.commitment,
+            Game::Practice(practice) => &practice.commitment,
+        }
+    }
+
+    fn proof(&self) -> &ProofSerializable {
+        match self {
+            Game::Round(start) => &start.proof,
+            Game::Practice(practice) => &practice.proof,
+        }
+    }
+}
+
+struct Bot {
+    http: reqwest::Client,
+    server: String,
+    // sent as the x-session-id header, so that the bot is rate limited as one player
+    session_id: String,
+    root: BigUint,
+    clue_verifier: Verifier,
+    membership_verifier: Verifier,
+    // solver with all words as candidates and its first guess, which is the same for every game
+    initial_solver: Solver,
+    opener: String,
+}
+
+impl Bot {
+    /// Starts a game in the current round, after waiting for the round following `previous`.
+    async fn start(&self, previous: Option<u32>) -> Result<StartResponse, GameError> {
+        loop {
+            let status: StatusResponse = self.get("status").await?;
+            if Some(status.word_id) != previous {
+                let start: StartResponse = self.get("start").await?;
+                if Some(start.word_id) != previous {
+                    return Ok(start);
+                }
+            }
+            info!("Waiting for the round after {}...", status.word_id);
+            tokio::time::sleep(ROUND_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Starts a practice session.
+    async fn start_practice(&self) -> Result<PracticeResponse, GameError> {
+        let response = self
+            .http
+            .post(format!("{}/practice", self.server))
+            .header(SESSION_ID_HEADER, &self.session_id)
+            .send()
+            .await
+            .map_err(GameError::Http)?;
+        if !response.status().is_success() {
+            let text = response.text().await.map_err(GameError::Http)?;
+            return Err(GameError::Rejected(text));
+        }
+        response.json().await.map_err(GameError::Http)
+    }
+
+    async fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, GameError> {
+        self.http
+            .get(format!("{}/{}", self.server, path))
+            .send()
+            .await
+            .map_err(GameError::Http)?
+            .json()
+            .await
+            .map_err(GameError::Http)
+    }
+
+    async fn play(&self, game: Game) -> Result<GameResult, GameError> {
+        let commitment =
+            BigUint::from_str(game.commitment()).map_err(|_| GameError::BadResponse)?;
+        let proof = Proof::<Bn254>::try_from(game.proof()).map_err(|_| GameError::BadResponse)?;
+        let valid = self
+            .membership_verifier
+            .verify(&membership_public_signals(&self.root, &commitment), &proof)
+            .unwrap_or(false);
+        if !valid {
+            return Err(GameError::InvalidMembershipProof);
+        }
+
+        let mut solver = self.initial_solver.clone();
+        let mut history = Vec::new();
+        let mut guess = self.opener.clone();
+
+        for round in 1..=MAX_GUESSES {
+            let request = match &game {
+                Game::Round(start) => {
+                    self.http
+                        .post(format!("{}/guess", self.server))
+                        .json(&GuessRequest {
+                            word_id: start.word_id,
+                            guess: guess.clone(),
+                        })
+                }
+                Game::Practice(practice) => self
+                    .http
+                    .post(format!(
+                        "{}/practice/{}/guess",
+                        self.server, practice.session_id
+                    ))
+                    .json(&PrivateGuessRequest {
+                        guess: guess.clone(),
+                    }),
+            };
+            let response = request
+                .header(SESSION_ID_HEADER, &self.session_id)
+                .send()
+                .await
+                .map_err(GameError::Http)?;
+            if !response.status().is_success() {
+                let text = response.text().await.map_err(GameError::Http)?;
+                return Err(GameError::Rejected(text));
+            }
+            let response: GuessResponse = response.json().await.map_err(GameError::Http)?;
+
+            let proof =
+                Proof::<Bn254>::try_from(&response.proof).map_err(|_| GameError::BadResponse)?;
+            let valid = self
+                .clue_verifier
+                .verify(
+                    &clue_public_signals(&guess, response.colors, &commitment),
+                    &proof,
+                )
+                .unwrap_or(false);
+            if !valid {
+                return Err(GameError::InvalidClueProof(guess));
+            }
+
+            if response.colors == [2; 5] {
+                return Ok(GameResult::Solved(round));
+            }
+
+            solver.apply_clue(&guess, response.colors);
+            history.push((guess, response.colors));
+            guess = match solver.next_guess() {
+                Some(guess) => guess,
+                None => return Err(GameError::Inconsistent(history)),
+            };
+        }
+
+        Ok(GameResult::Failed)
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    tracing_subscriber::fmt::init();
+    let args = Args::parse();
+
+    let word_bank = match WordBank::new() {
+        Ok(word_bank) => word_bank,
+        Err(e) => {
+            error!("Failed to create the word bank: {:?}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let verifiers = Verifier::from_file(CLUE_VERIFICATION_KEY_PATH).and_then(|clue| {
+        Verifier::from_file(MEMBERSHIP_VERIFICATION_KEY_PATH).map(|membership| (clue, membership))
+    });
+    let (clue_verifier, membership_verifier) = match verifiers {
+        Ok(verifiers) => verifiers,
+        Err(e) => {
+            error!("Failed to load the verification keys: {:?}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    info!("Computing the opening guess...");
+    let initial_solver = Solver::new(&word_bank, args.strategy.into());
+    let opener = initial_solver
+        .next_guess()
+        .expect("word bank should not be empty");
+    info!("Opening with {}", opener);
+
+    let bot = Bot {
+        http: reqwest::Client::new(),
+        server: args.server.trim_end_matches('/').into(),
+        session_id: format!("solver-{:08x}", rand::random::<u32>()),
+        root: word_bank.root_hash(),
+        clue_verifier,
+        membership_verifier,
+        initial_solver,
+        opener,
+    };
+
+    let mut solved = 0;
+    let mut total_guesses = 0;
+    let mut failed = 0;
+    let mut errors = 0;
+    let mut inconsistencies = 0;
+
+    let mut previous = None;
+    for game in 0..args.games {
+        let started = match args.mode {
+            Mode::Round => bot.start(previous).await.map(|start| {
+                info!("Game {}: round {}", game, start.word_id);
+                previous = Some(start.word_id);
+                Game::Round(start)
+            }),
+            Mode::Practice => bot.start_practice().await.map(|practice| {
+                info!("Game {}: practice session {}", game, practice.session_id);
+                Game::Practice(practice)
+            }),
+        };
+        let played = match started {
+            Ok(started) => bot.play(started).await,
+            Err(e) => Err(e),
+        };
+        match played {
+            Ok(GameResult::Solved(guesses)) => {
+                info!("Game {}: solved in {} guesses", game, guesses);
+                solved += 1;
+                total_guesses += guesses;
+            }
+            Ok(GameResult::Failed) => {
+                info!("Game {}: not solved in {} guesses", game, MAX_GUESSES);
+                failed += 1;
+            }
+            Err(e @ GameError::Http(_))
+            | Err(e @ GameError::Rejected(_))
+            | Err(e @ GameError::BadResponse) => {
+                warn!("Game {}: aborted: {:?}", game, e);
+                errors += 1;
+            }
+            Err(e) => {
+                error!("Game {}: SERVER INCONSISTENCY: {:?}", game, e);
+                inconsistencies += 1;
+            }
+        }
+    }
+
+    let finished = solved + failed;
+    println!("games played:            {}", args.games);
+    println!("games finished:          {}", finished);
+    if finished > 0 {
+        println!(
+            "solve rate:              {:.2}%",
+            100.0 * solved as f64 / finished as f64
+        );
+    }
+    if solved > 0 {
+        println!(
+            "average guesses:         {:.3}",
+            total_guesses as f64 / solved as f64
+        );
+    }
+    println!("aborted games:           {}", errors);
+    println!("server inconsistencies:  {}", inconsistencies);
+
+    if inconsistencies > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
@@ -0,0 +1,333 @@
+//! Maintenance commands for the data the server loads at startup.
+//!
+//! Usage:
+//! - `cargo run --release --bin tools -- merkle build` builds the merkle tree over the solution
+//!   words the server loads (or the ones given with `--solutions` and `--guesses`), and writes it
+//!   to `../words/trees`, named by the content hash of the words, where `WordBank` looks for it
+//! - `cargo run --release --bin tools -- merkle inspect <file.tree>` prints the header of a tree,
+//!   after hashing it again from the leaves
+//! - `cargo run --release --bin tools -- wordbank check` checks the word lists the server loads
+//!   (or the ones given with `--solutions` and `--guesses`), and lists every offending line
+//! - `cargo run --release --bin tools -- membership bench --arity 8 --circuit
+//!   ../proof-membership/membership_arity` prints the constraint count of a compiled membership
+//!   circuit, and times its proofs over the solution words' tree of that arity
+
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::{Duration, Instant},
+};
+
+use ark_bn254::Bn254;
+use ark_circom::{read_zkey, CircomConfig};
+use clap::{Parser, Subcommand};
+use merkle::{MerkleTree, RootCheck};
+use num_bigint::BigUint;
+use server::{
+    proofs::{generate_membership_proof, HASH_PARAMS},
+    secret::RoundSecret,
+    verifier::{membership_public_signals, Verifier},
+    word_bank::{
+        save_tree, WordSource, WordSources, OTHER_WORDS_PATH, SOLUTION_WORDS_PATH, TREES_PATH,
+    },
+};
+
+#[derive(Parser)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prebuilt merkle trees
+    #[command(subcommand)]
+    Merkle(MerkleCommand),
+    /// Word lists of the word bank
+    #[command(subcommand)]
+    Wordbank(WordbankCommand),
+    /// Membership circuits
+    #[command(subcommand)]
+    Membership(MembershipCommand),
+}
+
+#[derive(Subcommand)]
+enum MerkleCommand {
+    /// Builds the tree over the words of the files
+    /// Builds the tree over the solution words, fails if the lists have offending lines
+    Build {
+        #[command(flatten)]
+        lists: WordListArgs,
+        /// Directory the tree is written to
+        #[arg(long, default_value = TREES_PATH)]
+        output: PathBuf,
+    },
+    /// Checks a tree file and prints its header
+    Inspect { path: PathBuf },
+}
+
+#[derive(Subcommand)]
+enum WordbankCommand {
+    /// Checks the word lists, fails if any line is malformed or repeated, or if a solution is not
+    /// a valid guess
+    Check {
+        #[command(flatten)]
+        lists: WordListArgs,
+    },
+}
+
+/// The word lists, by default the ones the server loads.
+#[derive(clap::Args)]
+struct WordListArgs {
+    /// Solution words, one word per line
+    #[arg(long, default_value = SOLUTION_WORDS_PATH)]
+    solutions: PathBuf,
+    /// Valid guess words, one word per line, the solutions have to be on them too
+    #[arg(long, num_args = 1.., default_values = [SOLUTION_WORDS_PATH, OTHER_WORDS_PATH])]
+    guesses: Vec<PathBuf>,
+}
+
+impl From<WordListArgs> for WordSources {
+    fn from(lists: WordListArgs) -> Self {
+        WordSources {
+            solutions: WordSource::File(lists.solutions),
+            guesses: lists.guesses.into_iter().map(WordSource::File).collect(),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum MembershipCommand {
+    /// Prints the constraint count of a compiled membership circuit, and times its proofs
+    Bench {
+        /// Children of every node of the tree, 2 for the binary `membership` circuit
+        #[arg(long, default_value_t = 2)]
+        arity: usize,
+        /// The compiled circuit, without the extension: `<circuit>.r1cs` and
+        /// `<circuit>_js/<name>.wasm` are loaded
+        #[arg(long, default_value = "../proof-membership/membership")]
+        circuit: PathBuf,
+        /// Proving key of the circuit
+        #[arg(long, default_value = "../keys/membership_final.zkey")]
+        zkey: PathBuf,
+        /// Verification key of the circuit, every proof is checked with it
+        #[arg(long, default_value = "../keys/membership_verification_key.json")]
+        verification_key: String,
+        /// How many proofs are timed
+        #[arg(long, default_value_t = 5)]
+        runs: usize,
+    },
+}
+
+fn main() -> ExitCode {
+    match Args::parse().command {
+        Command::Merkle(MerkleCommand::Build { lists, output }) => build(lists.into(), &output),
+        Command::Merkle(MerkleCommand::Inspect { path }) => inspect(&path),
+        Command::Wordbank(WordbankCommand::Check { lists }) => check(lists.into()),
+        Command::Membership(MembershipCommand::Bench {
+            arity,
+            circuit,
+            zkey,
+            verification_key,
+            runs,
+        }) => bench(arity, &circuit, &zkey, &verification_key, runs),
+    }
+}
+
+/// The normalized solution words, as the server loads them. Fails on any offending line, as the
+/// server refuses to start then.
+fn load_solutions(sources: &WordSources) -> Result<Vec<String>, String> {
+    let lists = sources
+        .load()
+        .map_err(|e| format!("Failed to read the word lists: {:?}", e))?;
+    if !lists.report.is_ok() {
+        return Err(format!("FAILED: {}", lists.report.to_string().trim_end()));
+    }
+    Ok(lists.solutions)
+}
+
+fn build(sources: WordSources, output: &Path) -> ExitCode {
+    let words = match load_solutions(&sources) {
+        Ok(words) => words,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match save_tree(output, &words) {
+        Ok((tree, path)) => {
+            println!(
+                "{}: {} leaves, depth {}, root {}",
+                path.display(),
+                tree.leaf_count(),
+                tree.depth(),
+                tree.root_hash()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Failed to build the tree: {:?}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn inspect(path: &Path) -> ExitCode {
+    let tree = std::fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| {
+            MerkleTree::from_bytes(&bytes, RootCheck::Recompute).map_err(|e| format!("{:?}", e))
+        });
+    match tree {
+        Ok(tree) => {
+            println!(
+                "OK: {} leaves, depth {}, root {}",
+                tree.leaf_count(),
+                tree.depth(),
+                tree.root_hash()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            println!("FAILED: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn check(sources: WordSources) -> ExitCode {
+    match sources.load() {
+        Ok(lists) if lists.report.is_ok() => {
+            print!("OK: {}", lists.report);
+            ExitCode::SUCCESS
+        }
+        Ok(lists) => {
+            print!("FAILED: {}", lists.report);
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("Failed to read the word lists: {:?}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn bench(
+    arity: usize,
+    circuit: &Path,
+    zkey: &Path,
+    verification_key: &str,
+    runs: usize,
+) -> ExitCode {
+    let r1cs = circuit.with_extension("r1cs");
+    match r1cs_constraints(&r1cs) {
+        Ok(constraints) => println!("{}: {} constraints", r1cs.display(), constraints),
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", r1cs.display(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    match time_proofs(arity, circuit, &r1cs, zkey, verification_key, runs) {
+        Ok(mut times) if !times.is_empty() => {
+            times.sort();
+            println!(
+                "arity {}: proving took {:?} (median of {}), from {:?} to {:?}",
+                arity,
+                times[times.len() / 2],
+                times.len(),
+                times[0],
+                times[times.len() - 1]
+            );
+            ExitCode::SUCCESS
+        }
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Failed to time the proofs: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Proves the membership of the first solution word `runs` times, and checks every proof.
+fn time_proofs(
+    arity: usize,
+    circuit: &Path,
+    r1cs: &Path,
+    zkey: &Path,
+    verification_key: &str,
+    runs: usize,
+) -> Result<Vec<Duration>, String> {
+    let words = load_solutions(&WordSources::default())?;
+    let tree =
+        MerkleTree::with_params(&words, arity, HASH_PARAMS).map_err(|e| format!("{:?}", e))?;
+    let path = tree.get_nary_path(0).map_err(|e| format!("{:?}", e))?;
+    // the salt doesn't change the constraints, any one will do
+    let salt = BigUint::from(1u32);
+    let secret = RoundSecret::new(&words[0], &salt).map_err(|e| format!("{:?}", e))?;
+    let commitment = HASH_PARAMS
+        .commitment(&words[0], &salt)
+        .map_err(|e| format!("{:?}", e))?;
+
+    let name = circuit.file_name().unwrap_or_default().to_string_lossy();
+    let wasm = circuit
+        .with_file_name(format!("{}_js", name))
+        .join(format!("{}.wasm", name));
+    let config = CircomConfig::<Bn254>::new(wasm, r1cs).map_err(|e| format!("{:?}", e))?;
+    let mut key_file = File::open(zkey).map_err(|e| e.to_string())?;
+    let (pk, _matrices) = read_zkey(&mut key_file).map_err(|e| e.to_string())?;
+    let verifier = Verifier::from_file(verification_key).map_err(|e| format!("{:?}", e))?;
+    let signals = membership_public_signals(&tree.root_hash(), &commitment);
+
+    let mut times = Vec::with_capacity(runs);
+    for _ in 0..runs {
+        let start = Instant::now();
+        let proof = generate_membership_proof(
+            &secret,
+            commitment.clone(),
+            path.clone(),
+            config.clone(),
+            pk.clone(),
+        )
+        .map_err(|e| format!("{:?}", e))?;
+        times.push(start.elapsed());
+        if !verifier
+            .verify(&signals, &proof)
+            .map_err(|e| format!("{:?}", e))?
+        {
+            return Err("the proof doesn't verify".into());
+        }
+    }
+    Ok(times)
+}
+
+/// Reads the number of constraints from the header section of an `.r1cs` file (iden3's binary
+/// format: sections of a u32 type and a u64 size, the header being type 1).
+fn r1cs_constraints(path: &Path) -> io::Result<u32> {
+    let mut bytes = Vec::new();
+    File::open(path)?.read_to_end(&mut bytes)?;
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "not an r1cs file");
+    let u32_at = |at: usize| -> io::Result<u32> {
+        let slice = bytes.get(at..at + 4).ok_or_else(invalid)?;
+        Ok(u32::from_le_bytes(slice.try_into().unwrap()))
+    };
+    if bytes.get(..4) != Some(b"r1cs".as_slice()) {
+        return Err(invalid());
+    }
+
+    let mut at = 12;
+    for _ in 0..u32_at(8)? {
+        let size = bytes.get(at + 4..at + 12).ok_or_else(invalid)?;
+        let size = u64::from_le_bytes(size.try_into().unwrap()) as usize;
+        if u32_at(at)? == 1 {
+            // field size and prime, then the wire and input counts before the constraint count
+            let field_size = u32_at(at + 12)? as usize;
+            return u32_at(at + 16 + field_size + 24);
+        }
+        at += 12 + size;
+    }
+    Err(invalid())
+}
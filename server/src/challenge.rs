@@ -0,0 +1,139 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ark_bn254::Bn254;
+use ark_groth16::Proof;
+use num_bigint::BigUint;
+use parking_lot::Mutex;
+
+use crate::secret::RoundSecret;
+
+/// A private game against a word chosen by a player. Played like a round of the normal game,
+/// except that it's never revealed, so the creator can't learn the salt.
+#[derive(Clone, Debug)]
+pub struct Challenge {
+    pub secret: Arc<RoundSecret>,
+    pub commitment: BigUint,
+    pub membership_proof: Proof<Bn254>,
+}
+
+struct StoredChallenge {
+    challenge: Challenge,
+    expires_at: Instant,
+}
+
+/// Challenges by their game id. Each challenge expires `ttl` after it's created, and there are
+/// at most `capacity` of them, the ones closest to expiring are dropped first.
+pub struct Challenges {
+    capacity: usize,
+    ttl: Duration,
+    challenges: Mutex<HashMap<String, StoredChallenge>>,
+}
+
+impl Challenges {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Challenges {
+            capacity,
+            ttl,
+            challenges: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Stores the challenge under a fresh random game id, which is returned.
+    pub fn insert(&self, challenge: Challenge) -> String {
+        self.insert_at(challenge, Instant::now())
+    }
+
+    pub fn get(&self, game_id: &str) -> Option<Challenge> {
+        self.get_at(game_id, Instant::now())
+    }
+
+    fn insert_at(&self, challenge: Challenge, now: Instant) -> String {
+        // 128 random bits, so that the game ids can't be guessed
+        let game_id = format!("{:032x}", rand::random::<u128>());
+        let mut challenges = self.challenges.lock();
+
+        challenges.retain(|_, stored| stored.expires_at > now);
+        if challenges.len() >= self.capacity {
+            let oldest = challenges
+                .iter()
+                .min_by_key(|(_, stored)| stored.expires_at)
+                .map(|(game_id, _)| game_id.clone());
+            if let Some(oldest) = oldest {
+                challenges.remove(&oldest);
+            }
+        }
+
+        challenges.insert(
+            game_id.clone(),
+            StoredChallenge {
+                challenge,
+                expires_at: now + self.ttl,
+            },
+        );
+        game_id
+    }
+
+    fn get_at(&self, game_id: &str, now: Instant) -> Option<Challenge> {
+        self.challenges
+            .lock()
+            .get(game_id)
+            .filter(|stored| stored.expires_at > now)
+            .map(|stored| stored.challenge.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use num_bigint::BigUint;
+
+    use super::{Challenge, Challenges};
+    use crate::test_util::dummy_game;
+
+    fn challenge(commitment: u32) -> Challenge {
+        let game = dummy_game(commitment);
+        Challenge {
+            secret: game.secret,
+            commitment: game.commitment,
+            membership_proof: game.membership_proof,
+        }
+    }
+
+    #[test]
+    fn expires_after_ttl() {
+        let challenges = Challenges::new(10, Duration::from_secs(60));
+        let now = Instant::now();
+
+        let game_id = challenges.insert_at(challenge(1), now);
+
+        assert_eq!(game_id.len(), 32);
+        assert!(challenges.get_at(&game_id, now).is_some());
+        assert!(challenges.get_at("unknown", now).is_none());
+        assert!(challenges
+            .get_at(&game_id, now + Duration::from_secs(60))
+            .is_none());
+    }
+
+    #[test]
+    fn oldest_dropped_over_capacity() {
+        let challenges = Challenges::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        let first = challenges.insert_at(challenge(1), now);
+        let second = challenges.insert_at(challenge(2), now + Duration::from_secs(1));
+        let third = challenges.insert_at(challenge(3), now + Duration::from_secs(2));
+
+        let later = now + Duration::from_secs(3);
+        assert!(challenges.get_at(&first, later).is_none());
+        assert_eq!(
+            challenges.get_at(&second, later).unwrap().commitment,
+            BigUint::from(2u32)
+        );
+        assert!(challenges.get_at(&third, later).is_some());
+    }
+}
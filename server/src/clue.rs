@@ -0,0 +1,60 @@
+/// Clue value for a letter which is in the right place.
+pub const GREEN: u8 = 2;
+/// Clue value for a letter which is in the solution, but in a different place.
+pub const YELLOW: u8 = 1;
+/// Clue value for a letter which is not in the solution (or all its occurrences are used up).
+pub const GREY: u8 = 0;
+
+/// Computes the clue for the guess against the solution the same way the clue circuit does.
+/// Greens are marked first, then the remaining letters of the solution are handed out as yellows
+/// to the guess letters from left to right. Both words are expected to be 5 lowercase letters.
+pub fn compute_clue(guess: &str, solution: &str) -> [u8; 5] {
+    let guess = guess.as_bytes();
+    let solution = solution.as_bytes();
+    let mut clue = [GREY; 5];
+
+    // letters of the solution which are not matched by greens
+    let mut counts = [0u8; 26];
+    for i in 0..5 {
+        if guess[i] == solution[i] {
+            clue[i] = GREEN;
+        } else {
+            counts[(solution[i] - 97) as usize] += 1;
+        }
+    }
+
+    for i in 0..5 {
+        let letter = (guess[i] - 97) as usize;
+        if clue[i] != GREEN && counts[letter] > 0 {
+            clue[i] = YELLOW;
+            counts[letter] -= 1;
+        }
+    }
+
+    clue
+}
+
+#[cfg(test)]
+mod test {
+    use super::compute_clue;
+
+    #[test]
+    fn all_green() {
+        assert_eq!(compute_clue("crane", "crane"), [2, 2, 2, 2, 2]);
+    }
+
+    #[test]
+    fn all_grey() {
+        assert_eq!(compute_clue("crane", "bulky"), [0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn repeated_guess_letter_gets_single_yellow() {
+        assert_eq!(compute_clue("speed", "abide"), [0, 0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn green_takes_precedence_over_yellow() {
+        assert_eq!(compute_clue("eerie", "there"), [1, 0, 1, 0, 2]);
+    }
+}
@@ -0,0 +1,237 @@
+use std::{collections::HashSet, env, net::IpAddr, str::FromStr};
+
+use crate::{
+    rate_limit::BucketConfig,
+    secret::StateKey,
+    word_bank::{WordSource, WordSources},
+};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:4000";
+const DEFAULT_AUDIT_LOG_PATH: &str = "audit.log";
+const DEFAULT_PROOF_CACHE_SIZE: usize = 1024;
+const DEFAULT_GRACE_ROUNDS: usize = 1;
+const DEFAULT_GRACE_PERIOD_SECONDS: u64 = 10 * 60;
+const DEFAULT_CHALLENGE_CAPACITY: usize = 10_000;
+const DEFAULT_CHALLENGE_TTL_HOURS: u64 = 7 * 24;
+// five years of hourly rounds
+const DEFAULT_SEED_CHAIN_LENGTH: u32 = 5 * 365 * 24;
+// a week of hourly rounds
+const DEFAULT_SCHEDULE_LENGTH: u32 = 7 * 24;
+const DEFAULT_PRACTICE_POOL_SIZE: usize = 16;
+const DEFAULT_PRACTICE_MAX_SESSIONS: usize = 10_000;
+const DEFAULT_PRACTICE_SESSION_TTL_MINUTES: u64 = 30;
+// a player makes at most six guesses a round, so these only get in the way of scripts
+const DEFAULT_RATE_LIMIT_PER_IP: BucketConfig = BucketConfig {
+    burst: 30,
+    per_minute: 60,
+};
+const DEFAULT_RATE_LIMIT_PER_SESSION: BucketConfig = BucketConfig {
+    burst: 10,
+    per_minute: 20,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    BadValue(&'static str),
+    MissingValue(&'static str),
+}
+
+/// Server configuration, read from the environment variables. Every variable is optional.
+#[derive(Clone, Debug)]
+pub struct Config {
+    // address the HTTP service listens on (SERVER_ADDRESS)
+    pub address: String,
+    // how many clue proofs can be generated at the same time (PROVER_WORKERS),
+    // defaults to the number of available cores
+    pub prover_workers: usize,
+    // file the audit log (round commitments) is appended to (AUDIT_LOG_PATH)
+    pub audit_log_path: String,
+    // file the current round is persisted to, encrypted, so that it survives restarts
+    // (STATE_PATH), not persisted if unset
+    pub state_path: Option<String>,
+    // key encrypting the persisted round, 64 hex characters (STATE_KEY), required with STATE_PATH
+    pub state_key: Option<StateKey>,
+    // token buckets limiting /guess requests of every client IP
+    // (RATE_LIMIT_IP_BURST, RATE_LIMIT_IP_PER_MINUTE)
+    pub rate_limit_per_ip: BucketConfig,
+    // token buckets limiting /guess requests of every session (x-session-id header)
+    // (RATE_LIMIT_SESSION_BURST, RATE_LIMIT_SESSION_PER_MINUTE)
+    pub rate_limit_per_session: BucketConfig,
+    // comma separated IPs which are never rate limited, e.g. internal bots (RATE_LIMIT_ALLOWLIST)
+    pub rate_limit_allowlist: HashSet<IpAddr>,
+    // how many clue proofs of the current round are cached, 0 disables the cache
+    // (PROOF_CACHE_SIZE)
+    pub proof_cache_size: usize,
+    // comma separated guesses proven at the start of every round, e.g. popular openers
+    // (PROOF_CACHE_WARM_UP), none by default
+    pub proof_cache_warm_up: Vec<String>,
+    // how many finished rounds still accept guesses (GRACE_ROUNDS), 0 closes them right away
+    pub grace_rounds: usize,
+    // for how long a finished round still accepts guesses (GRACE_PERIOD_SECONDS)
+    pub grace_period_seconds: u64,
+    // how many challenge games are kept at most (CHALLENGE_CAPACITY)
+    pub challenge_capacity: usize,
+    // for how long a challenge game can be played (CHALLENGE_TTL_HOURS)
+    pub challenge_ttl_hours: u64,
+    // how many rounds a seed chain selects the words of (SEED_CHAIN_LENGTH)
+    pub seed_chain_length: u32,
+    // how many rounds a schedule commits to at once (SCHEDULE_LENGTH)
+    pub schedule_length: u32,
+    // how many practice games are proven ahead of time (PRACTICE_POOL_SIZE)
+    pub practice_pool_size: usize,
+    // how many practice sessions are kept at most (PRACTICE_MAX_SESSIONS)
+    pub practice_max_sessions: usize,
+    // after how long without a guess a practice session expires (PRACTICE_SESSION_TTL_MINUTES)
+    pub practice_session_ttl_minutes: u64,
+    // whether a prebuilt merkle tree of the solution words is checked against the words in the
+    // background after startup (VERIFY_MERKLE_TREE), the server stops if it differs
+    pub verify_merkle_tree: bool,
+    // word lists of the word bank: the solutions (SOLUTION_WORDS_PATH), and the comma separated
+    // lists of valid guesses (GUESS_WORDS_PATHS), by default the lists in `words`, or their copy
+    // compiled into the binary (EMBEDDED_WORDS)
+    pub word_sources: WordSources,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, Error> {
+        let address = env::var("SERVER_ADDRESS").unwrap_or_else(|_| DEFAULT_ADDRESS.into());
+        let prover_workers = match parse_var("PROVER_WORKERS")? {
+            Some(0) => return Err(Error::BadValue("PROVER_WORKERS")),
+            Some(workers) => workers,
+            None => std::thread::available_parallelism().map_or(1, |n| n.get()),
+        };
+
+        let audit_log_path =
+            env::var("AUDIT_LOG_PATH").unwrap_or_else(|_| DEFAULT_AUDIT_LOG_PATH.into());
+
+        let state_path = env::var("STATE_PATH").ok();
+        let state_key = match env::var("STATE_KEY") {
+            Ok(key) => Some(StateKey::from_hex(&key).map_err(|_| Error::BadValue("STATE_KEY"))?),
+            Err(_) => None,
+        };
+        if state_path.is_some() && state_key.is_none() {
+            return Err(Error::MissingValue("STATE_KEY"));
+        }
+
+        let rate_limit_per_ip = BucketConfig {
+            burst: parse_var("RATE_LIMIT_IP_BURST")?.unwrap_or(DEFAULT_RATE_LIMIT_PER_IP.burst),
+            per_minute: parse_var("RATE_LIMIT_IP_PER_MINUTE")?
+                .unwrap_or(DEFAULT_RATE_LIMIT_PER_IP.per_minute),
+        };
+        let rate_limit_per_session = BucketConfig {
+            burst: parse_var("RATE_LIMIT_SESSION_BURST")?
+                .unwrap_or(DEFAULT_RATE_LIMIT_PER_SESSION.burst),
+            per_minute: parse_var("RATE_LIMIT_SESSION_PER_MINUTE")?
+                .unwrap_or(DEFAULT_RATE_LIMIT_PER_SESSION.per_minute),
+        };
+        let rate_limit_allowlist = match env::var("RATE_LIMIT_ALLOWLIST") {
+            Ok(ips) => ips
+                .split(',')
+                .map(str::trim)
+                .filter(|ip| !ip.is_empty())
+                .map(|ip| ip.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| Error::BadValue("RATE_LIMIT_ALLOWLIST"))?,
+            Err(_) => HashSet::new(),
+        };
+
+        let proof_cache_size = parse_var("PROOF_CACHE_SIZE")?.unwrap_or(DEFAULT_PROOF_CACHE_SIZE);
+        let proof_cache_warm_up = env::var("PROOF_CACHE_WARM_UP")
+            .map(|words| {
+                words
+                    .split(',')
+                    .map(|word| word.trim().to_lowercase())
+                    .filter(|word| !word.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let grace_rounds = parse_var("GRACE_ROUNDS")?.unwrap_or(DEFAULT_GRACE_ROUNDS);
+        let grace_period_seconds =
+            parse_var("GRACE_PERIOD_SECONDS")?.unwrap_or(DEFAULT_GRACE_PERIOD_SECONDS);
+
+        let challenge_capacity = match parse_var("CHALLENGE_CAPACITY")? {
+            Some(0) => return Err(Error::BadValue("CHALLENGE_CAPACITY")),
+            Some(capacity) => capacity,
+            None => DEFAULT_CHALLENGE_CAPACITY,
+        };
+        let challenge_ttl_hours =
+            parse_var("CHALLENGE_TTL_HOURS")?.unwrap_or(DEFAULT_CHALLENGE_TTL_HOURS);
+
+        let seed_chain_length = match parse_var("SEED_CHAIN_LENGTH")? {
+            Some(0) => return Err(Error::BadValue("SEED_CHAIN_LENGTH")),
+            Some(length) => length,
+            None => DEFAULT_SEED_CHAIN_LENGTH,
+        };
+        // a schedule has to fit in a single seed chain
+        let schedule_length = match parse_var("SCHEDULE_LENGTH")? {
+            Some(length) if length == 0 || length > seed_chain_length => {
+                return Err(Error::BadValue("SCHEDULE_LENGTH"))
+            }
+            Some(length) => length,
+            None if DEFAULT_SCHEDULE_LENGTH > seed_chain_length => seed_chain_length,
+            None => DEFAULT_SCHEDULE_LENGTH,
+        };
+        let practice_pool_size =
+            parse_var("PRACTICE_POOL_SIZE")?.unwrap_or(DEFAULT_PRACTICE_POOL_SIZE);
+        let practice_max_sessions = match parse_var("PRACTICE_MAX_SESSIONS")? {
+            Some(0) => return Err(Error::BadValue("PRACTICE_MAX_SESSIONS")),
+            Some(max_sessions) => max_sessions,
+            None => DEFAULT_PRACTICE_MAX_SESSIONS,
+        };
+        let practice_session_ttl_minutes = parse_var("PRACTICE_SESSION_TTL_MINUTES")?
+            .unwrap_or(DEFAULT_PRACTICE_SESSION_TTL_MINUTES);
+        let verify_merkle_tree = parse_var("VERIFY_MERKLE_TREE")?.unwrap_or(true);
+
+        let mut word_sources = if parse_var("EMBEDDED_WORDS")?.unwrap_or(false) {
+            WordSources::embedded()
+        } else {
+            WordSources::default()
+        };
+        if let Ok(path) = env::var("SOLUTION_WORDS_PATH") {
+            word_sources.solutions = WordSource::File(path.into());
+        }
+        if let Ok(paths) = env::var("GUESS_WORDS_PATHS") {
+            word_sources.guesses = paths
+                .split(',')
+                .map(str::trim)
+                .filter(|path| !path.is_empty())
+                .map(|path| WordSource::File(path.into()))
+                .collect();
+            if word_sources.guesses.is_empty() {
+                return Err(Error::BadValue("GUESS_WORDS_PATHS"));
+            }
+        }
+
+        Ok(Config {
+            address,
+            prover_workers,
+            audit_log_path,
+            state_path,
+            state_key,
+            rate_limit_per_ip,
+            rate_limit_per_session,
+            rate_limit_allowlist,
+            proof_cache_size,
+            proof_cache_warm_up,
+            grace_rounds,
+            grace_period_seconds,
+            challenge_capacity,
+            challenge_ttl_hours,
+            seed_chain_length,
+            schedule_length,
+            practice_pool_size,
+            practice_max_sessions,
+            practice_session_ttl_minutes,
+            verify_merkle_tree,
+            word_sources,
+        })
+    }
+}
+
+fn parse_var<T: FromStr>(name: &'static str) -> Result<Option<T>, Error> {
+    match env::var(name) {
+        Ok(value) => value.parse().map(Some).map_err(|_| Error::BadValue(name)),
+        Err(_) => Ok(None),
+    }
+}
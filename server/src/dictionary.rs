@@ -0,0 +1,94 @@
+use merkle::{NonMembershipWitness, SortedMerkleTree};
+use num_bigint::BigUint;
+
+use crate::{proofs::HASH_PARAMS, word_bank::is_word_ok};
+
+#[derive(Debug)]
+pub enum Error {
+    MerkleCreateFail(merkle::Error),
+}
+
+/// Sorted merkle tree over the hashes of all acceptable guess words, the solution words and the
+/// other valid ones. Its root is published, so that a rejected guess comes with the proof that
+/// the word is not in the tree, and a client can check its guesses against the same tree.
+pub struct Dictionary {
+    tree: SortedMerkleTree,
+}
+
+impl Dictionary {
+    pub fn new<'a>(words: impl Iterator<Item = &'a String>) -> Result<Self, Error> {
+        let words: Vec<String> = words.cloned().collect();
+        let tree = SortedMerkleTree::new(&words, HASH_PARAMS).map_err(Error::MerkleCreateFail)?;
+        Ok(Dictionary { tree })
+    }
+
+    pub fn root_hash(&self) -> BigUint {
+        self.tree.root_hash()
+    }
+
+    /// Number of words in the tree.
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    /// Whether the word is in the tree.
+    pub fn contains(&self, word: &str) -> bool {
+        leaf(word).is_some_and(|leaf| self.tree.contains(&leaf))
+    }
+
+    /// Proof that the word is not in the tree. `None` if it is, or if it's not a word at all
+    /// (not 5 lowercase letters), as it can't be hashed then.
+    pub fn absence_proof(&self, word: &str) -> Option<NonMembershipWitness> {
+        self.tree.non_membership_witness(&leaf(word)?).ok()
+    }
+}
+
+/// Checks the proof that the word is not in the dictionary with the root.
+pub fn verify_absence(word: &str, proof: &NonMembershipWitness, root: &BigUint) -> bool {
+    leaf(word).is_some_and(|leaf| proof.verify(&leaf, root, HASH_PARAMS).unwrap_or(false))
+}
+
+// the leaf of the word, if it's well-formed
+fn leaf(word: &str) -> Option<BigUint> {
+    if !is_word_ok(word) {
+        return None;
+    }
+    HASH_PARAMS.word_leaf(word).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        dictionary::{verify_absence, Dictionary},
+        word_bank::WordBank,
+    };
+
+    #[test]
+    fn rejections_proven() {
+        let word_bank = WordBank::new().expect("word bank should load");
+        let dictionary = Dictionary::new(word_bank.all_words()).unwrap();
+        let root = dictionary.root_hash();
+
+        // published in the README, changes only with the word lists
+        assert_eq!(dictionary.len(), 12972);
+        assert_eq!(
+            root.to_string(),
+            "5218044997700184954803699218973511299292424004075201938258657522667259364369"
+        );
+
+        assert!(dictionary.contains("aahed") && dictionary.contains("crane"));
+        assert!(dictionary.absence_proof("crane").is_none());
+        assert!(dictionary.absence_proof("CRANE").is_none());
+
+        let proof = dictionary
+            .absence_proof("zzzzz")
+            .expect("zzzzz is not a word");
+        assert!(verify_absence("zzzzz", &proof, &root));
+        // the proof of one word doesn't show another one is missing
+        assert!(!verify_absence("crane", &proof, &root));
+    }
+}
@@ -1,42 +1,272 @@
 use ark_bn254::Bn254;
 use ark_circom::{read_zkey, CircomConfig};
 use ark_groth16::{Proof, ProvingKey};
-use log::{error, info};
-use merkle::hash_word_with_salt;
-use num_bigint::{BigUint, RandomBits};
+use merkle::MerklePathEntry;
+use num_bigint::BigUint;
 use parking_lot::RwLock;
-use rand::{thread_rng, Rng};
-use std::{io, sync::Arc, time::Duration};
+use std::{
+    collections::VecDeque,
+    io,
+    path::Path,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::broadcast;
+use tracing::{error, info, info_span, warn, Instrument};
+use zeroize::Zeroize;
 
 use crate::{
-    proofs::generate_membership_proof,
+    challenge::{Challenge, Challenges},
+    config::Config,
+    dictionary::{self, Dictionary},
+    grace::GraceRounds,
+    history::{FreshnessProof, RoundHistory, UsedWord, FRESHNESS_ROUNDS},
+    logging::AUDIT_TARGET,
+    metrics::Metrics,
+    practice::PracticeGames,
+    proof_cache::ProofCache,
+    proofs::{
+        self, generate_clue_proof, generate_freshness_proof, generate_membership_proof,
+        ProofTimings, HASH_PARAMS,
+    },
+    prover_pool::{self, ProverPool},
+    rate_limit::{InMemoryStore, RateLimiter},
+    round_store::{self, PersistedState, RoundStore},
+    schedule::{self, RoundPlanner, ScheduleProof},
+    secret::{self, RoundSecret},
+    seed_chain::SeedAnchor,
+    verifier::{
+        self, clue_public_signals, freshness_public_signals, membership_public_signals, Verifier,
+        CLUE_VERIFICATION_KEY_PATH, FRESHNESS_VERIFICATION_KEY_PATH,
+        MEMBERSHIP_VERIFICATION_KEY_PATH,
+    },
     word_bank::{self, PickWordResult, WordBank},
 };
 
 const SLEEP_DURATION: Duration = Duration::from_secs(60 * 60);
+// how many finished rounds are kept for revealing their solutions (a month of hourly rounds)
+const REVEAL_HISTORY_LEN: usize = 24 * 30;
+// how many round events a slow subscriber can fall behind before it starts missing them
+const ROUND_EVENTS_CAPACITY: usize = 16;
+// the freshness circuit, optional until its keys are generated
+const FRESHNESS_KEY_PATH: &str = "../keys/freshness_final.zkey";
+const FRESHNESS_WASM_PATH: &str = "../proof-freshness/freshness_js/freshness.wasm";
+const FRESHNESS_R1CS_PATH: &str = "../proof-freshness/freshness.r1cs";
 
 #[derive(Debug)]
 pub enum Error {
     WordBankCreate(word_bank::Error),
+    DictionaryCreate(dictionary::Error),
     FileRead(io::Error),
     ZKeyRead,
     MembershipConfigCreate,
     ClueConfigCreate,
+    FreshnessConfigCreate,
+    VerifierCreate(verifier::Error),
+    MetricsCreate(prometheus::Error),
     CreateGame(CreateGameError),
+    RoundStoreLoad(round_store::Error),
+    Schedule(schedule::Error),
+    // the persisted round's membership proof doesn't verify against the current word bank
+    RestoredRoundInvalid,
 }
 
 #[derive(Debug)]
 pub enum CreateGameError {
+    Pool(prover_pool::Error),
     Merkle(merkle::Error),
+    Secret(secret::Error),
+    Schedule(schedule::Error),
     Prove,
+    Verify,
+    // the freshness proof doesn't verify
+    VerifyFreshness,
+    // the commitment is not the one in the published schedule
+    NotScheduled,
+}
+
+#[derive(Debug)]
+pub enum ClueProofError {
+    Pool(prover_pool::Error),
+    Prove(proofs::Error),
+    // the generated proof doesn't verify
+    Verify,
 }
 
 /// All data shared by the game service and HTTP service request handlers.
 pub struct SharedState {
     // this state changes per each round
     pub mutable_game_state: RwLock<MutableState>,
+    // finished rounds which still accept guesses
+    pub grace_rounds: RwLock<GraceRounds>,
+    // solutions and salts of the closed rounds, oldest first
+    pub revealed_rounds: RwLock<VecDeque<RoundReveal>>,
+    // clue proofs of the current round, by guess
+    pub proof_cache: ProofCache,
+    // private games against the words chosen by players
+    pub challenges: Challenges,
+    // private games against random words, one per practice session
+    pub practice_games: PracticeGames,
+    // published on every round rotation, and whenever a round is closed
+    pub round_events: broadcast::Sender<GameEvent>,
+    // unix time of the next planned rotation
+    pub next_rotation_timestamp: AtomicI64,
     // this state is fixed for the whole lifetime of the server
     pub immutable_state: ImmutableState,
+    pub metrics: Metrics,
+}
+
+impl SharedState {
+    /// The round which guesses for `word_id` are played against: the current one, or a finished
+    /// one which is still in its grace period.
+    pub fn playable_round(&self, word_id: u32) -> Option<MutableState> {
+        let current = self.mutable_game_state.read();
+        if current.word_id == word_id {
+            return Some(current.clone());
+        }
+        drop(current);
+        self.grace_rounds.read().get(word_id).cloned()
+    }
+
+    /// Responds with the clue for the guess in the given round, and its proof. The proof comes from
+    /// the cache if the guess was already proven this round.
+    pub async fn clue_proof(
+        self: &Arc<Self>,
+        game_state: &MutableState,
+        guess: &str,
+    ) -> Result<([u8; 5], Proof<Bn254>), ClueProofError> {
+        if let Some(cached) = self.proof_cache.get(game_state.word_id, guess) {
+            self.metrics.proof_cache_hits.inc();
+            return Ok(cached);
+        }
+        self.metrics.proof_cache_misses.inc();
+
+        let (clue, proof) = self
+            .prove_clue(&game_state.secret, &game_state.commitment, guess)
+            .await?;
+        self.proof_cache
+            .insert(game_state.word_id, guess, clue, proof.clone());
+        Ok((clue, proof))
+    }
+
+    /// Proves the clue for the guess against the secret on the prover pool, and verifies the
+    /// proof against the commitment.
+    pub async fn prove_clue(
+        self: &Arc<Self>,
+        secret: &Arc<RoundSecret>,
+        commitment: &BigUint,
+        guess: &str,
+    ) -> Result<([u8; 5], Proof<Bn254>), ClueProofError> {
+        // proving is CPU heavy, so it's done on the prover pool instead of the async runtime
+        let state = self.clone();
+        let secret = secret.clone();
+        let job_commitment = commitment.clone();
+        let job_guess = guess.to_owned();
+        let (proof, clue, timings) = self
+            .immutable_state
+            .prover_pool
+            .run(move || {
+                generate_clue_proof(
+                    job_guess,
+                    &secret,
+                    job_commitment,
+                    state.immutable_state.clue_config.clone(),
+                    state.immutable_state.clue_pk.clone(),
+                )
+            })
+            .await
+            .map_err(ClueProofError::Pool)?
+            .map_err(ClueProofError::Prove)?;
+        self.observe_proof_timings(timings);
+
+        if !self.is_clue_proof_valid(guess, clue, commitment, &proof) {
+            return Err(ClueProofError::Verify);
+        }
+        Ok((clue, proof))
+    }
+
+    /// Creates a game for the picked word outside of the rounds, proving its membership on the
+    /// prover pool. Such games are not rounds, so their word id is never used.
+    async fn create_private_game(
+        self: &Arc<Self>,
+        picked: PickWordResult,
+    ) -> Result<MutableState, CreateGameError> {
+        let state = self.clone();
+        self.immutable_state
+            .prover_pool
+            .run(move || {
+                let planned = PlannedGame::random(picked).map_err(CreateGameError::Secret)?;
+                create_game(&state.immutable_state, 0, planned)
+            })
+            .await
+            .map_err(CreateGameError::Pool)?
+    }
+
+    /// Creates a challenge game for the picked word. Returns the game id under which the
+    /// challenge is stored.
+    pub async fn create_challenge(
+        self: &Arc<Self>,
+        picked: PickWordResult,
+    ) -> Result<String, CreateGameError> {
+        let game_state = self.create_private_game(picked).await?;
+        Ok(self.challenges.insert(Challenge {
+            secret: game_state.secret,
+            commitment: game_state.commitment,
+            membership_proof: game_state.membership_proof,
+        }))
+    }
+
+    /// Starts a practice session against a random word. The game comes from the pool of ready
+    /// games, or is proven right away when the pool is empty. Returns the session id and the game.
+    pub async fn start_practice(
+        self: &Arc<Self>,
+    ) -> Result<(String, MutableState), CreateGameError> {
+        let game_state = match self.practice_games.take_ready() {
+            Some(game_state) => game_state,
+            None => {
+                self.metrics.practice_pool_misses.inc();
+                let picked = self.immutable_state.word_bank.pick_word();
+                self.create_private_game(picked).await?
+            }
+        };
+        let session_id = self.practice_games.start(game_state.clone());
+        Ok((session_id, game_state))
+    }
+
+    fn observe_proof_timings(&self, timings: ProofTimings) {
+        self.metrics
+            .witness_generation_seconds
+            .observe(timings.witness.as_secs_f64());
+        self.metrics
+            .proving_seconds
+            .observe(timings.proving.as_secs_f64());
+    }
+
+    // Checks the proof before it's sent, so that a broken prover setup shows up in the metrics
+    // instead of in the players' browsers.
+    fn is_clue_proof_valid(
+        &self,
+        guess: &str,
+        clue: [u8; 5],
+        commitment: &BigUint,
+        proof: &Proof<Bn254>,
+    ) -> bool {
+        let valid = self
+            .immutable_state
+            .clue_verifier
+            .verify(&clue_public_signals(guess, clue, commitment), proof)
+            .unwrap_or(false);
+        if !valid {
+            self.metrics
+                .proof_verification_failures
+                .with_label_values(&["clue"])
+                .inc();
+        }
+        valid
+    }
 }
 
 /// Contains the gameplay-related data that changes every fixed period of time, including
@@ -46,14 +276,54 @@ pub struct SharedState {
 pub struct MutableState {
     // Describes the current round of the game. Goes like 0, 1, 2, ..., per each update
     pub word_id: u32,
-    // The secret solution word
-    pub solution: String,
-    // Random salt, sampled every round
-    pub salt: BigUint,
+    // The secret solution word and the random salt, sampled every round
+    pub secret: Arc<RoundSecret>,
     // cm(solution, salt)
     pub commitment: BigUint,
     // Ready membership proof for the current word
     pub membership_proof: Proof<Bn254>,
+    // The seed chain which selected the word, for rounds (not for the private games)
+    pub seed_anchor: Option<SeedAnchor>,
+    // Where the commitment is in the published schedule, for rounds
+    pub schedule_proof: Option<ScheduleProof>,
+    // Shows that the word differs from the words of the previous rounds, for rounds, once the
+    // freshness circuit has its keys
+    pub freshness_proof: Option<FreshnessProof>,
+    // The previous rounds the word differs from, oldest first, for rounds. Opened with the round
+    pub previous: Arc<[UsedWord]>,
+}
+
+/// Opening of the commitment of a finished round. Published so that players can check the
+/// clues they got against the actual solution.
+#[derive(Clone, Debug)]
+pub struct RoundReveal {
+    pub word_id: u32,
+    pub solution: String,
+    pub salt: BigUint,
+    pub selection_seed: Option<[u8; 32]>,
+    pub selection_attempt: u32,
+    // the previous rounds, whose words the selection skipped, and the freshness proof is against
+    pub previous: Arc<[UsedWord]>,
+}
+
+/// Changes of the game which are pushed to the clients.
+#[derive(Clone, Debug)]
+pub enum GameEvent {
+    RoundStarted(Box<RoundEvent>),
+    // the round no longer accepts guesses, and its commitment is opened
+    RoundClosed(RoundReveal),
+}
+
+/// Public parts of a new round.
+#[derive(Clone, Debug)]
+pub struct RoundEvent {
+    pub word_id: u32,
+    pub commitment: BigUint,
+    pub membership_proof: Proof<Bn254>,
+    pub seed_anchor: Option<SeedAnchor>,
+    pub schedule_proof: Option<ScheduleProof>,
+    pub freshness_proof: Option<FreshnessProof>,
+    pub next_rotation_timestamp: i64,
 }
 
 /// Contains the shared state that doesn't change, and allows for handling requests properly
@@ -61,21 +331,68 @@ pub struct MutableState {
 pub struct ImmutableState {
     pub clue_config: CircomConfig<Bn254>,
     pub clue_pk: ProvingKey<Bn254>,
+    // every clue proof is verified before it's sent, to catch a broken prover setup
+    pub clue_verifier: Verifier,
+    pub membership_config: CircomConfig<Bn254>,
+    pub membership_pk: ProvingKey<Bn254>,
+    pub membership_verifier: Verifier,
+    pub freshness: Option<FreshnessKeys>,
     pub word_bank: WordBank, // not clonable
+    // tree over all acceptable guesses, rejected guesses are proven absent from it
+    pub dictionary: Dictionary,
+    pub prover_pool: ProverPool,
+    // limits the rate of guesses, as each costs a proof
+    pub rate_limiter: RateLimiter,
+}
+
+/// Proving and verification keys of the freshness circuit.
+pub struct FreshnessKeys {
+    pub config: CircomConfig<Bn254>,
+    pub pk: ProvingKey<Bn254>,
+    pub verifier: Verifier,
+}
+
+impl FreshnessKeys {
+    /// Loads the keys, or returns `None` if they haven't been generated yet.
+    fn load() -> Result<Option<Self>, Error> {
+        if !Path::new(FRESHNESS_KEY_PATH).exists() {
+            return Ok(None);
+        }
+        let mut key_file = std::fs::File::open(FRESHNESS_KEY_PATH).map_err(Error::FileRead)?;
+        let (pk, _matrices) = read_zkey(&mut key_file).map_err(|_| Error::ZKeyRead)?;
+        let config = CircomConfig::<Bn254>::new(FRESHNESS_WASM_PATH, FRESHNESS_R1CS_PATH)
+            .map_err(|_| Error::FreshnessConfigCreate)?;
+        let verifier =
+            Verifier::from_file(FRESHNESS_VERIFICATION_KEY_PATH).map_err(Error::VerifierCreate)?;
+        Ok(Some(FreshnessKeys {
+            config,
+            pk,
+            verifier,
+        }))
+    }
 }
 
 /// Service which holds the shared state, and updates it every fixed period of time.
 pub struct GameStateService {
     shared_state: Arc<SharedState>,
-    membership_config: CircomConfig<Bn254>,
-    membership_pk: ProvingKey<Bn254>,
+    // keeps the current round across restarts, if configured
+    round_store: Option<RoundStore>,
+    // selects the words of the rounds, and commits to them in advance
+    planner: RoundPlanner,
+    // the recent rounds, whose words aren't selected again
+    history: RoundHistory,
+    // guesses proven in the background at the start of every round, so that they hit the cache
+    warm_up_words: Vec<String>,
 }
 
 impl GameStateService {
     /// Create the service. Creates the word bank, loads the proving keys, and creates the initial shared state.
-    pub fn new() -> Result<Self, Error> {
+    pub fn new(config: &Config) -> Result<Self, Error> {
         info!("Creating word bank...");
-        let word_bank = WordBank::new().map_err(Error::WordBankCreate)?;
+        let word_bank =
+            WordBank::from_sources(&config.word_sources).map_err(Error::WordBankCreate)?;
+        info!("Creating dictionary tree...");
+        let dictionary = Dictionary::new(word_bank.all_words()).map_err(Error::DictionaryCreate)?;
 
         info!("Setting circom configs and proving keys (this may take a while)...");
         let mut key_file =
@@ -94,28 +411,160 @@ impl GameStateService {
             "../proof-clue/clue.r1cs",
         )
         .map_err(|_| Error::ClueConfigCreate)?;
+        let clue_verifier =
+            Verifier::from_file(CLUE_VERIFICATION_KEY_PATH).map_err(Error::VerifierCreate)?;
+        let membership_verifier =
+            Verifier::from_file(MEMBERSHIP_VERIFICATION_KEY_PATH).map_err(Error::VerifierCreate)?;
+        let freshness = FreshnessKeys::load()?;
+        if freshness.is_none() {
+            warn!("No keys of the freshness circuit, the rounds are not proven fresh");
+        }
+        let metrics = Metrics::new().map_err(Error::MetricsCreate)?;
+        let immutable_state = ImmutableState {
+            clue_config,
+            clue_pk,
+            clue_verifier,
+            membership_config,
+            membership_pk,
+            membership_verifier,
+            freshness,
+            word_bank,
+            dictionary,
+            prover_pool: ProverPool::new(config.prover_workers),
+            rate_limiter: RateLimiter::new(
+                config.rate_limit_per_ip,
+                config.rate_limit_per_session,
+                config.rate_limit_allowlist.clone(),
+                Box::<InMemoryStore>::default(),
+            ),
+        };
+        let word_bank = &immutable_state.word_bank;
 
-        info!("Creating initial game state...");
-        let game_state = create_game(
-            &word_bank,
-            0,
-            membership_config.clone(),
-            membership_pk.clone(),
-        )
-        .map_err(Error::CreateGame)?;
+        let round_store = match (&config.state_path, &config.state_key) {
+            (Some(path), Some(key)) => Some(RoundStore::new(path, key)),
+            _ => None,
+        };
+        let restored = match &round_store {
+            Some(store) => store.load().map_err(Error::RoundStoreLoad)?,
+            None => None,
+        };
+
+        let (game_state, planner, history) = match restored {
+            Some(PersistedState {
+                mut game_state,
+                seed_chain,
+                schedule_plan,
+                history,
+            }) => {
+                info!(
+                    word_id = game_state.word_id,
+                    "Restoring persisted game state..."
+                );
+                let signals =
+                    membership_public_signals(&word_bank.root_hash(), &game_state.commitment);
+                if !immutable_state
+                    .membership_verifier
+                    .verify(&signals, &game_state.membership_proof)
+                    .unwrap_or(false)
+                {
+                    return Err(Error::RestoredRoundInvalid);
+                }
+                // rounds persisted before there was a history only remember themselves
+                let mut history = RoundHistory::new(history);
+                history.push(used_word(&game_state));
+                // the round was planned with the persisted schedule, rounds persisted before
+                // there was one are followed by a new schedule
+                let first_word_id = match schedule_plan {
+                    Some(_) => game_state.word_id,
+                    None => game_state.word_id + 1,
+                };
+                let planner = RoundPlanner::new(
+                    word_bank,
+                    first_word_id,
+                    seed_chain,
+                    schedule_plan,
+                    &history,
+                    config.seed_chain_length,
+                    config.schedule_length,
+                )
+                .map_err(Error::Schedule)?;
+                game_state.schedule_proof = planner.schedule_proof(game_state.word_id);
+                // the freshness proof isn't persisted, it's proven again
+                game_state.previous = history.before(game_state.word_id).into();
+                game_state.freshness_proof = prove_freshness(
+                    &immutable_state,
+                    &game_state.secret,
+                    &game_state.commitment,
+                    &game_state.previous,
+                )
+                .map_err(Error::CreateGame)?;
+                (game_state, planner, history)
+            }
+            None => {
+                info!("Creating initial game state...");
+                let mut history = RoundHistory::default();
+                let mut planner = RoundPlanner::new(
+                    word_bank,
+                    0,
+                    None,
+                    None,
+                    &history,
+                    config.seed_chain_length,
+                    config.schedule_length,
+                )
+                .map_err(Error::Schedule)?;
+                let planned = planner
+                    .plan(word_bank, 0, &history)
+                    .map_err(Error::Schedule)?;
+                let game_state =
+                    create_game(&immutable_state, 0, planned).map_err(Error::CreateGame)?;
+                history.push(used_word(&game_state));
+                persist_round(round_store.as_ref(), &game_state, &planner, &history);
+                (game_state, planner, history)
+            }
+        };
+        metrics.last_rotation_timestamp_seconds.set(unix_now());
+
+        let mut warm_up_words = config.proof_cache_warm_up.clone();
+        warm_up_words.retain(|word| {
+            let exists = word_bank.has_word(word);
+            if !exists {
+                warn!(word = %word, "Skipping warm-up word which is not in the word bank");
+            }
+            exists
+        });
+
+        let proof_cache = ProofCache::new(config.proof_cache_size);
+        proof_cache.clear(game_state.word_id);
         let shared_state = SharedState {
             mutable_game_state: RwLock::new(game_state),
-            immutable_state: ImmutableState {
-                clue_config,
-                clue_pk,
-                word_bank,
-            },
+            grace_rounds: RwLock::new(GraceRounds::new(
+                config.grace_rounds,
+                Duration::from_secs(config.grace_period_seconds),
+            )),
+            revealed_rounds: RwLock::new(VecDeque::new()),
+            proof_cache,
+            round_events: broadcast::channel(ROUND_EVENTS_CAPACITY).0,
+            next_rotation_timestamp: AtomicI64::new(unix_now() + SLEEP_DURATION.as_secs() as i64),
+            challenges: Challenges::new(
+                config.challenge_capacity,
+                Duration::from_secs(config.challenge_ttl_hours * 60 * 60),
+            ),
+            practice_games: PracticeGames::new(
+                config.practice_pool_size,
+                config.practice_max_sessions,
+                Duration::from_secs(config.practice_session_ttl_minutes * 60),
+            ),
+            immutable_state,
+            metrics,
         };
 
         Ok(GameStateService {
             shared_state: Arc::new(shared_state),
-            membership_config,
-            membership_pk,
+            round_store,
+            planner,
+            history,
+            warm_up_words,
         })
     }
 
@@ -124,79 +573,417 @@ impl GameStateService {
         self.shared_state.clone()
     }
 
-    /// Runs the service. Every SLEEP_DURATION game state is updated.
+    /// Runs the service. Every SLEEP_DURATION game state is updated, and the finished rounds are
+    /// closed once their grace period is over.
     pub async fn run(mut self) {
-        info!(
-            "Starting game state service with initial state: {:?}",
-            self.shared_state.mutable_game_state.read().clone(),
-        );
+        let word_id = self.shared_state.mutable_game_state.read().word_id;
+        info!(word_id, "Starting game state service");
+        audit_round(&self.shared_state.mutable_game_state.read());
+        self.warm_up_proof_cache();
+        self.refill_practice_pool();
 
+        let mut next_rotation = self.schedule_rotation();
         loop {
-            // update the state every `SLEEP_DURATION`
-            tokio::time::sleep(SLEEP_DURATION).await;
-            self.update_game_state();
+            let next_close = self.shared_state.grace_rounds.read().next_close();
+            let wake_up = next_close.map_or(next_rotation, |close| close.min(next_rotation));
+            tokio::time::sleep_until(wake_up.into()).await;
+
+            if Instant::now() >= next_rotation {
+                self.update_game_state().await;
+                next_rotation = self.schedule_rotation();
+            }
+            let closed = self
+                .shared_state
+                .grace_rounds
+                .write()
+                .close_expired(Instant::now());
+            self.close_rounds(closed);
+        }
+    }
+
+    // the state is updated every `SLEEP_DURATION`
+    fn schedule_rotation(&self) -> Instant {
+        self.shared_state.next_rotation_timestamp.store(
+            unix_now() + SLEEP_DURATION.as_secs() as i64,
+            Ordering::Relaxed,
+        );
+        Instant::now() + SLEEP_DURATION
+    }
+
+    /// Spawns a task keeping the pool of practice games full. The games are proven one at a time,
+    /// so that the pool doesn't hog the prover pool when many sessions start at once.
+    fn refill_practice_pool(&self) {
+        let state = self.shared_state.clone();
+
+        tokio::spawn(
+            async move {
+                loop {
+                    while state.practice_games.missing() > 0 {
+                        let picked = state.immutable_state.word_bank.pick_word();
+                        match state.create_private_game(picked).await {
+                            Ok(game_state) => state.practice_games.add_ready(game_state),
+                            Err(e) => {
+                                // retried once another game is taken
+                                error!(error = ?e, "Failed to create a practice game");
+                                break;
+                            }
+                        }
+                    }
+                    state.practice_games.wait_for_refill().await;
+                }
+            }
+            .instrument(info_span!("practice_pool")),
+        );
+    }
+
+    /// Proves the warm-up words for the current round in the background, one at a time, so that
+    /// the players' guesses still get most of the prover pool.
+    fn warm_up_proof_cache(&self) {
+        if self.warm_up_words.is_empty() {
+            return;
+        }
+        let state = self.shared_state.clone();
+        let words = self.warm_up_words.clone();
+        let game_state = state.mutable_game_state.read().clone();
+        let span = info_span!("warm_up", word_id = game_state.word_id);
+
+        tokio::spawn(
+            async move {
+                for word in words {
+                    // stop once the round is over, the proofs would be useless
+                    if state.mutable_game_state.read().word_id != game_state.word_id {
+                        return;
+                    }
+                    if state.proof_cache.get(game_state.word_id, &word).is_some() {
+                        continue;
+                    }
+                    let proven = state
+                        .prove_clue(&game_state.secret, &game_state.commitment, &word)
+                        .await;
+                    match proven {
+                        Ok((clue, proof)) => {
+                            state
+                                .proof_cache
+                                .insert(game_state.word_id, &word, clue, proof)
+                        }
+                        Err(e) => {
+                            error!(error = ?e, "Failed to warm up the proof cache");
+                            return;
+                        }
+                    }
+                }
+                info!("Proof cache warmed up");
+            }
+            .instrument(span),
+        );
+    }
+
+    fn publish_round(&self, game_state: &MutableState) {
+        let event = RoundEvent {
+            word_id: game_state.word_id,
+            commitment: game_state.commitment.clone(),
+            membership_proof: game_state.membership_proof.clone(),
+            seed_anchor: game_state.seed_anchor,
+            schedule_proof: game_state.schedule_proof.clone(),
+            freshness_proof: game_state.freshness_proof.clone(),
+            next_rotation_timestamp: unix_now() + SLEEP_DURATION.as_secs() as i64,
+        };
+        // fails only if nobody is subscribed
+        let _ = self
+            .shared_state
+            .round_events
+            .send(GameEvent::RoundStarted(Box::new(event)));
+    }
+
+    /// Moves the closed rounds to the reveal archive, which opens their commitments.
+    fn close_rounds(&self, closed: Vec<MutableState>) {
+        for game_state in closed {
+            let reveal = game_state
+                .secret
+                .reveal(game_state.word_id, game_state.previous.clone());
+            let mut revealed_rounds = self.shared_state.revealed_rounds.write();
+            if revealed_rounds.len() == REVEAL_HISTORY_LEN {
+                revealed_rounds.pop_front();
+            }
+            revealed_rounds.push_back(reveal.clone());
+            drop(revealed_rounds);
+
+            info!(word_id = game_state.word_id, "Round closed");
+            let _ = self
+                .shared_state
+                .round_events
+                .send(GameEvent::RoundClosed(reveal));
         }
     }
 
-    fn update_game_state(&mut self) {
-        let word_bank = &self.shared_state.immutable_state.word_bank;
+    /// Plans the round, and proves it on the prover pool like the private games, so that the
+    /// rotation doesn't block the async runtime.
+    async fn create_round(&mut self, word_id: u32) -> Result<MutableState, CreateGameError> {
+        let planned = self
+            .planner
+            .plan(
+                &self.shared_state.immutable_state.word_bank,
+                word_id,
+                &self.history,
+            )
+            .map_err(CreateGameError::Schedule)?;
+        let state = self.shared_state.clone();
+        self.shared_state
+            .immutable_state
+            .prover_pool
+            .run(move || create_game(&state.immutable_state, word_id, planned))
+            .await
+            .map_err(CreateGameError::Pool)?
+    }
+
+    async fn update_game_state(&mut self) {
         let word_id = self.shared_state.mutable_game_state.read().word_id + 1; // increment the game round
+        let span = info_span!("rotation", word_id);
 
-        match create_game(
-            word_bank,
-            word_id,
-            self.membership_config.clone(),
-            self.membership_pk.clone(),
-        ) {
+        let created = self.create_round(word_id).instrument(span.clone()).await;
+        let _span = span.entered();
+        match created {
             Ok(game_state) => {
-                *self.shared_state.mutable_game_state.write() = game_state;
-                info!(
-                    "New game state: {:?}",
-                    self.shared_state.mutable_game_state.read().clone()
+                self.history.push(used_word(&game_state));
+                let finished = std::mem::replace(
+                    &mut *self.shared_state.mutable_game_state.write(),
+                    game_state,
                 );
+                // the finished round still accepts guesses for a while
+                let closed = self
+                    .shared_state
+                    .grace_rounds
+                    .write()
+                    .push(finished, Instant::now());
+                self.shared_state.proof_cache.clear(word_id);
+                self.warm_up_proof_cache();
+                persist_round(
+                    self.round_store.as_ref(),
+                    &self.shared_state.mutable_game_state.read(),
+                    &self.planner,
+                    &self.history,
+                );
+                self.shared_state
+                    .metrics
+                    .last_rotation_timestamp_seconds
+                    .set(unix_now());
+                info!("New game round created");
+                let game_state = self.shared_state.mutable_game_state.read().clone();
+                audit_round(&game_state);
+                self.publish_round(&game_state);
+                self.close_rounds(closed);
             }
             Err(e) => {
+                self.shared_state.metrics.rotation_failures.inc();
+                let circuit = match e {
+                    CreateGameError::Verify => Some("membership"),
+                    CreateGameError::VerifyFreshness => Some("freshness"),
+                    _ => None,
+                };
+                if let Some(circuit) = circuit {
+                    self.shared_state
+                        .metrics
+                        .proof_verification_failures
+                        .with_label_values(&[circuit])
+                        .inc();
+                }
                 error!(
-                    "Failed to create new game round, continuing the old round: {:?}",
-                    e
+                    error = ?e,
+                    "Failed to create new game round, continuing the old round"
                 );
             }
         }
     }
 }
 
-/// Creates the game's mutable state, by picking a random word from the word bank, and obtaining
-/// the remaining parts (cm, salt, merkle path) accordingly.
-fn create_game(
-    word_bank: &WordBank,
+/// Everything about a game which is fixed before its membership is proven.
+pub struct PlannedGame {
+    pub secret: RoundSecret,
+    // merkle path of the word in the word bank
+    pub path: Vec<MerklePathEntry>,
+    pub seed_anchor: Option<SeedAnchor>,
+    pub schedule_proof: Option<ScheduleProof>,
+    // the recent rounds the word differs from, for rounds
+    pub previous: Option<Vec<UsedWord>>,
+}
+
+impl PlannedGame {
+    /// Plans a game outside of the rounds for the picked word, with a random salt.
+    pub fn random(picked: PickWordResult) -> Result<Self, secret::Error> {
+        let PickWordResult { mut word, path } = picked;
+        let secret = RoundSecret::random(&word);
+        word.zeroize();
+        Ok(PlannedGame {
+            secret: secret?,
+            path,
+            seed_anchor: None,
+            schedule_proof: None,
+            previous: None,
+        })
+    }
+}
+
+/// Creates the game's mutable state for the planned word (selected by the seed chain for the
+/// rounds, randomly for practice, by a player for the challenges), and obtains the remaining
+/// parts (cm, membership proof) accordingly.
+pub fn create_game(
+    immutable_state: &ImmutableState,
     word_id: u32,
-    membership_config: CircomConfig<Bn254>,
-    membership_pk: ProvingKey<Bn254>,
+    planned: PlannedGame,
 ) -> Result<MutableState, CreateGameError> {
-    let PickWordResult {
-        word: solution,
+    let PlannedGame {
+        secret,
         path,
-    } = word_bank.pick_word();
+        seed_anchor,
+        schedule_proof,
+        previous,
+    } = planned;
 
-    let salt: BigUint = thread_rng().sample(RandomBits::new(256));
-    let commitment = hash_word_with_salt(&solution, &salt).map_err(CreateGameError::Merkle)?;
+    let commitment = HASH_PARAMS
+        .commitment(secret.solution(), &secret.salt())
+        .map_err(CreateGameError::Merkle)?;
+    if let Some(proof) = &schedule_proof {
+        if !HASH_PARAMS
+            .verify_path(&commitment, &proof.path, &proof.root)
+            .map_err(CreateGameError::Merkle)?
+        {
+            return Err(CreateGameError::NotScheduled);
+        }
+    }
 
     // Prove membership only once and keep membership_proof in shared game state
     let membership_proof = generate_membership_proof(
-        solution.clone(),
+        &secret,
         commitment.clone(),
-        salt.clone(),
-        path,
-        membership_config,
-        membership_pk,
+        path.into_iter().map(Into::into).collect(),
+        immutable_state.membership_config.clone(),
+        immutable_state.membership_pk.clone(),
     )
     .map_err(|_| CreateGameError::Prove)?;
 
+    let signals = membership_public_signals(&immutable_state.word_bank.root_hash(), &commitment);
+    if !immutable_state
+        .membership_verifier
+        .verify(&signals, &membership_proof)
+        .unwrap_or(false)
+    {
+        return Err(CreateGameError::Verify);
+    }
+
+    let freshness_proof = match &previous {
+        Some(previous) => prove_freshness(immutable_state, &secret, &commitment, previous)?,
+        None => None,
+    };
+
     Ok(MutableState {
         word_id,
-        solution,
+        secret: Arc::new(secret),
         commitment,
-        salt,
         membership_proof,
+        seed_anchor,
+        schedule_proof,
+        freshness_proof,
+        previous: previous.unwrap_or_default().into(),
     })
 }
+
+/// Proves that the word differs from the words of the previous rounds, if the freshness circuit
+/// has its keys.
+fn prove_freshness(
+    immutable_state: &ImmutableState,
+    secret: &RoundSecret,
+    commitment: &BigUint,
+    previous: &[UsedWord],
+) -> Result<Option<FreshnessProof>, CreateGameError> {
+    let Some(keys) = &immutable_state.freshness else {
+        return Ok(None);
+    };
+    let proof = generate_freshness_proof(
+        secret,
+        commitment.clone(),
+        previous,
+        keys.config.clone(),
+        keys.pk.clone(),
+    )
+    .map_err(|_| CreateGameError::Prove)?;
+
+    let previous: Vec<BigUint> = previous
+        .iter()
+        .map(|used| used.commitment.clone())
+        .collect();
+    let signals = freshness_public_signals(commitment, &previous, FRESHNESS_ROUNDS);
+    if !keys.verifier.verify(&signals, &proof).unwrap_or(false) {
+        return Err(CreateGameError::VerifyFreshness);
+    }
+    Ok(Some(FreshnessProof { previous, proof }))
+}
+
+fn used_word(game_state: &MutableState) -> UsedWord {
+    UsedWord {
+        word_id: game_state.word_id,
+        secret: game_state.secret.clone(),
+        commitment: game_state.commitment.clone(),
+    }
+}
+
+/// Saves the round, so that it survives a restart, along with what the planner needs to continue
+/// the seed chain and the schedule, and the recent rounds. A failure is only logged, as the round
+/// can still be played, it just won't be resumed.
+fn persist_round(
+    round_store: Option<&RoundStore>,
+    game_state: &MutableState,
+    planner: &RoundPlanner,
+    history: &RoundHistory,
+) {
+    if let Some(store) = round_store {
+        if let Err(e) = store.save(
+            game_state,
+            planner.seed_chain(),
+            planner.schedule_plan(),
+            history,
+        ) {
+            error!(error = ?e, "Failed to persist the game round");
+        }
+    }
+}
+
+/// Records the round's commitment in the audit log.
+fn audit_round(game_state: &MutableState) {
+    info!(
+        target: AUDIT_TARGET,
+        word_id = game_state.word_id,
+        commitment = %game_state.commitment,
+        "round committed"
+    );
+}
+
+pub(crate) fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use num_bigint::BigUint;
+
+    use super::MutableState;
+    use crate::{secret::RoundSecret, test_util::dummy_game};
+
+    #[test]
+    fn debug_redacts_secrets() {
+        let state = MutableState {
+            secret: Arc::new(RoundSecret::new("crane", &BigUint::from(987654321u32)).unwrap()),
+            commitment: BigUint::from(1234u32),
+            ..dummy_game(7)
+        };
+
+        let printed = format!("{:?}", state);
+
+        assert!(!printed.contains("crane"));
+        assert!(!printed.contains("987654321"));
+        assert!(printed.contains("1234"));
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::game_state::MutableState;
+
+struct GraceRound {
+    game_state: MutableState,
+    closes_at: Instant,
+}
+
+/// Finished rounds which still accept guesses, so that players who are in the middle of a game
+/// when the round rotates can finish it. A round is closed once its grace period is over, or
+/// once more than `limit` newer rounds have finished, whichever comes first. Closed rounds are
+/// returned to the caller for revealing, oldest first.
+pub struct GraceRounds {
+    rounds: VecDeque<GraceRound>,
+    limit: usize,
+    period: Duration,
+}
+
+impl GraceRounds {
+    pub fn new(limit: usize, period: Duration) -> Self {
+        GraceRounds {
+            rounds: VecDeque::new(),
+            limit,
+            period,
+        }
+    }
+
+    /// Starts the grace period of a just finished round. Returns the rounds which got closed.
+    pub fn push(&mut self, game_state: MutableState, now: Instant) -> Vec<MutableState> {
+        self.rounds.push_back(GraceRound {
+            game_state,
+            closes_at: now + self.period,
+        });
+
+        let mut closed = Vec::new();
+        while self.rounds.len() > self.limit {
+            closed.extend(self.rounds.pop_front().map(|round| round.game_state));
+        }
+        closed.extend(self.close_expired(now));
+        closed
+    }
+
+    /// Closes the rounds whose grace period is over.
+    pub fn close_expired(&mut self, now: Instant) -> Vec<MutableState> {
+        let mut closed = Vec::new();
+        while self
+            .rounds
+            .front()
+            .map_or(false, |round| round.closes_at <= now)
+        {
+            closed.extend(self.rounds.pop_front().map(|round| round.game_state));
+        }
+        closed
+    }
+
+    /// When the next round is closed, if any is open.
+    pub fn next_close(&self) -> Option<Instant> {
+        self.rounds.front().map(|round| round.closes_at)
+    }
+
+    pub fn get(&self, word_id: u32) -> Option<&MutableState> {
+        self.rounds
+            .iter()
+            .map(|round| &round.game_state)
+            .find(|game_state| game_state.word_id == word_id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::GraceRounds;
+    use crate::{game_state::MutableState, test_util::dummy_game as round};
+
+    fn word_ids(rounds: Vec<MutableState>) -> Vec<u32> {
+        rounds.iter().map(|round| round.word_id).collect()
+    }
+
+    #[test]
+    fn closed_after_period() {
+        let mut grace = GraceRounds::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        assert!(grace.push(round(0), now).is_empty());
+        assert!(grace.get(0).is_some());
+        assert_eq!(grace.next_close(), Some(now + Duration::from_secs(60)));
+
+        assert!(grace
+            .close_expired(now + Duration::from_secs(59))
+            .is_empty());
+        let closed = grace.close_expired(now + Duration::from_secs(60));
+
+        assert_eq!(word_ids(closed), vec![0]);
+        assert!(grace.get(0).is_none());
+        assert_eq!(grace.next_close(), None);
+    }
+
+    #[test]
+    fn oldest_closed_over_limit() {
+        let mut grace = GraceRounds::new(2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        grace.push(round(0), now);
+        grace.push(round(1), now);
+        let closed = grace.push(round(2), now);
+
+        assert_eq!(word_ids(closed), vec![0]);
+        assert!(grace.get(1).is_some());
+        assert!(grace.get(2).is_some());
+    }
+
+    #[test]
+    fn disabled_closes_immediately() {
+        let now = Instant::now();
+
+        let mut no_rounds = GraceRounds::new(0, Duration::from_secs(60));
+        assert_eq!(word_ids(no_rounds.push(round(0), now)), vec![0]);
+
+        let mut no_period = GraceRounds::new(2, Duration::ZERO);
+        assert_eq!(word_ids(no_period.push(round(0), now)), vec![0]);
+    }
+}
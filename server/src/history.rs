@@ -0,0 +1,106 @@
+//! History of the recently played rounds. A word isn't selected again within
+//! `FRESHNESS_ROUNDS` rounds. Once the freshness circuit (`proof-freshness`) has keys, every round
+//! comes with a proof of it: the circuit opens the commitments of the previous rounds in zero
+//! knowledge, and checks that none of their words is the round's word.
+
+use std::{collections::VecDeque, sync::Arc};
+
+use ark_bn254::Bn254;
+use ark_groth16::Proof;
+use num_bigint::BigUint;
+
+use crate::secret::RoundSecret;
+
+/// How many previous rounds the word of a round differs from. Fixed by the freshness circuit.
+pub const FRESHNESS_ROUNDS: usize = 48;
+
+/// A played round, with the secret opening its commitment.
+#[derive(Clone, Debug)]
+pub struct UsedWord {
+    pub word_id: u32,
+    pub secret: Arc<RoundSecret>,
+    pub commitment: BigUint,
+}
+
+/// Proof that the round's word differs from the words committed in the previous rounds.
+#[derive(Clone, Debug)]
+pub struct FreshnessProof {
+    // commitments of the previous rounds, oldest first
+    pub previous: Vec<BigUint>,
+    pub proof: Proof<Bn254>,
+}
+
+/// The rounds played most recently, at most `FRESHNESS_ROUNDS` of them, oldest first.
+#[derive(Clone, Debug, Default)]
+pub struct RoundHistory {
+    rounds: VecDeque<UsedWord>,
+}
+
+impl RoundHistory {
+    pub fn new(rounds: impl IntoIterator<Item = UsedWord>) -> Self {
+        let mut history = RoundHistory::default();
+        for round in rounds {
+            history.push(round);
+        }
+        history
+    }
+
+    /// Records the round, dropping the oldest one over the limit. A round which is already in
+    /// the history (restarted, or created again) replaces the recorded one.
+    pub fn push(&mut self, round: UsedWord) {
+        self.rounds.retain(|used| used.word_id < round.word_id);
+        self.rounds.push_back(round);
+        while self.rounds.len() > FRESHNESS_ROUNDS {
+            self.rounds.pop_front();
+        }
+    }
+
+    /// The rounds the word of the round `word_id` has to differ from, oldest first.
+    pub fn before(&self, word_id: u32) -> Vec<UsedWord> {
+        let first = word_id.saturating_sub(FRESHNESS_ROUNDS as u32);
+        self.rounds
+            .iter()
+            .filter(|used| used.word_id >= first && used.word_id < word_id)
+            .cloned()
+            .collect()
+    }
+
+    pub fn rounds(&self) -> impl Iterator<Item = &UsedWord> {
+        self.rounds.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RoundHistory, UsedWord, FRESHNESS_ROUNDS};
+    use crate::test_util::dummy_game;
+
+    fn used(word_id: u32) -> UsedWord {
+        let game = dummy_game(word_id);
+        UsedWord {
+            word_id,
+            secret: game.secret,
+            commitment: game.commitment,
+        }
+    }
+
+    #[test]
+    fn keeps_recent_rounds() {
+        let rounds = FRESHNESS_ROUNDS as u32;
+        let mut history = RoundHistory::new((0..rounds + 10).map(used));
+        assert_eq!(history.rounds().count(), FRESHNESS_ROUNDS);
+        assert_eq!(history.rounds().next().unwrap().word_id, 10);
+
+        let before: Vec<_> = history
+            .before(rounds + 12)
+            .iter()
+            .map(|used| used.word_id)
+            .collect();
+        assert_eq!(before, (12..rounds + 10).collect::<Vec<_>>());
+
+        // created again after a restart
+        history.push(used(rounds + 5));
+        assert_eq!(history.rounds().last().unwrap().word_id, rounds + 5);
+        assert!(history.before(rounds + 12).len() < FRESHNESS_ROUNDS);
+    }
+}
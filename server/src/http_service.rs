@@ -1,48 +1,144 @@
-use axum::http::header::CONTENT_TYPE;
-use axum::http::StatusCode;
+use axum::http::header::{CONTENT_TYPE, RETRY_AFTER};
+use axum::http::{HeaderName, StatusCode};
 use axum::routing::post;
 use axum::{
-    extract::State, http::Method, response::IntoResponse, routing::get, serve, Json, Router,
+    extract::{ConnectInfo, MatchedPath, Path, Request, State},
+    http::{HeaderMap, Method},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    serve, Json, Router,
 };
-use log::{error, info};
-use std::sync::Arc;
+use num_bigint::BigUint;
+use std::{net::SocketAddr, sync::Arc, time::Instant};
 use tokio::net::TcpListener;
-use tower_http::cors::{Any, CorsLayer};
+use tower::ServiceBuilder;
+use tower_http::{
+    cors::{Any, CorsLayer},
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, RequestId, SetRequestIdLayer},
+    trace::TraceLayer,
+};
+use tracing::{error, info, info_span, warn, Instrument};
 
+use crate::challenge::Challenge;
 use crate::game_state::SharedState;
-use crate::proofs::generate_clue_proof;
-use crate::request_response::StartResponse;
+use crate::metrics::Metrics;
+use crate::request_response::{AbsenceProofSerializable, NoSuchWordResponse, PrivateGuessRequest};
+use crate::request_response::{ChallengeRequest, ChallengeResponse, PracticeResponse};
+use crate::request_response::{DictionaryResponse, RevealResponse, StartResponse, StatusResponse};
+use crate::request_response::{GuessBatchItem, GuessBatchRequest, GuessBatchResponse};
 use crate::request_response::{GuessRequest, GuessResponse};
+use crate::secret::RoundSecret;
+use crate::ws;
+
+/// Header identifying the player's session, which is rate limited on top of the client IP.
+pub const SESSION_ID_HEADER: &str = "x-session-id";
+// longer session ids are ignored, so that they can't blow up the rate limiter memory
+const MAX_SESSION_ID_LEN: usize = 64;
+/// A batch can hold all guesses of a game, and no more.
+const MAX_BATCH_GUESSES: usize = 6;
 
-/// Runs the HTTP service. Routes two paths:
+/// Runs the HTTP service. Routes thirteen paths:
 /// START, GET
 /// GUESS, POST { word_id, guess }
+/// GUESS BATCH, POST { word_id, guesses }
+/// REVEAL, GET /reveal/:word_id
+/// CHALLENGE, POST { word }
+/// CHALLENGE, GET /challenge/:game_id
+/// CHALLENGE GUESS, POST /challenge/:game_id/guess { guess }
+/// PRACTICE, POST
+/// PRACTICE GUESS, POST /practice/:session_id/guess { guess }
+/// DICTIONARY, GET
+/// STATUS, GET
+/// METRICS, GET (prometheus text format)
+/// WS, GET (websocket, see `ws`)
 pub async fn run(addr: &str, state: Arc<SharedState>) {
     info!("Starting server...");
     let cors = CorsLayer::new()
         .allow_methods([Method::GET, Method::POST])
         .allow_origin(Any)
-        .allow_headers([CONTENT_TYPE]);
+        .allow_headers([CONTENT_TYPE, HeaderName::from_static(SESSION_ID_HEADER)])
+        .expose_headers([RETRY_AFTER]);
 
     let app = Router::new()
         .route("/start", get(handle_start))
-        .route("/guess", post(handle_guess))
-        .layer(cors)
+        .route(
+            "/guess",
+            post(handle_guess)
+                .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit)),
+        )
+        .route("/guess/batch", post(handle_guess_batch))
+        .route("/reveal/:word_id", get(handle_reveal))
+        .route(
+            "/challenge",
+            post(handle_create_challenge)
+                .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit)),
+        )
+        .route("/challenge/:game_id", get(handle_challenge))
+        .route(
+            "/challenge/:game_id/guess",
+            post(handle_challenge_guess)
+                .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit)),
+        )
+        .route(
+            "/practice",
+            post(handle_start_practice)
+                .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit)),
+        )
+        .route(
+            "/practice/:session_id/guess",
+            post(handle_practice_guess)
+                .route_layer(middleware::from_fn_with_state(state.clone(), rate_limit)),
+        )
+        .route("/dictionary", get(handle_dictionary))
+        .route("/status", get(handle_status))
+        .route("/metrics", get(handle_metrics))
+        .route("/ws", get(ws::handle_ws))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            count_requests,
+        ))
+        .layer(
+            // every request gets an id (unless the client sent one), which is attached to all
+            // its logs and returned in the x-request-id response header
+            ServiceBuilder::new()
+                .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+                .layer(TraceLayer::new_for_http().make_span_with(request_span))
+                .layer(PropagateRequestIdLayer::x_request_id())
+                .layer(cors),
+        )
         .with_state(state);
 
     let listener = match TcpListener::bind(addr).await {
         Ok(listener) => listener,
         Err(e) => {
-            error!("Couldn't create listener: {}", e);
+            error!(error = %e, "Couldn't create listener");
             return;
         }
     };
 
+    // the client address is needed for rate limiting
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
     if let Err(e) = serve(listener, app).await {
-        error!("Error while serving: {}", e);
+        error!(error = %e, "Error while serving");
     }
 }
 
+fn request_span(request: &Request) -> tracing::Span {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .unwrap_or_default();
+
+    info_span!(
+        "request",
+        request_id,
+        method = %request.method(),
+        uri = %request.uri(),
+    )
+}
+
 /// Responds with the current round of the game (word_id) and the membership proof.
 async fn handle_start(State(state): State<Arc<SharedState>>) -> impl IntoResponse {
     let game_state = state.mutable_game_state.read().clone();
@@ -50,7 +146,11 @@ async fn handle_start(State(state): State<Arc<SharedState>>) -> impl IntoRespons
     Json(StartResponse {
         word_id: game_state.word_id,
         commitment: game_state.commitment.to_string(),
+        root: state.immutable_state.word_bank.root_hash().to_string(),
         proof: game_state.membership_proof.into(),
+        seed_anchor: game_state.seed_anchor.map(Into::into),
+        schedule: game_state.schedule_proof.clone().map(Into::into),
+        freshness: game_state.freshness_proof.clone().map(Into::into),
     })
     .into_response()
 }
@@ -61,37 +161,494 @@ async fn handle_guess(
     State(state): State<Arc<SharedState>>,
     Json(guess): Json<GuessRequest>,
 ) -> impl IntoResponse {
-    if guess.word_id != state.mutable_game_state.read().word_id {
-        return Json((StatusCode::BAD_REQUEST, "bad word id")).into_response();
+    match answer_guess(&state, &guess).await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => e.into_response(),
     }
+}
+
+#[derive(Debug)]
+pub(crate) enum GuessError {
+    BadWordId,
+    // with the proof that the guess is not in the dictionary, if it's a word at all
+    NoSuchWord(Option<AbsenceProofSerializable>),
+    Proof,
+}
+
+impl GuessError {
+    pub(crate) fn message(&self) -> &'static str {
+        match self {
+            GuessError::BadWordId => "bad word id",
+            GuessError::NoSuchWord(_) => "word does not exist",
+            GuessError::Proof => "failed to generate a clue proof",
+        }
+    }
+}
+
+impl IntoResponse for GuessError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            GuessError::NoSuchWord(absence) => return no_such_word(absence),
+            GuessError::BadWordId => StatusCode::BAD_REQUEST,
+            GuessError::Proof => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(self.message())).into_response()
+    }
+}
+
+// proof that the word is not in the dictionary, unless it's not a word at all
+fn absence_proof(state: &SharedState, word: &str) -> Option<AbsenceProofSerializable> {
+    state
+        .immutable_state
+        .dictionary
+        .absence_proof(word)
+        .map(Into::into)
+}
+
+fn no_such_word(absence: Option<AbsenceProofSerializable>) -> Response {
+    let response = NoSuchWordResponse {
+        error: "word does not exist".into(),
+        absence,
+    };
+    (StatusCode::BAD_REQUEST, Json(response)).into_response()
+}
+
+/// Answers a guess the same way whichever protocol it came with.
+pub(crate) async fn answer_guess(
+    state: &Arc<SharedState>,
+    guess: &GuessRequest,
+) -> Result<GuessResponse, GuessError> {
+    let game_state = state
+        .playable_round(guess.word_id)
+        .ok_or(GuessError::BadWordId)?;
     if !state.immutable_state.word_bank.has_word(&guess.guess) {
-        return Json((StatusCode::BAD_REQUEST, "word does not exist")).into_response();
+        return Err(GuessError::NoSuchWord(absence_proof(state, &guess.guess)));
     }
 
-    let game_state = state.mutable_game_state.read().clone();
+    let started = Instant::now();
+    let (clue, proof) = state
+        .clue_proof(&game_state, &guess.guess)
+        .await
+        .map_err(|e| {
+            error!(error = ?e, "Clue proof generation failed");
+            GuessError::Proof
+        })?;
+
+    state
+        .metrics
+        .guess_latency_seconds
+        .observe(started.elapsed().as_secs_f64());
+
+    Ok(GuessResponse {
+        colors: clue,
+        proof: proof.into(),
+    })
+}
+
+/// Responds with the clues and proofs for up to six guesses of a playable round, in the order of
+/// the guesses. The guesses are proven in parallel, and a guess which fails gets an error in its
+/// result, without failing the others. Rate limited by the number of proofs, instead of requests.
+async fn handle_guess_batch(
+    State(state): State<Arc<SharedState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(batch): Json<GuessBatchRequest>,
+) -> impl IntoResponse {
+    if batch.guesses.is_empty() || batch.guesses.len() > MAX_BATCH_GUESSES {
+        return (StatusCode::BAD_REQUEST, Json("bad number of guesses")).into_response();
+    }
+    let Some(game_state) = state.playable_round(batch.word_id) else {
+        return (StatusCode::BAD_REQUEST, Json("bad word id")).into_response();
+    };
 
-    let (proof, clue) = match generate_clue_proof(
-        guess.guess,
-        game_state.solution.clone(),
-        game_state.commitment.clone(),
-        game_state.salt.clone(),
-        state.immutable_state.clue_config.clone(),
-        state.immutable_state.clue_pk.clone(),
-    ) {
-        Ok(value) => value,
+    let word_bank = &state.immutable_state.word_bank;
+    let proofs_needed = batch
+        .guesses
+        .iter()
+        .filter(|guess| word_bank.has_word(guess))
+        .count();
+    if let Err(response) = check_rate_limit(&state, addr, &headers, proofs_needed as u32) {
+        return response;
+    }
+
+    // every guess gets its own task, so that the prover pool proves them in parallel
+    let jobs: Vec<_> = batch
+        .guesses
+        .iter()
+        .map(|guess| {
+            if !word_bank.has_word(guess) {
+                return None;
+            }
+            let state = state.clone();
+            let game_state = game_state.clone();
+            let guess = guess.clone();
+            Some(tokio::spawn(
+                async move { state.clue_proof(&game_state, &guess).await }.in_current_span(),
+            ))
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(jobs.len());
+    for (guess, job) in batch.guesses.into_iter().zip(jobs) {
+        let absence = job
+            .is_none()
+            .then(|| absence_proof(&state, &guess))
+            .flatten();
+        let (response, error) = match job {
+            None => (None, Some("word does not exist")),
+            Some(job) => match job.await {
+                Ok(Ok((clue, proof))) => (
+                    Some(GuessResponse {
+                        colors: clue,
+                        proof: proof.into(),
+                    }),
+                    None,
+                ),
+                Ok(Err(e)) => {
+                    error!(error = ?e, "Clue proof generation failed");
+                    (None, Some("failed to generate a clue proof"))
+                }
+                Err(e) => {
+                    error!(error = ?e, "Clue proof task failed");
+                    (None, Some("failed to generate a clue proof"))
+                }
+            },
+        };
+        results.push(GuessBatchItem {
+            guess,
+            response,
+            error: error.map(String::from),
+            absence,
+        });
+    }
+
+    Json(GuessBatchResponse {
+        word_id: game_state.word_id,
+        results,
+    })
+    .into_response()
+}
+
+/// Responds with the solution and salt of a finished round, so that players can check their
+/// clues against it. Rounds which are still in progress (or too old) are not found.
+async fn handle_reveal(
+    State(state): State<Arc<SharedState>>,
+    Path(word_id): Path<u32>,
+) -> impl IntoResponse {
+    let revealed_rounds = state.revealed_rounds.read();
+    match revealed_rounds.iter().find(|r| r.word_id == word_id) {
+        Some(reveal) => Json(RevealResponse::from(reveal.clone())).into_response(),
+        None => (StatusCode::NOT_FOUND, Json("round not revealed")).into_response(),
+    }
+}
+
+/// Creates a challenge game against the sent word, which must be one of the solution words.
+/// Responds with the game id to share, and the commitment and membership proof the friends play
+/// against. The salt never leaves the server, so the creator can't prove clues for the word.
+async fn handle_create_challenge(
+    State(state): State<Arc<SharedState>>,
+    Json(request): Json<ChallengeRequest>,
+) -> impl IntoResponse {
+    let Some(picked) = state.immutable_state.word_bank.find_word(&request.word) else {
+        return (StatusCode::BAD_REQUEST, Json("not a solution word")).into_response();
+    };
+
+    match state.create_challenge(picked).await {
+        Ok(game_id) => match state.challenges.get(&game_id) {
+            Some(challenge) => Json(challenge_response(&state, game_id, challenge)).into_response(),
+            // dropped right away, only if the capacity is tiny
+            None => (StatusCode::SERVICE_UNAVAILABLE, Json("too many challenges")).into_response(),
+        },
         Err(e) => {
-            error!("Clue proof generation failed: {:?}", e);
-            return Json((
+            error!(error = ?e, "Challenge creation failed");
+            (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                "failed to generate a clue proof",
-            ))
-            .into_response();
+                Json("failed to create the challenge"),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Responds with the commitment and membership proof of a challenge game.
+async fn handle_challenge(
+    State(state): State<Arc<SharedState>>,
+    Path(game_id): Path<String>,
+) -> impl IntoResponse {
+    match state.challenges.get(&game_id) {
+        Some(challenge) => Json(challenge_response(&state, game_id, challenge)).into_response(),
+        None => (StatusCode::NOT_FOUND, Json("no such challenge")).into_response(),
+    }
+}
+
+/// Checks that the guess word exists in the WordBank, and responds with a clue against the
+/// challenge's word, and a clue correctness proof.
+async fn handle_challenge_guess(
+    State(state): State<Arc<SharedState>>,
+    Path(game_id): Path<String>,
+    Json(guess): Json<PrivateGuessRequest>,
+) -> impl IntoResponse {
+    let Some(challenge) = state.challenges.get(&game_id) else {
+        return (StatusCode::NOT_FOUND, Json("no such challenge")).into_response();
+    };
+    answer_private_guess(
+        &state,
+        &challenge.secret,
+        &challenge.commitment,
+        &guess.guess,
+    )
+    .await
+}
+
+/// Starts a practice session against a random word of its own. Responds with the session id,
+/// and the commitment and membership proof to play against.
+async fn handle_start_practice(State(state): State<Arc<SharedState>>) -> impl IntoResponse {
+    match state.start_practice().await {
+        Ok((session_id, game_state)) => Json(PracticeResponse {
+            session_id,
+            commitment: game_state.commitment.to_string(),
+            root: state.immutable_state.word_bank.root_hash().to_string(),
+            proof: game_state.membership_proof.into(),
+        })
+        .into_response(),
+        Err(e) => {
+            error!(error = ?e, "Practice game creation failed");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("failed to start a practice game"),
+            )
+                .into_response()
         }
+    }
+}
+
+/// Like a challenge guess, but against the word of the practice session, which is kept alive.
+async fn handle_practice_guess(
+    State(state): State<Arc<SharedState>>,
+    Path(session_id): Path<String>,
+    Json(guess): Json<PrivateGuessRequest>,
+) -> impl IntoResponse {
+    let Some(game_state) = state.practice_games.get(&session_id) else {
+        return (StatusCode::NOT_FOUND, Json("no such practice session")).into_response();
     };
+    answer_private_guess(
+        &state,
+        &game_state.secret,
+        &game_state.commitment,
+        &guess.guess,
+    )
+    .await
+}
 
-    Json(GuessResponse {
-        colors: clue,
-        proof: proof.into(),
+/// Responds with the clue of the guess against the secret of a private game, and its proof.
+/// Clue proofs of private games are not cached.
+async fn answer_private_guess(
+    state: &Arc<SharedState>,
+    secret: &Arc<RoundSecret>,
+    commitment: &BigUint,
+    guess: &str,
+) -> Response {
+    if !state.immutable_state.word_bank.has_word(guess) {
+        return no_such_word(absence_proof(state, guess));
+    }
+
+    match state.prove_clue(secret, commitment, guess).await {
+        Ok((clue, proof)) => Json(GuessResponse {
+            colors: clue,
+            proof: proof.into(),
+        })
+        .into_response(),
+        Err(e) => {
+            error!(error = ?e, "Clue proof generation failed");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json("failed to generate a clue proof"),
+            )
+                .into_response()
+        }
+    }
+}
+
+fn challenge_response(
+    state: &SharedState,
+    game_id: String,
+    challenge: Challenge,
+) -> ChallengeResponse {
+    ChallengeResponse {
+        game_id,
+        commitment: challenge.commitment.to_string(),
+        root: state.immutable_state.word_bank.root_hash().to_string(),
+        proof: challenge.membership_proof.into(),
+    }
+}
+
+/// Responds with the root of the dictionary, which the proofs of the rejected guesses are against.
+async fn handle_dictionary(State(state): State<Arc<SharedState>>) -> impl IntoResponse {
+    let dictionary = &state.immutable_state.dictionary;
+
+    Json(DictionaryResponse {
+        root: dictionary.root_hash().to_string(),
+        words: dictionary.len(),
     })
     .into_response()
 }
+
+/// Responds with the current round and the load of the prover pool.
+async fn handle_status(State(state): State<Arc<SharedState>>) -> impl IntoResponse {
+    let prover_pool = &state.immutable_state.prover_pool;
+
+    Json(StatusResponse {
+        word_id: state.mutable_game_state.read().word_id,
+        prover_workers: prover_pool.workers(),
+        prover_queue_depth: prover_pool.queue_depth(),
+    })
+    .into_response()
+}
+
+/// Responds with all metrics in the prometheus text format.
+async fn handle_metrics(State(state): State<Arc<SharedState>>) -> impl IntoResponse {
+    let metrics = &state.metrics;
+    metrics
+        .prover_queue_depth
+        .set(state.immutable_state.prover_pool.queue_depth() as i64);
+    metrics
+        .word_id
+        .set(state.mutable_game_state.read().word_id as i64);
+
+    match metrics.encode() {
+        Ok(text) => text.into_response(),
+        Err(e) => {
+            error!(error = ?e, "Failed to encode metrics");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Rate limits a single guess request.
+async fn rate_limit(
+    State(state): State<Arc<SharedState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match check_rate_limit(&state, addr, request.headers(), 1) {
+        Ok(()) => next.run(request).await,
+        Err(response) => response,
+    }
+}
+
+/// Charges the client IP and the session (if sent) `cost` tokens, or responds with 429 and
+/// `Retry-After` if either is over its rate limit.
+fn check_rate_limit(
+    state: &SharedState,
+    addr: SocketAddr,
+    headers: &HeaderMap,
+    cost: u32,
+) -> Result<(), Response> {
+    let session = headers
+        .get(SESSION_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= MAX_SESSION_ID_LEN);
+
+    charge_rate_limit(state, addr, session, cost).map_err(|retry_after| {
+        (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, retry_after.to_string())],
+            "too many requests",
+        )
+            .into_response()
+    })
+}
+
+/// Charges the client IP and the session `cost` tokens. If either is over its rate limit,
+/// returns in how many seconds to retry.
+pub(crate) fn charge_rate_limit(
+    state: &SharedState,
+    addr: SocketAddr,
+    session: Option<&str>,
+    cost: u32,
+) -> Result<(), u64> {
+    let checked = state
+        .immutable_state
+        .rate_limiter
+        .check(addr.ip().to_canonical(), session, cost);
+    checked.map_err(|rejected| {
+        warn!(limit = rejected.limit.as_str(), "Guess rate limited");
+        state
+            .metrics
+            .rate_limited_requests
+            .with_label_values(&[rejected.limit.as_str()])
+            .inc();
+        rejected.retry_after.as_secs_f64().ceil().max(1.0) as u64
+    })
+}
+
+/// Counts the requests by the matched route (so that path parameters don't blow up the number
+/// of series) and the response status.
+async fn count_requests(
+    State(state): State<Arc<SharedState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    count_request(&state.metrics, request, next).await
+}
+
+async fn count_request(metrics: &Metrics, request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_owned())
+        .unwrap_or_default();
+
+    let response = next.run(request).await;
+
+    metrics
+        .requests
+        .with_label_values(&[&route, response.status().as_str()])
+        .inc();
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use axum::{
+        body::Body,
+        extract::{Request, State},
+        middleware::{self, Next},
+        response::{IntoResponse, Response},
+        routing::post,
+        Router,
+    };
+    use tower::ServiceExt;
+
+    use super::{count_request, GuessError};
+    use crate::metrics::Metrics;
+
+    async fn count(State(metrics): State<Arc<Metrics>>, request: Request, next: Next) -> Response {
+        count_request(&metrics, request, next).await
+    }
+
+    #[tokio::test]
+    async fn errors_counted_by_status() {
+        let metrics = Arc::new(Metrics::new().expect("metrics should register"));
+        let app = Router::new()
+            .route(
+                "/guess",
+                post(|| async { GuessError::BadWordId.into_response() }),
+            )
+            .route_layer(middleware::from_fn_with_state(metrics.clone(), count));
+
+        let request = Request::post("/guess").body(Body::empty()).unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), 400);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], br#""bad word id""#);
+
+        let text = metrics.encode().expect("metrics should encode");
+        assert!(text.contains(r#"wordle_http_requests_total{route="/guess",status="400"} 1"#));
+    }
+}
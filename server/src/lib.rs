@@ -0,0 +1,27 @@
+pub mod challenge;
+pub mod clue;
+pub mod config;
+pub mod dictionary;
+pub mod game_state;
+pub mod grace;
+pub mod history;
+pub mod http_service;
+pub mod logging;
+pub mod metrics;
+pub mod practice;
+pub mod proof_cache;
+pub mod proofs;
+pub mod prover_pool;
+pub mod rate_limit;
+pub mod request_response;
+pub mod round_store;
+pub mod schedule;
+pub mod secret;
+pub mod seed_chain;
+pub mod solver;
+#[cfg(test)]
+pub(crate) mod test_util;
+pub mod transcript;
+pub mod verifier;
+pub mod word_bank;
+pub mod ws;
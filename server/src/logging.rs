@@ -0,0 +1,109 @@
+use std::{fs::OpenOptions, io, sync::Mutex};
+
+use tracing::Metadata;
+use tracing_subscriber::{
+    filter::{filter_fn, FilterExt, FilterFn},
+    fmt,
+    prelude::*,
+    EnvFilter,
+};
+
+/// Target of the audit log events, which are written to the audit log only.
+pub const AUDIT_TARGET: &str = "audit";
+
+/// Names of the fields which hold the round secrets. Spans and events recording any of them
+/// are never written anywhere, whatever the log level.
+const REDACTED_FIELDS: [&str; 2] = ["solution", "salt"];
+
+/// Sets up JSON logs on stdout (filtered with `RUST_LOG`), and the audit log appended to
+/// the file at `audit_log_path`. Logs of the `log` crate users are forwarded as well.
+pub fn init(audit_log_path: &str) -> io::Result<()> {
+    let audit_file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(audit_log_path)?;
+
+    let logs = fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_filter(
+            redaction_filter()
+                .and(filter_fn(|metadata| metadata.target() != AUDIT_TARGET))
+                .and(EnvFilter::from_default_env()),
+        );
+    let audit = fmt::layer()
+        .json()
+        .with_writer(Mutex::new(audit_file))
+        .with_filter(
+            redaction_filter().and(filter_fn(|metadata| metadata.target() == AUDIT_TARGET)),
+        );
+
+    tracing_subscriber::registry()
+        .with(logs)
+        .with(audit)
+        .try_init()
+        .map_err(io::Error::other)
+}
+
+/// Filter which drops all spans and events with any of the `REDACTED_FIELDS`. It only looks at
+/// the field names, so it's decided once per callsite, and costs nothing afterwards.
+pub fn redaction_filter() -> FilterFn<fn(&Metadata<'_>) -> bool> {
+    filter_fn(has_no_redacted_fields as fn(&Metadata<'_>) -> bool)
+}
+
+fn has_no_redacted_fields(metadata: &Metadata<'_>) -> bool {
+    !metadata
+        .fields()
+        .iter()
+        .any(|field| REDACTED_FIELDS.contains(&field.name()))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing::info;
+    use tracing_subscriber::{fmt, prelude::*};
+
+    use super::redaction_filter;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn secret_fields_never_logged() {
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::registry().with(
+            fmt::layer()
+                .json()
+                .with_writer(move || writer.clone())
+                .with_filter(redaction_filter()),
+        );
+
+        tracing::subscriber::with_default(subscriber, || {
+            info!(word_id = 1, solution = "crane", "round created");
+            info!(salt = 1234567, "salt sampled");
+            info!(word_id = 2, "round rotated");
+        });
+
+        let logs = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert!(!logs.contains("crane"));
+        assert!(!logs.contains("1234567"));
+        assert!(logs.contains("round rotated"));
+    }
+}
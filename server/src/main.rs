@@ -1,19 +1,35 @@
-use game_state::GameStateService;
-use log::error;
+use server::{
+    config::Config,
+    game_state::{self, GameStateService},
+    http_service, logging, word_bank,
+};
 use tokio::select;
-
-mod game_state;
-mod http_service;
-mod proofs;
-mod request_response;
-mod word_bank;
+use tracing::{error, info};
 
 #[tokio::main]
 async fn main() {
-    env_logger::init();
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Bad configuration: {:?}", e);
+            return;
+        }
+    };
 
-    let state_service = match GameStateService::new() {
+    if let Err(e) = logging::init(&config.audit_log_path) {
+        eprintln!("Failed to set up logging: {}", e);
+        return;
+    }
+
+    let state_service = match GameStateService::new(&config) {
         Ok(state) => state,
+        Err(game_state::Error::WordBankCreate(word_bank::Error::InvalidWords(report))) => {
+            error!(
+                "Invalid word lists (see `tools wordbank check`): {}",
+                report
+            );
+            return;
+        }
         Err(e) => {
             error!("Failed to initialize the game state: {:?}", e);
             return;
@@ -21,9 +37,22 @@ async fn main() {
     };
 
     let shared_state = state_service.get_state();
+    if config.verify_merkle_tree {
+        // the tree may be prebuilt, the rounds are served while it's checked
+        let state = shared_state.clone();
+        tokio::task::spawn_blocking(
+            move || match state.immutable_state.word_bank.verify_tree() {
+                Ok(()) => info!("Merkle tree of the solution words verified"),
+                Err(e) => {
+                    error!("Prebuilt merkle tree is invalid: {:?}", e);
+                    std::process::exit(1);
+                }
+            },
+        );
+    }
     let state_service_handle = tokio::spawn(async move { state_service.run().await });
     let http_service_handle =
-        tokio::spawn(async move { http_service::run("127.0.0.1:4000", shared_state).await });
+        tokio::spawn(async move { http_service::run(&config.address, shared_state).await });
 
     select! {
         _ = state_service_handle => {
@@ -0,0 +1,165 @@
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+
+// proving takes seconds, so the default (sub-second) buckets are not enough
+const PROVING_BUCKETS: [f64; 12] = [
+    0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0,
+];
+
+/// Prometheus metrics of the server, exposed by the HTTP service on `/metrics`.
+pub struct Metrics {
+    registry: Registry,
+    // requests by route and response status
+    pub requests: IntCounterVec,
+    // time of building the clue circuit witness
+    pub witness_generation_seconds: Histogram,
+    // time of generating the clue proof, without the witness
+    pub proving_seconds: Histogram,
+    // time of handling the whole guess request, including waiting for a prover
+    pub guess_latency_seconds: Histogram,
+    // jobs waiting for a free prover, set on scrape
+    pub prover_queue_depth: IntGauge,
+    // current round, set on scrape
+    pub word_id: IntGauge,
+    pub last_rotation_timestamp_seconds: IntGauge,
+    pub rotation_failures: IntCounter,
+    // generated proofs which didn't pass verification, by circuit
+    pub proof_verification_failures: IntCounterVec,
+    // guess requests rejected by the rate limiter, by limit (ip or session)
+    pub rate_limited_requests: IntCounterVec,
+    // guesses answered with a cached clue proof, and those which had to be proven
+    pub proof_cache_hits: IntCounter,
+    pub proof_cache_misses: IntCounter,
+    pub websocket_connections: IntGauge,
+    pub practice_pool_misses: IntCounter,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("wordle".into()), None)?;
+
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status"),
+            &["route", "status"],
+        )?;
+        let witness_generation_seconds = Histogram::with_opts(
+            HistogramOpts::new(
+                "witness_generation_seconds",
+                "Time of building the clue circuit witness",
+            )
+            .buckets(PROVING_BUCKETS.to_vec()),
+        )?;
+        let proving_seconds = Histogram::with_opts(
+            HistogramOpts::new("proving_seconds", "Time of generating a clue proof")
+                .buckets(PROVING_BUCKETS.to_vec()),
+        )?;
+        let guess_latency_seconds = Histogram::with_opts(
+            HistogramOpts::new(
+                "guess_latency_seconds",
+                "Time of handling a guess request, including the prover queue",
+            )
+            .buckets(PROVING_BUCKETS.to_vec()),
+        )?;
+        let prover_queue_depth =
+            IntGauge::new("prover_queue_depth", "Proving jobs waiting for a worker")?;
+        let word_id = IntGauge::new("word_id", "Current game round")?;
+        let last_rotation_timestamp_seconds = IntGauge::new(
+            "last_rotation_timestamp_seconds",
+            "Unix time of the last successful round rotation",
+        )?;
+        let rotation_failures = IntCounter::new(
+            "rotation_failures_total",
+            "Failed attempts to create a new game round",
+        )?;
+        let proof_verification_failures = IntCounterVec::new(
+            Opts::new(
+                "proof_verification_failures_total",
+                "Generated proofs which failed verification, by circuit",
+            ),
+            &["circuit"],
+        )?;
+        let rate_limited_requests = IntCounterVec::new(
+            Opts::new(
+                "rate_limited_requests_total",
+                "Guess requests rejected by the rate limiter, by limit",
+            ),
+            &["limit"],
+        )?;
+        let proof_cache_hits = IntCounter::new(
+            "proof_cache_hits_total",
+            "Guesses answered with a cached clue proof",
+        )?;
+        let proof_cache_misses = IntCounter::new(
+            "proof_cache_misses_total",
+            "Guesses which weren't in the clue proof cache",
+        )?;
+
+        let websocket_connections =
+            IntGauge::new("websocket_connections", "Open websocket connections")?;
+        let practice_pool_misses = IntCounter::new(
+            "practice_pool_misses_total",
+            "Practice sessions which started with the pool of ready games empty",
+        )?;
+
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(witness_generation_seconds.clone()))?;
+        registry.register(Box::new(proving_seconds.clone()))?;
+        registry.register(Box::new(guess_latency_seconds.clone()))?;
+        registry.register(Box::new(prover_queue_depth.clone()))?;
+        registry.register(Box::new(word_id.clone()))?;
+        registry.register(Box::new(last_rotation_timestamp_seconds.clone()))?;
+        registry.register(Box::new(rotation_failures.clone()))?;
+        registry.register(Box::new(proof_verification_failures.clone()))?;
+        registry.register(Box::new(rate_limited_requests.clone()))?;
+        registry.register(Box::new(proof_cache_hits.clone()))?;
+        registry.register(Box::new(proof_cache_misses.clone()))?;
+        registry.register(Box::new(websocket_connections.clone()))?;
+        registry.register(Box::new(practice_pool_misses.clone()))?;
+
+        Ok(Metrics {
+            registry,
+            requests,
+            witness_generation_seconds,
+            proving_seconds,
+            guess_latency_seconds,
+            prover_queue_depth,
+            word_id,
+            last_rotation_timestamp_seconds,
+            rotation_failures,
+            proof_verification_failures,
+            rate_limited_requests,
+            proof_cache_hits,
+            proof_cache_misses,
+            websocket_connections,
+            practice_pool_misses,
+        })
+    }
+
+    /// Encodes all metrics in the Prometheus text format.
+    pub fn encode(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer).expect("prometheus text format should be utf-8"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Metrics;
+
+    #[test]
+    fn encodes_registered_metrics() {
+        let metrics = Metrics::new().expect("metrics should register");
+        metrics.requests.with_label_values(&["/guess", "200"]).inc();
+        metrics.rotation_failures.inc();
+        metrics.proving_seconds.observe(1.5);
+
+        let text = metrics.encode().expect("metrics should encode");
+
+        assert!(text.contains(r#"wordle_http_requests_total{route="/guess",status="200"} 1"#));
+        assert!(text.contains("wordle_rotation_failures_total 1"));
+        assert!(text.contains("wordle_proving_seconds_count 1"));
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use tokio::sync::Notify;
+
+use crate::game_state::MutableState;
+
+struct PracticeSession {
+    game_state: MutableState,
+    last_active: Instant,
+}
+
+/// Private practice games, one per session, each against its own random word and salt. Proving
+/// the membership takes a while, so the games are proven ahead of time into a pool of up to
+/// `pool_size` ready games, which the game state service refills. A session expires once it
+/// hasn't been played for `ttl`, and there are at most `max_sessions` of them, the least recently
+/// played ones are dropped first.
+pub struct PracticeGames {
+    pool_size: usize,
+    max_sessions: usize,
+    ttl: Duration,
+    ready: Mutex<Vec<MutableState>>,
+    sessions: Mutex<HashMap<String, PracticeSession>>,
+    // wakes up the refill of the pool
+    refill: Notify,
+}
+
+impl PracticeGames {
+    pub fn new(pool_size: usize, max_sessions: usize, ttl: Duration) -> Self {
+        PracticeGames {
+            pool_size,
+            max_sessions,
+            ttl,
+            ready: Mutex::new(Vec::with_capacity(pool_size)),
+            sessions: Mutex::new(HashMap::new()),
+            refill: Notify::new(),
+        }
+    }
+
+    /// Takes a ready game out of the pool, if there is one, and asks for a refill.
+    pub fn take_ready(&self) -> Option<MutableState> {
+        let game_state = self.ready.lock().pop();
+        self.refill.notify_one();
+        game_state
+    }
+
+    /// How many games the pool is missing.
+    pub fn missing(&self) -> usize {
+        self.pool_size.saturating_sub(self.ready.lock().len())
+    }
+
+    pub fn add_ready(&self, game_state: MutableState) {
+        let mut ready = self.ready.lock();
+        if ready.len() < self.pool_size {
+            ready.push(game_state);
+        }
+    }
+
+    /// Waits until a game is taken out of the pool.
+    pub async fn wait_for_refill(&self) {
+        self.refill.notified().await
+    }
+
+    /// Starts a session playing the game, under a fresh random session id, which is returned.
+    pub fn start(&self, game_state: MutableState) -> String {
+        self.start_at(game_state, Instant::now())
+    }
+
+    /// The game of the session, if it hasn't expired. Playing keeps the session alive.
+    pub fn get(&self, session_id: &str) -> Option<MutableState> {
+        self.get_at(session_id, Instant::now())
+    }
+
+    fn start_at(&self, game_state: MutableState, now: Instant) -> String {
+        // 128 random bits, so that the session ids can't be guessed
+        let session_id = format!("{:032x}", rand::random::<u128>());
+        let mut sessions = self.sessions.lock();
+
+        sessions.retain(|_, session| now.duration_since(session.last_active) < self.ttl);
+        if sessions.len() >= self.max_sessions {
+            let idlest = sessions
+                .iter()
+                .min_by_key(|(_, session)| session.last_active)
+                .map(|(session_id, _)| session_id.clone());
+            if let Some(idlest) = idlest {
+                sessions.remove(&idlest);
+            }
+        }
+
+        sessions.insert(
+            session_id.clone(),
+            PracticeSession {
+                game_state,
+                last_active: now,
+            },
+        );
+        session_id
+    }
+
+    fn get_at(&self, session_id: &str, now: Instant) -> Option<MutableState> {
+        let mut sessions = self.sessions.lock();
+        let session = sessions
+            .get_mut(session_id)
+            .filter(|session| now.duration_since(session.last_active) < self.ttl)?;
+        session.last_active = now;
+        Some(session.game_state.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use num_bigint::BigUint;
+
+    use super::PracticeGames;
+    use crate::test_util::dummy_game as game;
+
+    #[test]
+    fn pool_refilled_up_to_size() {
+        let practice = PracticeGames::new(2, 10, Duration::from_secs(60));
+        assert_eq!(practice.missing(), 2);
+        assert!(practice.take_ready().is_none());
+
+        practice.add_ready(game(1));
+        practice.add_ready(game(2));
+        practice.add_ready(game(3));
+
+        assert_eq!(practice.missing(), 0);
+        assert!(practice.take_ready().is_some());
+        assert_eq!(practice.missing(), 1);
+    }
+
+    #[test]
+    fn expires_after_inactivity() {
+        let practice = PracticeGames::new(2, 10, Duration::from_secs(60));
+        let now = Instant::now();
+
+        let session_id = practice.start_at(game(1), now);
+        // playing keeps the session alive
+        let played = now + Duration::from_secs(50);
+        assert!(practice.get_at(&session_id, played).is_some());
+        assert!(practice
+            .get_at(&session_id, played + Duration::from_secs(59))
+            .is_some());
+
+        let idle = played + Duration::from_secs(59 + 60);
+        assert!(practice.get_at(&session_id, idle).is_none());
+        assert!(practice.get_at("unknown", now).is_none());
+    }
+
+    #[test]
+    fn idlest_dropped_over_capacity() {
+        let practice = PracticeGames::new(2, 2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        let first = practice.start_at(game(1), now);
+        let second = practice.start_at(game(2), now + Duration::from_secs(1));
+        practice.get_at(&first, now + Duration::from_secs(2));
+        let third = practice.start_at(game(3), now + Duration::from_secs(3));
+
+        let later = now + Duration::from_secs(4);
+        assert!(practice.get_at(&second, later).is_none());
+        assert_eq!(
+            practice.get_at(&first, later).unwrap().commitment,
+            BigUint::from(1u32)
+        );
+        assert!(practice.get_at(&third, later).is_some());
+    }
+}
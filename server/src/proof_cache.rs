@@ -0,0 +1,164 @@
+use std::collections::{BTreeMap, HashMap};
+
+use ark_bn254::Bn254;
+use ark_groth16::Proof;
+use parking_lot::Mutex;
+
+/// Clue proofs of the current round, keyed by the guess, so that popular guesses are proven only
+/// once per round. Bounded, the least recently used proof is evicted first. Entries of other rounds
+/// are never returned: the cache is cleared once a newer round shows up, and proofs of older rounds
+/// are not inserted.
+pub struct ProofCache {
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+struct Entry {
+    clue: [u8; 5],
+    proof: Proof<Bn254>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    word_id: u32,
+    entries: HashMap<String, Entry>,
+    // guesses by the last use, for finding the least recently used one
+    by_last_use: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl Inner {
+    // clears the cache if the round is newer, returns false if it's older
+    fn enter_round(&mut self, word_id: u32) -> bool {
+        if word_id > self.word_id {
+            self.word_id = word_id;
+            self.entries.clear();
+            self.by_last_use.clear();
+        }
+        word_id == self.word_id
+    }
+
+    fn touch(&mut self, guess: &str) {
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some(entry) = self.entries.get_mut(guess) {
+            self.by_last_use.remove(&entry.last_used);
+            entry.last_used = tick;
+            self.by_last_use.insert(tick, guess.to_owned());
+        }
+    }
+}
+
+impl ProofCache {
+    /// Creates a cache holding at most `capacity` proofs. With 0 it caches nothing.
+    pub fn new(capacity: usize) -> Self {
+        ProofCache {
+            capacity,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    pub fn get(&self, word_id: u32, guess: &str) -> Option<([u8; 5], Proof<Bn254>)> {
+        let mut inner = self.inner.lock();
+        if word_id != inner.word_id {
+            return None;
+        }
+        inner.touch(guess);
+        inner
+            .entries
+            .get(guess)
+            .map(|entry| (entry.clue, entry.proof.clone()))
+    }
+
+    pub fn insert(&self, word_id: u32, guess: &str, clue: [u8; 5], proof: Proof<Bn254>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut inner = self.inner.lock();
+        if !inner.enter_round(word_id) {
+            return;
+        }
+        if inner.entries.contains_key(guess) {
+            inner.touch(guess);
+            return;
+        }
+
+        if inner.entries.len() == self.capacity {
+            if let Some((_, evicted)) = inner.by_last_use.pop_first() {
+                inner.entries.remove(&evicted);
+            }
+        }
+        inner.tick += 1;
+        let tick = inner.tick;
+        inner.entries.insert(
+            guess.to_owned(),
+            Entry {
+                clue,
+                proof,
+                last_used: tick,
+            },
+        );
+        inner.by_last_use.insert(tick, guess.to_owned());
+    }
+
+    /// Drops the proofs of the rounds before `word_id`. Called on rotation.
+    pub fn clear(&self, word_id: u32) {
+        self.inner.lock().enter_round(word_id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ProofCache;
+    use crate::test_util::dummy_proof as proof;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let cache = ProofCache::new(2);
+        cache.insert(0, "crane", [0; 5], proof());
+        cache.insert(0, "slate", [1; 5], proof());
+
+        // crane is used, so slate is the one evicted
+        assert!(cache.get(0, "crane").is_some());
+        cache.insert(0, "adieu", [2; 5], proof());
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(0, "crane").map(|(clue, _)| clue), Some([0; 5]));
+        assert_eq!(cache.get(0, "adieu").map(|(clue, _)| clue), Some([2; 5]));
+        assert!(cache.get(0, "slate").is_none());
+    }
+
+    #[test]
+    fn cleared_on_new_round() {
+        let cache = ProofCache::new(4);
+        cache.insert(3, "crane", [0; 5], proof());
+        cache.clear(4);
+
+        assert!(cache.is_empty());
+        assert!(cache.get(3, "crane").is_none());
+
+        // a proof of the finished round which completes late is not cached
+        cache.insert(3, "slate", [0; 5], proof());
+        assert!(cache.is_empty());
+
+        cache.insert(4, "slate", [0; 5], proof());
+        assert!(cache.get(4, "slate").is_some());
+    }
+
+    #[test]
+    fn zero_capacity_caches_nothing() {
+        let cache = ProofCache::new(0);
+        cache.insert(0, "crane", [0; 5], proof());
+
+        assert!(cache.get(0, "crane").is_none());
+    }
+}
@@ -3,8 +3,20 @@ use ark_circom::{circom::Inputs, CircomBuilder, CircomConfig, CircomReduction};
 use ark_ff::{BigInteger, PrimeField};
 use ark_groth16::{Groth16, Proof, ProvingKey};
 use ark_snark::SNARK;
-use merkle::{MerklePathEntry, NodeType};
+use merkle::{HashParams, NaryPathEntry};
 use num_bigint::{BigInt, BigUint};
+use std::time::{Duration, Instant};
+use tracing::info_span;
+
+use crate::{
+    history::{UsedWord, FRESHNESS_ROUNDS},
+    secret::RoundSecret,
+};
+
+/// Hash parameters of the circuits the keys were generated from, so the ones of the commitments
+/// and the merkle trees. The frontend verifies the dictionary with the same ones, `HASH_VERSION`
+/// in `front/src/dictionary.ts`.
+pub const HASH_PARAMS: HashParams = HashParams::V1;
 
 #[derive(Debug)]
 pub enum Error {
@@ -13,24 +25,33 @@ pub enum Error {
     Prove,
 }
 
+/// How long the parts of generating a proof took.
+#[derive(Clone, Copy, Debug)]
+pub struct ProofTimings {
+    pub witness: Duration,
+    pub proving: Duration,
+}
+
 pub fn generate_clue_proof(
     guess: String,
-    solution: String,
+    secret: &RoundSecret,
     commitment: BigUint,
-    salt: BigUint,
     config: CircomConfig<Bn254>,
     pk: ProvingKey<Bn254>,
-) -> Result<(Proof<Bn254>, [u8; 5]), Error> {
-    let guess = string_to_bigints(guess);
-    let solution = string_to_bigints(solution);
+) -> Result<(Proof<Bn254>, [u8; 5], ProofTimings), Error> {
+    let started = Instant::now();
+    let guess = string_to_bigints(&guess);
+    let solution = string_to_bigints(secret.solution());
 
     let mut builder = CircomBuilder::new(config);
     builder.push_input("word", Inputs::BigIntVec(solution));
     builder.push_input("guess", Inputs::BigIntVec(guess));
-    builder.push_input("salt", Inputs::BigInt(salt.into()));
+    builder.push_input("salt", Inputs::BigInt(secret.salt().into()));
     builder.push_input("commit", Inputs::BigInt(commitment.into()));
 
-    let circom = builder.build().map_err(|_| Error::BuildProof)?;
+    let circom = info_span!("witness", circuit = "clue")
+        .in_scope(|| builder.build())
+        .map_err(|_| Error::BuildProof)?;
 
     // the first five public inputs are actually public outputs
     let mut inputs = circom.get_public_inputs().ok_or(Error::GetPublicInputs)?;
@@ -45,51 +66,111 @@ pub fn generate_clue_proof(
         }
     }
 
+    let witness = started.elapsed();
     let mut rng = rand::thread_rng();
 
     // Generate the proof
-    let proof = Groth16::<Bn254, CircomReduction>::prove(&pk, circom, &mut rng)
+    let proof = info_span!("prove", circuit = "clue")
+        .in_scope(|| Groth16::<Bn254, CircomReduction>::prove(&pk, circom, &mut rng))
         .map_err(|_| Error::Prove)?;
+    let timings = ProofTimings {
+        witness,
+        proving: started.elapsed() - witness,
+    };
 
-    Ok((proof, clue))
+    Ok((proof, clue, timings))
 }
 
+/// Proves that the committed solution is a leaf of the merkle tree. The path has the arity of the
+/// circuit (binary paths convert into it): all children of every level go to `hashes`, and the
+/// position of the one on the path to `pathIndicators`, which for the binary circuit is 0 for the
+/// left child and 1 for the right one.
 pub fn generate_membership_proof(
-    solution: String,
+    secret: &RoundSecret,
     commitment: BigUint,
-    salt: BigUint,
-    path: Vec<MerklePathEntry>,
+    path: Vec<NaryPathEntry>,
     config: CircomConfig<Bn254>,
     pk: ProvingKey<Bn254>,
 ) -> Result<Proof<Bn254>, Error> {
-    let solution = string_to_bigints(solution);
+    let solution = string_to_bigints(secret.solution());
     let mut builder = CircomBuilder::new(config);
 
     let mut hashes = Vec::with_capacity(path.len());
     let mut indicators = Vec::with_capacity(path.len());
 
     for entry in path {
-        hashes.push(vec![entry.left.into(), entry.right.into()]);
-        indicators.push(match entry.on_path {
-            NodeType::Left => 0.into(),
-            NodeType::Right => 1.into(),
-        });
+        hashes.push(entry.children.into_iter().map(Into::into).collect());
+        indicators.push(entry.position.into());
     }
 
     builder.push_input("word", Inputs::BigIntVec(solution));
-    builder.push_input("salt", Inputs::BigInt(salt.into()));
+    builder.push_input("salt", Inputs::BigInt(secret.salt().into()));
     builder.push_input("cm", Inputs::BigInt(commitment.into()));
     builder.push_input("hashes", Inputs::BigIntVecVec(hashes));
     builder.push_input("pathIndicators", Inputs::BigIntVec(indicators));
 
-    let circom = builder.build().map_err(|_| Error::BuildProof)?;
+    let circom = info_span!("witness", circuit = "membership")
+        .in_scope(|| builder.build())
+        .map_err(|_| Error::BuildProof)?;
 
     // Generate the proof
     let mut rng = rand::thread_rng();
-    Groth16::<Bn254, CircomReduction>::prove(&pk, circom, &mut rng).map_err(|_| Error::Prove)
+    info_span!("prove", circuit = "membership")
+        .in_scope(|| Groth16::<Bn254, CircomReduction>::prove(&pk, circom, &mut rng))
+        .map_err(|_| Error::Prove)
+}
+
+/// Proves that the solution differs from the solutions of the previous rounds. The unused slots
+/// of the circuit are padded with zeros, and marked inactive.
+pub fn generate_freshness_proof(
+    secret: &RoundSecret,
+    commitment: BigUint,
+    previous: &[UsedWord],
+    config: CircomConfig<Bn254>,
+    pk: ProvingKey<Bn254>,
+) -> Result<Proof<Bn254>, Error> {
+    if previous.len() > FRESHNESS_ROUNDS {
+        return Err(Error::BuildProof);
+    }
+    let solution = string_to_bigints(secret.solution());
+    let mut builder = CircomBuilder::new(config);
+
+    let mut prev_cms = Vec::with_capacity(FRESHNESS_ROUNDS);
+    let mut active = Vec::with_capacity(FRESHNESS_ROUNDS);
+    let mut prev_words = Vec::with_capacity(FRESHNESS_ROUNDS);
+    let mut prev_salts = Vec::with_capacity(FRESHNESS_ROUNDS);
+    for used in previous {
+        prev_cms.push(used.commitment.clone().into());
+        active.push(1.into());
+        prev_words.push(string_to_bigints(used.secret.solution()));
+        prev_salts.push(used.secret.salt().into());
+    }
+    for _ in previous.len()..FRESHNESS_ROUNDS {
+        prev_cms.push(0.into());
+        active.push(0.into());
+        prev_words.push(vec![0.into(); 5]);
+        prev_salts.push(0.into());
+    }
+
+    builder.push_input("word", Inputs::BigIntVec(solution));
+    builder.push_input("salt", Inputs::BigInt(secret.salt().into()));
+    builder.push_input("cm", Inputs::BigInt(commitment.into()));
+    builder.push_input("prevCm", Inputs::BigIntVec(prev_cms));
+    builder.push_input("active", Inputs::BigIntVec(active));
+    builder.push_input("prevWord", Inputs::BigIntVecVec(prev_words));
+    builder.push_input("prevSalt", Inputs::BigIntVec(prev_salts));
+
+    let circom = info_span!("witness", circuit = "freshness")
+        .in_scope(|| builder.build())
+        .map_err(|_| Error::BuildProof)?;
+
+    let mut rng = rand::thread_rng();
+    info_span!("prove", circuit = "freshness")
+        .in_scope(|| Groth16::<Bn254, CircomReduction>::prove(&pk, circom, &mut rng))
+        .map_err(|_| Error::Prove)
 }
 
-fn string_to_bigints(s: String) -> Vec<BigInt> {
+fn string_to_bigints(s: &str) -> Vec<BigInt> {
     s.as_bytes().iter().map(|x| (x - 97).into()).collect()
 }
 
@@ -99,8 +180,8 @@ mod test {
 
     #[test]
     fn string_to_bigints_correct() {
-        let word: String = "hello".into();
-        let bigints = string_to_bigints(word.clone());
+        let word = "hello";
+        let bigints = string_to_bigints(word);
 
         assert_eq!(bigints.len(), word.len());
         assert_eq!(bigints[0], 7.into()); //  h
@@ -0,0 +1,162 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use tokio::sync::Semaphore;
+use tracing::Span;
+
+#[derive(Debug)]
+pub enum Error {
+    Closed,
+    JobFailed,
+}
+
+/// Runs the CPU heavy proving jobs on the blocking thread pool, at most `workers` of them at a
+/// time, so that proving doesn't starve the async runtime. The remaining jobs wait in a queue.
+pub struct ProverPool {
+    workers: usize,
+    // a permit is held by the job itself, so it's only released once the job ends, even if the
+    // request which queued it is dropped in the meantime
+    permits: Arc<Semaphore>,
+    queued: AtomicUsize,
+}
+
+/// Keeps the job counted as queued until it gets a worker, or until its request is dropped.
+struct QueuedGuard<'a>(&'a AtomicUsize);
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl ProverPool {
+    pub fn new(workers: usize) -> Self {
+        ProverPool {
+            workers,
+            permits: Arc::new(Semaphore::new(workers)),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Number of jobs waiting for a free worker.
+    pub fn queue_depth(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Waits for a free worker, and runs the job on it.
+    pub async fn run<T, F>(&self, job: F) -> Result<T, Error>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        let guard = QueuedGuard(&self.queued);
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .map_err(|_| Error::Closed)?;
+        drop(guard);
+
+        // keep the job within the request's span, it's lost when moving to another thread
+        let span = Span::current();
+        tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            span.in_scope(job)
+        })
+        .await
+        .map_err(|_| Error::JobFailed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::ProverPool;
+
+    #[tokio::test]
+    async fn jobs_wait_for_free_worker() {
+        let pool = Arc::new(ProverPool::new(1));
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+
+        let first = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(move || wait.recv().unwrap()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let second = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(|| 2).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(pool.queue_depth(), 1);
+
+        release.send(()).unwrap();
+        first.await.unwrap().unwrap();
+        assert_eq!(second.await.unwrap().unwrap(), 2);
+        assert_eq!(pool.queue_depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn dropped_request_keeps_worker() {
+        let pool = Arc::new(ProverPool::new(1));
+        let running = Arc::new(AtomicUsize::new(0));
+        let (started, wait_started) = tokio::sync::oneshot::channel::<()>();
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+
+        let first = tokio::spawn({
+            let (pool, running) = (pool.clone(), running.clone());
+            async move {
+                pool.run(move || {
+                    running.fetch_add(1, Ordering::SeqCst);
+                    started.send(()).unwrap();
+                    wait.recv().unwrap();
+                    running.fetch_sub(1, Ordering::SeqCst);
+                })
+                .await
+            }
+        });
+        wait_started.await.unwrap();
+        // the request goes away, but its job keeps running
+        first.abort();
+        assert!(first.await.unwrap_err().is_cancelled());
+
+        let second = tokio::spawn({
+            let (pool, running) = (pool.clone(), running.clone());
+            async move { pool.run(move || running.load(Ordering::SeqCst)).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(running.load(Ordering::SeqCst), 1);
+        assert_eq!(pool.queue_depth(), 1);
+
+        release.send(()).unwrap();
+        // the second job only ran once the first one ended
+        assert_eq!(second.await.unwrap().unwrap(), 0);
+        assert_eq!(running.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn dropped_job_leaves_queue() {
+        let pool = ProverPool::new(0);
+
+        let res = tokio::time::timeout(Duration::from_millis(10), pool.run(|| ())).await;
+
+        assert!(res.is_err());
+        assert_eq!(pool.queue_depth(), 0);
+    }
+}
@@ -0,0 +1,330 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+// above this many tracked clients, the least recently updated bucket is dropped
+const MAX_TRACKED_KEYS: usize = 100_000;
+
+/// Token bucket: holds up to `burst` tokens, each request takes one per proof it needs, and
+/// tokens come back at `per_minute` rate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BucketConfig {
+    pub burst: u32,
+    pub per_minute: u32,
+}
+
+impl BucketConfig {
+    fn refill_per_second(&self) -> f64 {
+        self.per_minute as f64 / 60.0
+    }
+}
+
+/// Which limit rejected the request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Ip,
+    Session,
+}
+
+impl Limit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Limit::Ip => "ip",
+            Limit::Session => "session",
+        }
+    }
+}
+
+/// Request rejected by the rate limiter, which can be retried after `retry_after`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rejected {
+    pub limit: Limit,
+    pub retry_after: Duration,
+}
+
+/// Storage of the token buckets. Kept behind a trait, so that the buckets can be moved to a store
+/// shared by many server instances.
+pub trait BucketStore: Send + Sync {
+    /// Takes `cost` tokens from the bucket under `key`. If there are not enough, takes none, and
+    /// returns how long it takes for the missing ones to come.
+    fn try_acquire(
+        &self,
+        key: &str,
+        config: &BucketConfig,
+        cost: u32,
+        now: Instant,
+    ) -> Result<(), Duration>;
+}
+
+struct Bucket {
+    // the limit the bucket was created for
+    config: BucketConfig,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens =
+            (self.tokens + elapsed * self.config.refill_per_second()).min(self.config.burst as f64);
+        self.updated = now;
+    }
+}
+
+#[derive(Default)]
+struct Buckets {
+    by_key: HashMap<String, Bucket>,
+    // the keys ordered by the last update, oldest first
+    by_update: BTreeSet<(Instant, String)>,
+}
+
+/// Buckets kept in the memory of a single server instance, at most `capacity` of them.
+pub struct InMemoryStore {
+    capacity: usize,
+    buckets: Mutex<Buckets>,
+}
+
+impl InMemoryStore {
+    pub fn new(capacity: usize) -> Self {
+        InMemoryStore {
+            capacity,
+            buckets: Mutex::default(),
+        }
+    }
+}
+
+impl Default for InMemoryStore {
+    fn default() -> Self {
+        InMemoryStore::new(MAX_TRACKED_KEYS)
+    }
+}
+
+impl BucketStore for InMemoryStore {
+    fn try_acquire(
+        &self,
+        key: &str,
+        config: &BucketConfig,
+        cost: u32,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock();
+        let Buckets { by_key, by_update } = &mut *buckets;
+        if by_key.len() >= self.capacity && !by_key.contains_key(key) {
+            // the client which was seen the longest time ago, its bucket is likely full anyway
+            if let Some((_, oldest)) = by_update.pop_first() {
+                by_key.remove(&oldest);
+            }
+        }
+
+        let bucket = by_key.entry(key.to_owned()).or_insert(Bucket {
+            config: *config,
+            tokens: config.burst as f64,
+            updated: now,
+        });
+        by_update.remove(&(bucket.updated, key.to_owned()));
+        bucket.refill(now);
+        by_update.insert((bucket.updated, key.to_owned()));
+
+        let cost = cost as f64;
+        if bucket.tokens >= cost {
+            bucket.tokens -= cost;
+            return Ok(());
+        }
+        let config = &bucket.config;
+        if config.per_minute == 0 {
+            // never refills, so retrying doesn't help for a long time
+            return Err(Duration::from_secs(60 * 60));
+        }
+        let missing = cost - bucket.tokens;
+        Err(Duration::from_secs_f64(
+            missing / config.refill_per_second(),
+        ))
+    }
+}
+
+/// Limits the rate of requests of every client IP and every session separately. Requests from
+/// the allowlisted IPs (e.g. internal bots) are never limited.
+pub struct RateLimiter {
+    per_ip: BucketConfig,
+    per_session: BucketConfig,
+    allowlist: HashSet<IpAddr>,
+    store: Box<dyn BucketStore>,
+}
+
+impl RateLimiter {
+    pub fn new(
+        per_ip: BucketConfig,
+        per_session: BucketConfig,
+        allowlist: HashSet<IpAddr>,
+        store: Box<dyn BucketStore>,
+    ) -> Self {
+        RateLimiter {
+            per_ip,
+            per_session,
+            allowlist,
+            store,
+        }
+    }
+
+    /// Charges the client `cost` tokens, one per proof the request needs.
+    pub fn check(&self, ip: IpAddr, session: Option<&str>, cost: u32) -> Result<(), Rejected> {
+        self.check_at(ip, session, cost, Instant::now())
+    }
+
+    fn check_at(
+        &self,
+        ip: IpAddr,
+        session: Option<&str>,
+        cost: u32,
+        now: Instant,
+    ) -> Result<(), Rejected> {
+        if self.allowlist.contains(&ip) {
+            return Ok(());
+        }
+
+        self.store
+            .try_acquire(&format!("ip:{}", ip), &self.per_ip, cost, now)
+            .map_err(|retry_after| Rejected {
+                limit: Limit::Ip,
+                retry_after,
+            })?;
+        if let Some(session) = session {
+            self.store
+                .try_acquire(
+                    &format!("session:{}", session),
+                    &self.per_session,
+                    cost,
+                    now,
+                )
+                .map_err(|retry_after| Rejected {
+                    limit: Limit::Session,
+                    retry_after,
+                })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        net::IpAddr,
+        time::{Duration, Instant},
+    };
+
+    use super::{BucketConfig, BucketStore, InMemoryStore, Limit, RateLimiter};
+
+    const IP: BucketConfig = BucketConfig {
+        burst: 3,
+        per_minute: 60,
+    };
+    const SESSION: BucketConfig = BucketConfig {
+        burst: 2,
+        per_minute: 30,
+    };
+
+    fn limiter(allowlist: &[&str]) -> RateLimiter {
+        RateLimiter::new(
+            IP,
+            SESSION,
+            allowlist.iter().map(|ip| ip.parse().unwrap()).collect(),
+            Box::<InMemoryStore>::default(),
+        )
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn burst_then_refill() {
+        let limiter = limiter(&[]);
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at(ip("10.0.0.1"), None, 1, now).is_ok());
+        }
+        let rejected = limiter.check_at(ip("10.0.0.1"), None, 1, now).unwrap_err();
+        assert_eq!(rejected.limit, Limit::Ip);
+        assert_eq!(rejected.retry_after, Duration::from_secs(1));
+
+        // other clients have their own buckets
+        assert!(limiter.check_at(ip("10.0.0.2"), None, 1, now).is_ok());
+
+        let later = now + Duration::from_secs(1);
+        assert!(limiter.check_at(ip("10.0.0.1"), None, 1, later).is_ok());
+        assert!(limiter.check_at(ip("10.0.0.1"), None, 1, later).is_err());
+    }
+
+    #[test]
+    fn sessions_limited_separately() {
+        let limiter = limiter(&[]);
+        let now = Instant::now();
+
+        assert!(limiter.check_at(ip("10.0.0.1"), Some("a"), 1, now).is_ok());
+        assert!(limiter.check_at(ip("10.0.0.2"), Some("a"), 1, now).is_ok());
+        let rejected = limiter
+            .check_at(ip("10.0.0.3"), Some("a"), 1, now)
+            .unwrap_err();
+        assert_eq!(rejected.limit, Limit::Session);
+        assert_eq!(rejected.retry_after, Duration::from_secs(2));
+
+        assert!(limiter.check_at(ip("10.0.0.3"), Some("b"), 1, now).is_ok());
+    }
+
+    #[test]
+    fn cost_charged_at_once() {
+        let limiter = limiter(&[]);
+        let now = Instant::now();
+
+        assert!(limiter.check_at(ip("10.0.0.1"), None, 2, now).is_ok());
+        // one token left, so a request costing two is rejected without taking it
+        let rejected = limiter.check_at(ip("10.0.0.1"), None, 2, now).unwrap_err();
+        assert_eq!(rejected.retry_after, Duration::from_secs(1));
+        assert!(limiter.check_at(ip("10.0.0.1"), None, 1, now).is_ok());
+    }
+
+    #[test]
+    fn least_recently_updated_evicted() {
+        let store = InMemoryStore::new(2);
+        let now = Instant::now();
+        let half = Duration::from_millis(500);
+
+        assert!(store.try_acquire("ip:1", &IP, 1, now).is_ok());
+        assert!(store
+            .try_acquire("session:a", &SESSION, 2, now + half)
+            .is_ok());
+        // full, so the bucket of ip:1 makes room
+        assert!(store.try_acquire("ip:2", &IP, 1, now + 2 * half).is_ok());
+        {
+            let buckets = store.buckets.lock();
+            let mut keys: Vec<_> = buckets.by_key.keys().cloned().collect();
+            keys.sort();
+            assert_eq!(keys, ["ip:2", "session:a"]);
+            assert_eq!(buckets.by_update.len(), 2);
+        }
+
+        // the session bucket still refills at its own rate, a quarter of a token in half a second
+        assert_eq!(
+            store.try_acquire("session:a", &SESSION, 1, now + 2 * half),
+            Err(Duration::from_millis(1500))
+        );
+    }
+
+    #[test]
+    fn allowlist_not_limited() {
+        let limiter = limiter(&["127.0.0.1"]);
+        let now = Instant::now();
+
+        for _ in 0..100 {
+            assert!(limiter
+                .check_at(ip("127.0.0.1"), Some("bot"), 1, now)
+                .is_ok());
+        }
+    }
+}
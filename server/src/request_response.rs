@@ -1,8 +1,25 @@
-use ark_bn254::Bn254;
+use std::str::FromStr;
+
+use ark_bn254::{Bn254, Fq, Fq2, G1Affine, G2Affine};
 use ark_groth16::Proof;
+use merkle::{MerklePathEntry, NodeType, NonMembershipWitness};
+use num_bigint::BigUint;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize)]
+use crate::{
+    game_state::RoundReveal, history::FreshnessProof, schedule::ScheduleProof,
+    seed_chain::SeedAnchor,
+};
+
+#[derive(Debug)]
+pub enum Error {
+    BadProofEncoding,
+    BadSeedEncoding,
+    BadScheduleEncoding,
+    BadAbsenceEncoding,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ProofSerializable {
     a: String,
     b: String,
@@ -15,19 +32,212 @@ pub struct GuessRequest {
     pub guess: String,
 }
 
-#[derive(Serialize)]
+/// Anchor of the seed chain selecting the round's word, hex encoded.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SeedAnchorSerializable {
+    pub anchor: String,
+    pub first_word_id: u32,
+}
+
+/// One level of a merkle path, `on_path` is 0 if the left node is on the path, 1 if the right.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PathEntrySerializable {
+    pub left: String,
+    pub right: String,
+    pub on_path: u8,
+}
+
+/// Proof that a guess is not in the dictionary: the adjacent leaves of the sorted tree around the
+/// hash of the guess, with their paths.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AbsenceProofSerializable {
+    pub low: String,
+    pub low_path: Vec<PathEntrySerializable>,
+    pub high: String,
+    pub high_path: Vec<PathEntrySerializable>,
+}
+
+/// Path of the round's commitment in the schedule, the leaves of which are the commitments of
+/// the rounds from `first_word_id` on.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScheduleProofSerializable {
+    pub root: String,
+    pub first_word_id: u32,
+    pub path: Vec<PathEntrySerializable>,
+}
+
+/// Proof that the round's word differs from the words committed in the `previous` rounds
+/// (their commitments, oldest first), checked with the freshness verification key.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FreshnessProofSerializable {
+    pub previous: Vec<String>,
+    pub proof: ProofSerializable,
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct StartResponse {
     pub word_id: u32,
     pub commitment: String,
+    pub root: String,
     pub proof: ProofSerializable,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed_anchor: Option<SeedAnchorSerializable>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<ScheduleProofSerializable>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freshness: Option<FreshnessProofSerializable>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize)]
 pub struct GuessResponse {
     pub colors: [u8; 5],
     pub proof: ProofSerializable,
 }
 
+/// Rejection of a guess which is not an acceptable word, with the proof that it's not in the
+/// dictionary, unless it's not a word at all.
+#[derive(Serialize, Deserialize)]
+pub struct NoSuchWordResponse {
+    pub error: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub absence: Option<AbsenceProofSerializable>,
+}
+
+/// Root of the dictionary, the sorted merkle tree over all acceptable guesses.
+#[derive(Serialize, Deserialize)]
+pub struct DictionaryResponse {
+    pub root: String,
+    pub words: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct GuessBatchRequest {
+    pub word_id: u32,
+    pub guesses: Vec<String>,
+}
+
+/// Result of one guess of a batch: the clue and its proof, or why there is none.
+#[derive(Serialize, Deserialize)]
+pub struct GuessBatchItem {
+    pub guess: String,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub response: Option<GuessResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub absence: Option<AbsenceProofSerializable>,
+}
+
+/// Results in the order of the requested guesses.
+#[derive(Serialize, Deserialize)]
+pub struct GuessBatchResponse {
+    pub word_id: u32,
+    pub results: Vec<GuessBatchItem>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChallengeRequest {
+    pub word: String,
+}
+
+/// A challenge game, played like a round, but against a word chosen by a player.
+#[derive(Serialize, Deserialize)]
+pub struct ChallengeResponse {
+    pub game_id: String,
+    pub commitment: String,
+    pub root: String,
+    pub proof: ProofSerializable,
+}
+
+/// A practice game, played like a round, but against a random word of the session's own.
+#[derive(Serialize, Deserialize)]
+pub struct PracticeResponse {
+    pub session_id: String,
+    pub commitment: String,
+    pub root: String,
+    pub proof: ProofSerializable,
+}
+
+/// A guess in a private game, a challenge or a practice session, identified by the path.
+#[derive(Serialize, Deserialize)]
+pub struct PrivateGuessRequest {
+    pub guess: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RevealResponse {
+    pub word_id: u32,
+    pub solution: String,
+    pub salt: String,
+    // the seed chain element which selected the solution, hex encoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selection_seed: Option<String>,
+    // how many words derived from the seed were skipped as recently used
+    #[serde(default)]
+    pub selection_attempt: u32,
+    // openings of the rounds before, oldest first: the words the selection skipped come from
+    // them, and the freshness proof is against their commitments
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub previous: Vec<PreviousRound>,
+}
+
+/// Opening of the commitment of a round played before the revealed one.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PreviousRound {
+    pub word_id: u32,
+    pub solution: String,
+    pub salt: String,
+}
+
+/// Messages the server sends over the websocket (`/ws`), tagged by `type`.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    // sent on connecting, and whenever a new round starts, with the latest revealed round
+    Round {
+        round: Box<StartResponse>,
+        previous: Option<RevealResponse>,
+        next_rotation_timestamp: i64,
+    },
+    // sent when a round is closed, which is after its grace period
+    Reveal(RevealResponse),
+    Countdown {
+        word_id: u32,
+        seconds_left: i64,
+    },
+    Clue {
+        word_id: u32,
+        guess: String,
+        colors: [u8; 5],
+        proof: ProofSerializable,
+    },
+    Error {
+        // the guess which failed, if any
+        guess: Option<String>,
+        message: String,
+        // seconds to wait, if rate limited
+        #[serde(skip_serializing_if = "Option::is_none")]
+        retry_after: Option<u64>,
+        // proof that the guess is not in the dictionary, if rejected as such
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        absence: Option<AbsenceProofSerializable>,
+    },
+}
+
+/// Messages the client sends over the websocket, tagged by `type`.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Guess(GuessRequest),
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StatusResponse {
+    pub word_id: u32,
+    pub prover_workers: usize,
+    pub prover_queue_depth: usize,
+}
+
 impl From<Proof<Bn254>> for ProofSerializable {
     fn from(proof: Proof<Bn254>) -> ProofSerializable {
         ProofSerializable {
@@ -37,3 +247,275 @@ impl From<Proof<Bn254>> for ProofSerializable {
         }
     }
 }
+
+/// Parses the proof back from the `Display` format of the curve points, which is
+/// "(x, y)" for G1 and "(QuadExtField(x0 + x1 * u), QuadExtField(y0 + y1 * u))" for G2.
+impl TryFrom<&ProofSerializable> for Proof<Bn254> {
+    type Error = Error;
+
+    fn try_from(proof: &ProofSerializable) -> Result<Self, Error> {
+        Ok(Proof {
+            a: parse_g1(&proof.a)?,
+            b: parse_g2(&proof.b)?,
+            c: parse_g1(&proof.c)?,
+        })
+    }
+}
+
+impl From<SeedAnchor> for SeedAnchorSerializable {
+    fn from(anchor: SeedAnchor) -> SeedAnchorSerializable {
+        SeedAnchorSerializable {
+            anchor: hex::encode(anchor.anchor),
+            first_word_id: anchor.first_word_id,
+        }
+    }
+}
+
+impl TryFrom<&SeedAnchorSerializable> for SeedAnchor {
+    type Error = Error;
+
+    fn try_from(anchor: &SeedAnchorSerializable) -> Result<Self, Error> {
+        Ok(SeedAnchor {
+            anchor: parse_seed(&anchor.anchor)?,
+            first_word_id: anchor.first_word_id,
+        })
+    }
+}
+
+impl From<ScheduleProof> for ScheduleProofSerializable {
+    fn from(proof: ScheduleProof) -> ScheduleProofSerializable {
+        ScheduleProofSerializable {
+            root: proof.root.to_string(),
+            first_word_id: proof.first_word_id,
+            path: proof.path.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<MerklePathEntry> for PathEntrySerializable {
+    fn from(entry: MerklePathEntry) -> PathEntrySerializable {
+        PathEntrySerializable {
+            left: entry.left.to_string(),
+            right: entry.right.to_string(),
+            on_path: match entry.on_path {
+                NodeType::Left => 0,
+                NodeType::Right => 1,
+            },
+        }
+    }
+}
+
+impl From<NonMembershipWitness> for AbsenceProofSerializable {
+    fn from(proof: NonMembershipWitness) -> AbsenceProofSerializable {
+        AbsenceProofSerializable {
+            low: proof.low.to_string(),
+            low_path: proof.low_path.into_iter().map(Into::into).collect(),
+            high: proof.high.to_string(),
+            high_path: proof.high_path.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<&AbsenceProofSerializable> for NonMembershipWitness {
+    type Error = Error;
+
+    fn try_from(proof: &AbsenceProofSerializable) -> Result<Self, Error> {
+        let parse = |s: &str| BigUint::from_str(s).map_err(|_| Error::BadAbsenceEncoding);
+        let path = |path: &[_]| parse_path(path).ok_or(Error::BadAbsenceEncoding);
+        Ok(NonMembershipWitness {
+            low: parse(&proof.low)?,
+            low_path: path(&proof.low_path)?,
+            high: parse(&proof.high)?,
+            high_path: path(&proof.high_path)?,
+        })
+    }
+}
+
+impl From<RoundReveal> for RevealResponse {
+    fn from(reveal: RoundReveal) -> RevealResponse {
+        RevealResponse {
+            word_id: reveal.word_id,
+            solution: reveal.solution,
+            salt: reveal.salt.to_string(),
+            selection_seed: reveal.selection_seed.map(hex::encode),
+            selection_attempt: reveal.selection_attempt,
+            previous: reveal
+                .previous
+                .iter()
+                .map(|used| PreviousRound {
+                    word_id: used.word_id,
+                    solution: used.secret.solution().into(),
+                    salt: used.secret.salt().to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<FreshnessProof> for FreshnessProofSerializable {
+    fn from(proof: FreshnessProof) -> FreshnessProofSerializable {
+        FreshnessProofSerializable {
+            previous: proof.previous.iter().map(ToString::to_string).collect(),
+            proof: proof.proof.into(),
+        }
+    }
+}
+
+impl TryFrom<&ScheduleProofSerializable> for ScheduleProof {
+    type Error = Error;
+
+    fn try_from(proof: &ScheduleProofSerializable) -> Result<Self, Error> {
+        let parse = |s: &str| BigUint::from_str(s).map_err(|_| Error::BadScheduleEncoding);
+        Ok(ScheduleProof {
+            root: parse(&proof.root)?,
+            first_word_id: proof.first_word_id,
+            path: parse_path(&proof.path).ok_or(Error::BadScheduleEncoding)?,
+        })
+    }
+}
+
+// the merkle path, if all its numbers and directions are correct
+fn parse_path(path: &[PathEntrySerializable]) -> Option<Vec<MerklePathEntry>> {
+    let parse = |s: &str| BigUint::from_str(s).ok();
+    path.iter()
+        .map(|entry| {
+            Some(MerklePathEntry {
+                left: parse(&entry.left)?,
+                right: parse(&entry.right)?,
+                on_path: match entry.on_path {
+                    0 => NodeType::Left,
+                    1 => NodeType::Right,
+                    _ => return None,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Parses a hex encoded seed chain element.
+pub fn parse_seed(s: &str) -> Result<[u8; 32], Error> {
+    let mut seed = [0; 32];
+    hex::decode_to_slice(s, &mut seed).map_err(|_| Error::BadSeedEncoding)?;
+    Ok(seed)
+}
+
+fn parse_g1(s: &str) -> Result<G1Affine, Error> {
+    let (x, y) = split_point(s)?;
+    let point = G1Affine::new_unchecked(parse_fq(x)?, parse_fq(y)?);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::BadProofEncoding);
+    }
+    Ok(point)
+}
+
+fn parse_g2(s: &str) -> Result<G2Affine, Error> {
+    let (x, y) = split_point(s)?;
+    let point = G2Affine::new_unchecked(parse_fq2(x)?, parse_fq2(y)?);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::BadProofEncoding);
+    }
+    Ok(point)
+}
+
+fn split_point(s: &str) -> Result<(&str, &str), Error> {
+    s.strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .and_then(|s| s.split_once(", "))
+        .ok_or(Error::BadProofEncoding)
+}
+
+fn parse_fq2(s: &str) -> Result<Fq2, Error> {
+    let (c0, c1) = s
+        .strip_prefix("QuadExtField(")
+        .and_then(|s| s.strip_suffix(" * u)"))
+        .and_then(|s| s.split_once(" + "))
+        .ok_or(Error::BadProofEncoding)?;
+    Ok(Fq2::new(parse_fq(c0)?, parse_fq(c1)?))
+}
+
+fn parse_fq(s: &str) -> Result<Fq, Error> {
+    // leading zeros are trimmed when displaying, so zero is displayed as an empty string
+    if s.is_empty() {
+        return Ok(Fq::from(0));
+    }
+    Fq::from_str(s).map_err(|_| Error::BadProofEncoding)
+}
+
+#[cfg(test)]
+mod test {
+    use ark_bn254::{Bn254, G1Affine, G2Affine};
+    use ark_ec::AffineRepr;
+    use ark_groth16::Proof;
+
+    use super::{ClientMessage, GuessBatchItem, GuessResponse, ProofSerializable, ServerMessage};
+    use crate::test_util::dummy_proof;
+
+    #[test]
+    fn proof_roundtrip() {
+        let proof = Proof::<Bn254> {
+            a: G1Affine::generator(),
+            b: G2Affine::generator(),
+            c: (G1Affine::generator() + G1Affine::generator()).into(),
+        };
+
+        let serializable: ProofSerializable = proof.clone().into();
+        let parsed = Proof::<Bn254>::try_from(&serializable).expect("proof should parse");
+
+        assert_eq!(parsed, proof);
+    }
+
+    #[test]
+    fn proof_not_on_curve() {
+        let mut serializable: ProofSerializable = dummy_proof().into();
+        serializable.a = "(1, 3)".into();
+
+        assert!(Proof::<Bn254>::try_from(&serializable).is_err());
+    }
+
+    #[test]
+    fn batch_item_json() {
+        let ok = GuessBatchItem {
+            guess: "crane".into(),
+            response: Some(GuessResponse {
+                colors: [2, 0, 1, 0, 0],
+                proof: dummy_proof().into(),
+            }),
+            error: None,
+            absence: None,
+        };
+        let failed = GuessBatchItem {
+            guess: "zzzzz".into(),
+            response: None,
+            error: Some("word does not exist".into()),
+            absence: None,
+        };
+
+        let ok = serde_json::to_value(ok).unwrap();
+        let failed = serde_json::to_value(failed).unwrap();
+
+        assert_eq!(ok["colors"], serde_json::json!([2, 0, 1, 0, 0]));
+        assert!(ok["proof"]["a"].is_string());
+        assert!(ok.get("error").is_none());
+        assert_eq!(
+            failed,
+            serde_json::json!({"guess": "zzzzz", "error": "word does not exist"})
+        );
+    }
+
+    #[test]
+    fn ws_messages_tagged() {
+        let guess: ClientMessage =
+            serde_json::from_str(r#"{"type": "guess", "word_id": 3, "guess": "crane"}"#).unwrap();
+        let ClientMessage::Guess(guess) = guess;
+        assert_eq!((guess.word_id, guess.guess.as_str()), (3, "crane"));
+
+        let countdown = ServerMessage::Countdown {
+            word_id: 3,
+            seconds_left: 120,
+        };
+        assert_eq!(
+            serde_json::to_value(countdown).unwrap(),
+            serde_json::json!({"type": "countdown", "word_id": 3, "seconds_left": 120})
+        );
+    }
+}
@@ -0,0 +1,446 @@
+use std::{fs, io, path::PathBuf, sync::Arc};
+
+use ark_bn254::Bn254;
+use ark_groth16::Proof;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use num_bigint::BigUint;
+use zeroize::Zeroizing;
+
+use crate::{
+    game_state::MutableState,
+    history::{RoundHistory, UsedWord, FRESHNESS_ROUNDS},
+    schedule::{SchedulePlan, ScheduleRange},
+    secret::{RoundSecret, StateKey},
+    seed_chain::{SeedAnchor, SeedChain},
+};
+
+/// Bumped whenever the layout of the persisted round changes.
+const FORMAT_VERSION: u8 = 4;
+// rounds persisted before the history of the recent rounds, still loaded
+const FORMAT_VERSION_NO_HISTORY: u8 = 3;
+// rounds persisted before the schedule, still loaded
+const FORMAT_VERSION_UNSCHEDULED: u8 = 2;
+// rounds persisted before the word selection was verifiable, still loaded
+const FORMAT_VERSION_UNSELECTED: u8 = 1;
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Encrypt,
+    // wrong key, or the file was tampered with
+    Decrypt,
+    BadFormat,
+}
+
+/// The persisted round, the seed chain selecting the words of the following rounds, the
+/// schedule the round belongs to, and the recent rounds. The chain and the schedule are missing
+/// from files of the previous formats, and so is the history, which then is empty.
+pub struct PersistedState {
+    pub game_state: MutableState,
+    pub seed_chain: Option<SeedChain>,
+    pub schedule_plan: Option<SchedulePlan>,
+    pub history: Vec<UsedWord>,
+}
+
+/// Persists the current round, so that a restarted server continues the round instead of
+/// starting a new one. The file is encrypted and authenticated with the key supplied at startup,
+/// and the plaintext is only ever kept in zeroized buffers.
+///
+/// Plaintext layout: version (1) | word_id (4, LE) | solution (5) | salt (32, LE) |
+/// commitment (32, LE) | has selection (1) | [selection seed (32) | selection attempt (4, LE) |
+/// anchor (32) | anchor first word_id (4, LE)] | chain seed (32) | chain first word_id (4, LE) |
+/// chain length (4, LE) | schedule first word_id (4, LE) | schedule length (4, LE) |
+/// selection attempts (schedule length * 4, LE) | history length (4, LE) |
+/// history rounds (history length * (word_id (4, LE) | solution (5) | salt (32, LE) |
+/// commitment (32, LE))) | compressed membership proof.
+/// File layout: nonce (12) | ciphertext.
+pub struct RoundStore {
+    path: PathBuf,
+    cipher: ChaCha20Poly1305,
+}
+
+impl RoundStore {
+    pub fn new(path: impl Into<PathBuf>, key: &StateKey) -> Self {
+        RoundStore {
+            path: path.into(),
+            cipher: ChaCha20Poly1305::new(Key::from_slice(key.as_bytes())),
+        }
+    }
+
+    pub fn save(
+        &self,
+        state: &MutableState,
+        seed_chain: &SeedChain,
+        schedule_plan: &SchedulePlan,
+        history: &RoundHistory,
+    ) -> Result<(), Error> {
+        let mut plaintext = Zeroizing::new(Vec::with_capacity(
+            384 + 4 * schedule_plan.attempts.len() + 73 * FRESHNESS_ROUNDS,
+        ));
+        plaintext.push(FORMAT_VERSION);
+        plaintext.extend_from_slice(&state.word_id.to_le_bytes());
+        plaintext.extend_from_slice(state.secret.solution_bytes());
+        plaintext.extend_from_slice(state.secret.salt_bytes());
+        plaintext.extend_from_slice(&to_32_bytes(&state.commitment)?);
+        match (state.secret.selection_seed(), &state.seed_anchor) {
+            (Some(seed), Some(anchor)) => {
+                plaintext.push(1);
+                plaintext.extend_from_slice(seed);
+                plaintext.extend_from_slice(&state.secret.selection_attempt().to_le_bytes());
+                plaintext.extend_from_slice(&anchor.anchor);
+                plaintext.extend_from_slice(&anchor.first_word_id.to_le_bytes());
+            }
+            _ => plaintext.push(0),
+        }
+        plaintext.extend_from_slice(seed_chain.seed_bytes());
+        plaintext.extend_from_slice(&seed_chain.anchor().first_word_id.to_le_bytes());
+        plaintext.extend_from_slice(&seed_chain.length().to_le_bytes());
+        let range = schedule_plan.range;
+        if schedule_plan.attempts.len() != range.length as usize {
+            return Err(Error::BadFormat);
+        }
+        plaintext.extend_from_slice(&range.first_word_id.to_le_bytes());
+        plaintext.extend_from_slice(&range.length.to_le_bytes());
+        for attempt in &schedule_plan.attempts {
+            plaintext.extend_from_slice(&attempt.to_le_bytes());
+        }
+        let rounds: Vec<_> = history.rounds().collect();
+        plaintext.extend_from_slice(&(rounds.len() as u32).to_le_bytes());
+        for used in rounds {
+            plaintext.extend_from_slice(&used.word_id.to_le_bytes());
+            plaintext.extend_from_slice(used.secret.solution_bytes());
+            plaintext.extend_from_slice(used.secret.salt_bytes());
+            plaintext.extend_from_slice(&to_32_bytes(&used.commitment)?);
+        }
+        state
+            .membership_proof
+            .serialize_compressed(&mut *plaintext)
+            .map_err(|_| Error::BadFormat)?;
+
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| Error::Encrypt)?;
+
+        let mut file = nonce.to_vec();
+        file.extend_from_slice(&ciphertext);
+
+        let tmp_path = self.path.with_extension("tmp");
+        fs::write(&tmp_path, file).map_err(Error::Io)?;
+        fs::rename(&tmp_path, &self.path).map_err(Error::Io)
+    }
+
+    pub fn load(&self) -> Result<Option<PersistedState>, Error> {
+        let file = match fs::read(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::Io(e)),
+        };
+        if file.len() < NONCE_LEN {
+            return Err(Error::BadFormat);
+        }
+        let (nonce, ciphertext) = file.split_at(NONCE_LEN);
+        let plaintext = Zeroizing::new(
+            self.cipher
+                .decrypt(Nonce::from_slice(nonce), ciphertext)
+                .map_err(|_| Error::Decrypt)?,
+        );
+
+        let mut bytes = plaintext.as_slice();
+        let [version] = take(&mut bytes)?;
+        if ![
+            FORMAT_VERSION,
+            FORMAT_VERSION_NO_HISTORY,
+            FORMAT_VERSION_UNSCHEDULED,
+            FORMAT_VERSION_UNSELECTED,
+        ]
+        .contains(&version)
+        {
+            return Err(Error::BadFormat);
+        }
+        let word_id = u32::from_le_bytes(take(&mut bytes)?);
+        let solution: [u8; 5] = take(&mut bytes)?;
+        let salt = Zeroizing::new(take::<32>(&mut bytes)?);
+        let commitment = BigUint::from_bytes_le(&take::<32>(&mut bytes)?);
+        if !solution.iter().all(|c| c.is_ascii_lowercase()) {
+            return Err(Error::BadFormat);
+        }
+        let mut secret = RoundSecret::from_bytes(solution, *salt);
+
+        let mut seed_anchor = None;
+        let mut seed_chain = None;
+        let mut schedule_plan = None;
+        let mut history = Vec::new();
+        if version != FORMAT_VERSION_UNSELECTED {
+            match take(&mut bytes)? {
+                [0] => {}
+                [1] => {
+                    let seed = Zeroizing::new(take::<32>(&mut bytes)?);
+                    let attempt = match version {
+                        FORMAT_VERSION => u32::from_le_bytes(take(&mut bytes)?),
+                        _ => 0,
+                    };
+                    secret = secret.with_selection(*seed, attempt);
+                    seed_anchor = Some(SeedAnchor {
+                        anchor: take(&mut bytes)?,
+                        first_word_id: u32::from_le_bytes(take(&mut bytes)?),
+                    });
+                }
+                _ => return Err(Error::BadFormat),
+            }
+            let chain_seed = Zeroizing::new(take::<32>(&mut bytes)?);
+            let first_word_id = u32::from_le_bytes(take(&mut bytes)?);
+            let length = u32::from_le_bytes(take(&mut bytes)?);
+            seed_chain = Some(SeedChain::from_bytes(*chain_seed, first_word_id, length));
+        }
+        if version == FORMAT_VERSION || version == FORMAT_VERSION_NO_HISTORY {
+            let range = ScheduleRange {
+                first_word_id: u32::from_le_bytes(take(&mut bytes)?),
+                length: u32::from_le_bytes(take(&mut bytes)?),
+            };
+            // the schedules before the history never skipped a word
+            let attempts = match version {
+                FORMAT_VERSION => (0..range.length)
+                    .map(|_| take(&mut bytes).map(u32::from_le_bytes))
+                    .collect::<Result<_, _>>()?,
+                _ => vec![0; range.length as usize],
+            };
+            schedule_plan = Some(SchedulePlan { range, attempts });
+        }
+        if version == FORMAT_VERSION {
+            let rounds = u32::from_le_bytes(take(&mut bytes)?);
+            for _ in 0..rounds {
+                let word_id = u32::from_le_bytes(take(&mut bytes)?);
+                let solution: [u8; 5] = take(&mut bytes)?;
+                let salt = Zeroizing::new(take::<32>(&mut bytes)?);
+                let commitment = BigUint::from_bytes_le(&take::<32>(&mut bytes)?);
+                if !solution.iter().all(|c| c.is_ascii_lowercase()) {
+                    return Err(Error::BadFormat);
+                }
+                history.push(UsedWord {
+                    word_id,
+                    secret: Arc::new(RoundSecret::from_bytes(solution, *salt)),
+                    commitment,
+                });
+            }
+        }
+
+        let membership_proof =
+            Proof::<Bn254>::deserialize_compressed(bytes).map_err(|_| Error::BadFormat)?;
+
+        Ok(Some(PersistedState {
+            game_state: MutableState {
+                word_id,
+                secret: Arc::new(secret),
+                commitment,
+                membership_proof,
+                seed_anchor,
+                schedule_proof: None,
+                freshness_proof: None,
+                previous: Arc::new([]),
+            },
+            seed_chain,
+            schedule_plan,
+            history,
+        }))
+    }
+}
+
+/// Splits off the next `N` bytes.
+fn take<const N: usize>(bytes: &mut &[u8]) -> Result<[u8; N], Error> {
+    if bytes.len() < N {
+        return Err(Error::BadFormat);
+    }
+    let (head, tail) = bytes.split_at(N);
+    *bytes = tail;
+    Ok(head.try_into().expect("length checked"))
+}
+
+fn to_32_bytes(n: &BigUint) -> Result<[u8; 32], Error> {
+    let bytes = n.to_bytes_le();
+    if bytes.len() > 32 {
+        return Err(Error::BadFormat);
+    }
+    let mut out = [0; 32];
+    out[..bytes.len()].copy_from_slice(&bytes);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use num_bigint::BigUint;
+
+    use ark_serialize::CanonicalSerialize;
+    use chacha20poly1305::{
+        aead::{Aead, AeadCore, OsRng},
+        ChaCha20Poly1305,
+    };
+
+    use super::{to_32_bytes, Error, RoundStore, FORMAT_VERSION_UNSELECTED};
+    use crate::{
+        game_state::MutableState,
+        history::{RoundHistory, UsedWord},
+        schedule::{SchedulePlan, ScheduleRange},
+        secret::{RoundSecret, StateKey},
+        seed_chain::SeedChain,
+        test_util::dummy_game,
+    };
+
+    fn chain() -> SeedChain {
+        SeedChain::from_bytes([9; 32], 40, 10)
+    }
+
+    fn schedule() -> SchedulePlan {
+        SchedulePlan {
+            range: ScheduleRange {
+                first_word_id: 40,
+                length: 5,
+            },
+            attempts: vec![0, 0, 3, 0, 1],
+        }
+    }
+
+    fn history() -> RoundHistory {
+        RoundHistory::new((40..42).map(|word_id| UsedWord {
+            word_id,
+            secret: Arc::new(RoundSecret::new("slate", &BigUint::from(word_id)).unwrap()),
+            commitment: BigUint::from(word_id + 100),
+        }))
+    }
+
+    fn state() -> MutableState {
+        let secret = RoundSecret::new("crane", &BigUint::from(987654321u32)).unwrap();
+        MutableState {
+            secret: Arc::new(secret.with_selection(chain().element(42).unwrap(), 3)),
+            commitment: BigUint::from(1234u32),
+            seed_anchor: Some(chain().anchor()),
+            ..dummy_game(42)
+        }
+    }
+
+    fn path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("round_store_{}_{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn save_load_roundtrip() {
+        let key = StateKey::from_hex(&"11".repeat(32)).unwrap();
+        let store = RoundStore::new(path("roundtrip"), &key);
+
+        store
+            .save(&state(), &chain(), &schedule(), &history())
+            .unwrap();
+        let persisted = store.load().unwrap().expect("state should be persisted");
+        let loaded = persisted.game_state;
+
+        assert_eq!(loaded.word_id, 42);
+        assert_eq!(loaded.secret.solution(), "crane");
+        assert_eq!(loaded.secret.salt(), BigUint::from(987654321u32));
+        assert_eq!(loaded.commitment, BigUint::from(1234u32));
+        assert_eq!(loaded.membership_proof, state().membership_proof);
+        assert_eq!(loaded.secret.selection_seed(), chain().element(42).as_ref());
+        assert_eq!(loaded.secret.selection_attempt(), 3);
+        assert_eq!(loaded.seed_anchor, Some(chain().anchor()));
+        let seed_chain = persisted.seed_chain.expect("chain should be persisted");
+        assert_eq!(seed_chain.anchor(), chain().anchor());
+        assert_eq!(seed_chain.element(45), chain().element(45));
+        assert_eq!(persisted.schedule_plan, Some(schedule()));
+        let words: Vec<_> = persisted
+            .history
+            .iter()
+            .map(|used| {
+                (
+                    used.word_id,
+                    used.secret.solution(),
+                    used.commitment.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            words,
+            [
+                (40, "slate", BigUint::from(140u32)),
+                (41, "slate", BigUint::from(141u32))
+            ]
+        );
+        assert_eq!(persisted.history[1].secret.salt(), BigUint::from(41u32));
+    }
+
+    #[test]
+    fn plaintext_not_on_disk() {
+        let key = StateKey::from_hex(&"22".repeat(32)).unwrap();
+        let path = path("plaintext");
+        let store = RoundStore::new(&path, &key);
+
+        store
+            .save(&state(), &chain(), &schedule(), &history())
+            .unwrap();
+        let file = std::fs::read(&path).unwrap();
+
+        assert!(!file.windows(5).any(|w| w == b"crane"));
+    }
+
+    #[test]
+    fn wrong_key_rejected() {
+        let path = path("wrong_key");
+        let key = StateKey::from_hex(&"33".repeat(32)).unwrap();
+        RoundStore::new(&path, &key)
+            .save(&state(), &chain(), &schedule(), &history())
+            .unwrap();
+
+        let other_key = StateKey::from_hex(&"44".repeat(32)).unwrap();
+        let res = RoundStore::new(&path, &other_key).load();
+
+        assert!(matches!(res, Err(Error::Decrypt)));
+    }
+
+    #[test]
+    fn previous_format_loaded_without_chain() {
+        let key = StateKey::from_hex(&"66".repeat(32)).unwrap();
+        let path = path("previous_format");
+        let store = RoundStore::new(&path, &key);
+
+        let state = state();
+        let mut plaintext = vec![FORMAT_VERSION_UNSELECTED];
+        plaintext.extend_from_slice(&state.word_id.to_le_bytes());
+        plaintext.extend_from_slice(state.secret.solution_bytes());
+        plaintext.extend_from_slice(state.secret.salt_bytes());
+        plaintext.extend_from_slice(&to_32_bytes(&state.commitment).unwrap());
+        state
+            .membership_proof
+            .serialize_compressed(&mut plaintext)
+            .unwrap();
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut file = nonce.to_vec();
+        file.extend(store.cipher.encrypt(&nonce, plaintext.as_slice()).unwrap());
+        std::fs::write(&path, file).unwrap();
+
+        let persisted = store.load().unwrap().expect("state should be persisted");
+
+        assert_eq!(persisted.game_state.word_id, 42);
+        assert_eq!(persisted.game_state.secret.solution(), "crane");
+        assert!(persisted.game_state.secret.selection_seed().is_none());
+        assert!(persisted.game_state.seed_anchor.is_none());
+        assert!(persisted.seed_chain.is_none());
+        assert!(persisted.schedule_plan.is_none());
+        assert!(persisted.history.is_empty());
+    }
+
+    #[test]
+    fn missing_file_is_none() {
+        let key = StateKey::from_hex(&"55".repeat(32)).unwrap();
+
+        assert!(RoundStore::new(path("missing"), &key)
+            .load()
+            .unwrap()
+            .is_none());
+    }
+}
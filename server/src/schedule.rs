@@ -0,0 +1,416 @@
+//! Precommitted schedule of the rounds. The commitments of the next `length` rounds are fixed
+//! in advance by the seed chain, and a merkle tree over them is built when the schedule starts.
+//! Its root is published right away (in the audit log, and with every round), and each round
+//! comes with the path of its commitment, so the server can't swap the word of a future round
+//! after seeing how the players do. Words used in the recent rounds are skipped when the schedule
+//! is planned, so the rounds of a schedule are planned in order, starting from the history.
+
+use merkle::{MerklePathEntry, MerkleTree};
+use num_bigint::BigUint;
+use tracing::info;
+
+use crate::{
+    game_state::PlannedGame,
+    history::{RoundHistory, FRESHNESS_ROUNDS},
+    logging::AUDIT_TARGET,
+    proofs::HASH_PARAMS,
+    secret::{self, RoundSecret},
+    seed_chain::{word_index, SeedChain},
+    word_bank::WordBank,
+};
+
+/// Gives up skipping the recent words, which only happens with too few solution words.
+pub const MAX_SELECTION_ATTEMPTS: u32 = 1024;
+
+#[derive(Debug)]
+pub enum Error {
+    Merkle(merkle::Error),
+    Secret(secret::Error),
+}
+
+/// Shows that the round's commitment is the leaf `word_id - first_word_id` of the schedule.
+#[derive(Clone, Debug)]
+pub struct ScheduleProof {
+    pub root: BigUint,
+    pub first_word_id: u32,
+    pub path: Vec<MerklePathEntry>,
+}
+
+/// The rounds a schedule commits to, persisted to rebuild the same schedule after a restart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScheduleRange {
+    pub first_word_id: u32,
+    pub length: u32,
+}
+
+impl ScheduleRange {
+    pub fn covers(&self, word_id: u32) -> bool {
+        word_id
+            .checked_sub(self.first_word_id)
+            .map_or(false, |i| i < self.length)
+    }
+}
+
+/// The rounds a schedule commits to, and the selection attempt of each of their words. Persisted
+/// to rebuild the same schedule after a restart, when the history it was planned with is gone.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchedulePlan {
+    pub range: ScheduleRange,
+    pub attempts: Vec<u32>,
+}
+
+impl SchedulePlan {
+    /// Selects the words of the rounds in the range, each one skipping the words of the
+    /// `FRESHNESS_ROUNDS` rounds before it, from the history and then from the range itself.
+    fn new(
+        seed_chain: &SeedChain,
+        word_bank: &WordBank,
+        range: ScheduleRange,
+        history: &RoundHistory,
+    ) -> Self {
+        let words = word_bank.solution_words();
+        let mut recent: Vec<(u32, String)> = history
+            .before(range.first_word_id)
+            .into_iter()
+            .map(|used| (used.word_id, used.secret.solution().to_string()))
+            .collect();
+        let mut attempts = Vec::with_capacity(range.length as usize);
+
+        for word_id in range.first_word_id..range.first_word_id + range.length {
+            let first = word_id.saturating_sub(FRESHNESS_ROUNDS as u32);
+            recent.retain(|(used, _)| *used >= first);
+            let element = seed_chain
+                .element(word_id)
+                .expect("the chain covers the round");
+            let (attempt, idx) = (0..MAX_SELECTION_ATTEMPTS)
+                .map(|attempt| (attempt, word_index(&element, word_id, attempt, words.len())))
+                .find(|(_, idx)| recent.iter().all(|(_, word)| *word != words[*idx]))
+                .unwrap_or_else(|| {
+                    let attempt = MAX_SELECTION_ATTEMPTS - 1;
+                    (attempt, word_index(&element, word_id, attempt, words.len()))
+                });
+            attempts.push(attempt);
+            recent.push((word_id, words[idx].clone()));
+        }
+
+        SchedulePlan { range, attempts }
+    }
+
+    fn attempt(&self, word_id: u32) -> Option<u32> {
+        if !self.range.covers(word_id) {
+            return None;
+        }
+        self.attempts
+            .get((word_id - self.range.first_word_id) as usize)
+            .copied()
+    }
+}
+
+struct Schedule {
+    plan: SchedulePlan,
+    tree: MerkleTree,
+}
+
+impl Schedule {
+    /// Computes the commitments of the planned rounds, which the chain has to cover.
+    fn new(
+        seed_chain: &SeedChain,
+        word_bank: &WordBank,
+        plan: SchedulePlan,
+    ) -> Result<Self, Error> {
+        let range = plan.range;
+        let mut commitments = Vec::with_capacity(range.length as usize);
+        for (i, &attempt) in plan.attempts.iter().enumerate() {
+            let word_id = range.first_word_id + i as u32;
+            let (secret, _) = round_secret(seed_chain, word_bank, word_id, attempt)?;
+            commitments.push(
+                HASH_PARAMS
+                    .commitment(secret.solution(), &secret.salt())
+                    .map_err(Error::Merkle)?,
+            );
+        }
+        let tree = MerkleTree::from_leaves_with_params(&commitments, 2, HASH_PARAMS)
+            .map_err(Error::Merkle)?;
+
+        info!(
+            target: AUDIT_TARGET,
+            first_word_id = range.first_word_id,
+            length = range.length,
+            root = %tree.root_hash(),
+            "schedule committed"
+        );
+        Ok(Schedule { plan, tree })
+    }
+
+    fn proof(&self, word_id: u32) -> Option<ScheduleProof> {
+        let range = self.plan.range;
+        if !range.covers(word_id) {
+            return None;
+        }
+        let idx = (word_id - range.first_word_id) as usize;
+        Some(ScheduleProof {
+            root: self.tree.root_hash(),
+            first_word_id: range.first_word_id,
+            path: self.tree.get_path(idx).ok()?,
+        })
+    }
+}
+
+/// Plans the rounds: selects their words and salts with the seed chain, and commits to the next
+/// `schedule_length` of them at once. Once the schedule runs out, the next one starts, with a new
+/// seed chain if the current one doesn't cover it.
+pub struct RoundPlanner {
+    seed_chain: SeedChain,
+    seed_chain_length: u32,
+    schedule: Schedule,
+    schedule_length: u32,
+}
+
+impl RoundPlanner {
+    /// Starts planning with the round `first_word_id`, continuing the seed chain and the
+    /// schedule if they were persisted. A new schedule skips the words of the rounds in the
+    /// history.
+    pub fn new(
+        word_bank: &WordBank,
+        first_word_id: u32,
+        seed_chain: Option<SeedChain>,
+        schedule_plan: Option<SchedulePlan>,
+        history: &RoundHistory,
+        seed_chain_length: u32,
+        schedule_length: u32,
+    ) -> Result<Self, Error> {
+        let (seed_chain, plan) = match (seed_chain, schedule_plan) {
+            (Some(seed_chain), Some(plan))
+                if plan.range.covers(first_word_id)
+                    && plan.attempts.len() == plan.range.length as usize
+                    && seed_chain.covers(plan.range.first_word_id)
+                    && seed_chain.covers(plan.range.first_word_id + plan.range.length - 1) =>
+            {
+                (seed_chain, plan)
+            }
+            (seed_chain, _) => {
+                let range = ScheduleRange {
+                    first_word_id,
+                    length: schedule_length,
+                };
+                let seed_chain = seed_chain
+                    .filter(|seed_chain| {
+                        seed_chain.covers(first_word_id)
+                            && seed_chain.covers(first_word_id + schedule_length - 1)
+                    })
+                    .unwrap_or_else(|| new_seed_chain(first_word_id, seed_chain_length));
+                let plan = SchedulePlan::new(&seed_chain, word_bank, range, history);
+                (seed_chain, plan)
+            }
+        };
+
+        Ok(RoundPlanner {
+            schedule: Schedule::new(&seed_chain, word_bank, plan)?,
+            seed_chain,
+            seed_chain_length,
+            schedule_length,
+        })
+    }
+
+    /// Plans the round, starting the next schedule if it's past the current one. The history
+    /// holds the rounds played so far, which the round's word differs from.
+    pub fn plan(
+        &mut self,
+        word_bank: &WordBank,
+        word_id: u32,
+        history: &RoundHistory,
+    ) -> Result<PlannedGame, Error> {
+        if !self.schedule.plan.range.covers(word_id) {
+            let range = ScheduleRange {
+                first_word_id: word_id,
+                length: self.schedule_length,
+            };
+            if !self.seed_chain.covers(word_id)
+                || !self.seed_chain.covers(word_id + range.length - 1)
+            {
+                self.seed_chain = new_seed_chain(word_id, self.seed_chain_length);
+            }
+            let plan = SchedulePlan::new(&self.seed_chain, word_bank, range, history);
+            self.schedule = Schedule::new(&self.seed_chain, word_bank, plan)?;
+        }
+
+        let attempt = self
+            .schedule
+            .plan
+            .attempt(word_id)
+            .expect("the schedule covers the round");
+        let (secret, path) = round_secret(&self.seed_chain, word_bank, word_id, attempt)?;
+        Ok(PlannedGame {
+            secret,
+            path,
+            seed_anchor: Some(self.seed_chain.anchor()),
+            schedule_proof: self.schedule.proof(word_id),
+            previous: Some(history.before(word_id)),
+        })
+    }
+
+    /// The path of the round's commitment, if it's in the current schedule.
+    pub fn schedule_proof(&self, word_id: u32) -> Option<ScheduleProof> {
+        self.schedule.proof(word_id)
+    }
+
+    pub fn seed_chain(&self) -> &SeedChain {
+        &self.seed_chain
+    }
+
+    pub fn schedule_range(&self) -> ScheduleRange {
+        self.schedule.plan.range
+    }
+
+    pub fn schedule_plan(&self) -> &SchedulePlan {
+        &self.schedule.plan
+    }
+}
+
+/// The secret of the round selected by the chain at the attempt, and the merkle path of its word.
+fn round_secret(
+    seed_chain: &SeedChain,
+    word_bank: &WordBank,
+    word_id: u32,
+    attempt: u32,
+) -> Result<(RoundSecret, Vec<MerklePathEntry>), Error> {
+    let (idx, selection) = seed_chain
+        .select(word_id, attempt, word_bank.solution_words().len())
+        .expect("the chain covers the round");
+    let picked = word_bank.word_at(idx);
+    let secret = RoundSecret::new(&picked.word, &selection.salt(word_id))
+        .map_err(Error::Secret)?
+        .with_selection(selection.seed, selection.attempt);
+    Ok((secret, picked.path))
+}
+
+/// Creates a seed chain for the rounds from `first_word_id` on, and records its anchor in the
+/// audit log, which commits to the words of all these rounds.
+fn new_seed_chain(first_word_id: u32, length: u32) -> SeedChain {
+    let seed_chain = SeedChain::random(first_word_id, length);
+    info!(
+        target: AUDIT_TARGET,
+        first_word_id,
+        length,
+        anchor = %hex::encode(seed_chain.anchor().anchor),
+        "seed chain committed"
+    );
+    seed_chain
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use num_bigint::BigUint;
+
+    use super::{RoundPlanner, SchedulePlan, ScheduleRange};
+    use crate::{
+        history::{RoundHistory, UsedWord, FRESHNESS_ROUNDS},
+        proofs::HASH_PARAMS,
+        secret::RoundSecret,
+        seed_chain::{word_index, SeedChain},
+        word_bank::WordBank,
+    };
+
+    #[test]
+    fn rounds_match_schedule() {
+        let word_bank = WordBank::new().unwrap();
+        let history = RoundHistory::default();
+        let mut planner = RoundPlanner::new(&word_bank, 5, None, None, &history, 20, 4).unwrap();
+        let range = planner.schedule_range();
+        assert_eq!(
+            range,
+            ScheduleRange {
+                first_word_id: 5,
+                length: 4
+            }
+        );
+
+        for word_id in 5..9 {
+            let planned = planner.plan(&word_bank, word_id, &history).unwrap();
+            let proof = planned.schedule_proof.expect("round is scheduled");
+            let commitment = HASH_PARAMS
+                .commitment(planned.secret.solution(), &planned.secret.salt())
+                .unwrap();
+            assert!(HASH_PARAMS
+                .verify_path(&commitment, &proof.path, &proof.root)
+                .unwrap());
+            assert_eq!(planner.schedule_range(), range);
+        }
+
+        // the next schedule starts once this one runs out
+        planner.plan(&word_bank, 9, &history).unwrap();
+        assert_eq!(planner.schedule_range().first_word_id, 9);
+    }
+
+    #[test]
+    fn restored_schedule_is_the_same() {
+        let word_bank = WordBank::new().unwrap();
+        let planner =
+            RoundPlanner::new(&word_bank, 0, None, None, &RoundHistory::default(), 20, 4).unwrap();
+        let proof = planner.schedule_proof(2).unwrap();
+
+        let seed_chain = SeedChain::from_bytes(
+            *planner.seed_chain().seed_bytes(),
+            0,
+            planner.seed_chain().length(),
+        );
+        // the history the schedule was planned with is not needed anymore
+        let restored = RoundPlanner::new(
+            &word_bank,
+            2,
+            Some(seed_chain),
+            Some(planner.schedule_plan().clone()),
+            &RoundHistory::default(),
+            20,
+            4,
+        )
+        .unwrap();
+
+        assert_eq!(restored.schedule_proof(2).unwrap().root, proof.root);
+    }
+
+    #[test]
+    fn recent_words_skipped() {
+        let word_bank = WordBank::new().unwrap();
+        let words = word_bank.solution_words();
+        let rounds = FRESHNESS_ROUNDS as u32;
+        let seed_chain = SeedChain::from_bytes([3; 32], 0, 3 * rounds);
+        let element = |word_id| seed_chain.element(word_id).unwrap();
+        // the history is the first schedule, planned without skipping
+        let history = RoundHistory::new((0..rounds).map(|word_id| {
+            let idx = word_index(&element(word_id), word_id, 0, words.len());
+            UsedWord {
+                word_id,
+                secret: Arc::new(RoundSecret::new(&words[idx], &BigUint::from(1u32)).unwrap()),
+                commitment: BigUint::from(word_id),
+            }
+        }));
+        let range = ScheduleRange {
+            first_word_id: rounds,
+            length: 2 * rounds,
+        };
+
+        let plan = SchedulePlan::new(&seed_chain, &word_bank, range, &history);
+        // this chain runs into recent words
+        assert!(plan.attempts.iter().any(|&attempt| attempt > 0));
+
+        let mut selected: Vec<String> = history
+            .rounds()
+            .map(|used| used.secret.solution().to_string())
+            .collect();
+        for (i, &attempt) in plan.attempts.iter().enumerate() {
+            let word_id = rounds + i as u32;
+            let word = &words[word_index(&element(word_id), word_id, attempt, words.len())];
+            let recent = &selected[selected.len() - FRESHNESS_ROUNDS..];
+            assert!(!recent.contains(word));
+            // the earlier attempts were skipped for a reason
+            for skipped in 0..attempt {
+                let idx = word_index(&element(word_id), word_id, skipped, words.len());
+                assert!(recent.contains(&words[idx]));
+            }
+            selected.push(word.clone());
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::{fmt, sync::Arc};
+
+use num_bigint::{BigUint, RandomBits};
+use rand::{thread_rng, Rng};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+use crate::{game_state::RoundReveal, history::UsedWord};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    BadSolution,
+    BadSalt,
+    BadKey,
+}
+
+/// The secret of a round: the solution and the salt opening the commitment, and for the rounds
+/// whose word is selected by a seed chain, the chain element. Kept in fixed size buffers which
+/// are zeroized on drop, never printed, and readable only within the crate, by the proving and
+/// revealing code. Shared through an `Arc`, so that it's never copied around.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct RoundSecret {
+    solution: [u8; 5],
+    // little endian
+    salt: [u8; 32],
+    selection_seed: Option<[u8; 32]>,
+    selection_attempt: u32,
+}
+
+impl RoundSecret {
+    /// Creates the secret for the solution with a freshly sampled 256-bit salt.
+    pub fn random(solution: &str) -> Result<Self, Error> {
+        let salt: BigUint = thread_rng().sample(RandomBits::new(256));
+        Self::new(solution, &salt)
+    }
+
+    pub fn new(solution: &str, salt: &BigUint) -> Result<Self, Error> {
+        let solution: [u8; 5] = solution
+            .as_bytes()
+            .try_into()
+            .map_err(|_| Error::BadSolution)?;
+        let mut salt_bytes = salt.to_bytes_le();
+        if salt_bytes.len() > 32 {
+            salt_bytes.zeroize();
+            return Err(Error::BadSalt);
+        }
+        let mut secret = RoundSecret {
+            solution,
+            salt: [0; 32],
+            selection_seed: None,
+            selection_attempt: 0,
+        };
+        secret.salt[..salt_bytes.len()].copy_from_slice(&salt_bytes);
+        salt_bytes.zeroize();
+        Ok(secret)
+    }
+
+    pub(crate) fn from_bytes(solution: [u8; 5], salt: [u8; 32]) -> Self {
+        RoundSecret {
+            solution,
+            salt,
+            selection_seed: None,
+            selection_attempt: 0,
+        }
+    }
+
+    /// Attaches the seed chain element which selected the solution at the attempt, revealed with
+    /// the salt.
+    pub(crate) fn with_selection(mut self, seed: [u8; 32], attempt: u32) -> Self {
+        self.selection_seed = Some(seed);
+        self.selection_attempt = attempt;
+        self
+    }
+
+    pub(crate) fn solution(&self) -> &str {
+        std::str::from_utf8(&self.solution).expect("solution should be ascii")
+    }
+
+    /// The salt as a number, for the circuit inputs. The returned copy can't be zeroized, so
+    /// it should be dropped as soon as possible.
+    pub(crate) fn salt(&self) -> BigUint {
+        BigUint::from_bytes_le(&self.salt)
+    }
+
+    pub(crate) fn salt_bytes(&self) -> &[u8; 32] {
+        &self.salt
+    }
+
+    pub(crate) fn solution_bytes(&self) -> &[u8; 5] {
+        &self.solution
+    }
+
+    pub(crate) fn selection_seed(&self) -> Option<&[u8; 32]> {
+        self.selection_seed.as_ref()
+    }
+
+    pub(crate) fn selection_attempt(&self) -> u32 {
+        self.selection_attempt
+    }
+
+    /// Opens the commitment once the round is over, along with the previous rounds.
+    pub(crate) fn reveal(&self, word_id: u32, previous: Arc<[UsedWord]>) -> RoundReveal {
+        RoundReveal {
+            word_id,
+            solution: self.solution().into(),
+            salt: self.salt(),
+            selection_seed: self.selection_seed,
+            selection_attempt: self.selection_attempt,
+            previous,
+        }
+    }
+}
+
+impl fmt::Debug for RoundSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RoundSecret(<redacted>)")
+    }
+}
+
+/// Key encrypting the persisted round state. Supplied at startup, zeroized on drop.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct StateKey([u8; 32]);
+
+impl StateKey {
+    /// Parses the key from 64 hex characters.
+    pub fn from_hex(hex_key: &str) -> Result<Self, Error> {
+        let mut key = [0; 32];
+        hex::decode_to_slice(hex_key.trim(), &mut key).map_err(|_| Error::BadKey)?;
+        Ok(StateKey(key))
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Debug for StateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StateKey(<redacted>)")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use num_bigint::BigUint;
+
+    use super::{Error, RoundSecret, StateKey};
+
+    #[test]
+    fn salt_roundtrip() {
+        let salt = BigUint::parse_bytes(b"123456789012345678901234567890", 10).unwrap();
+        let secret = RoundSecret::new("crane", &salt).unwrap();
+
+        assert_eq!(secret.solution(), "crane");
+        assert_eq!(secret.salt(), salt);
+    }
+
+    #[test]
+    fn bad_inputs_rejected() {
+        let too_big = BigUint::from(1u32) << 256;
+
+        assert_eq!(
+            RoundSecret::new("cranes", &BigUint::from(1u32)).err(),
+            Some(Error::BadSolution)
+        );
+        assert_eq!(
+            RoundSecret::new("crane", &too_big).err(),
+            Some(Error::BadSalt)
+        );
+        assert_eq!(StateKey::from_hex("abcd").err(), Some(Error::BadKey));
+    }
+
+    #[test]
+    fn debug_redacted() {
+        let secret = RoundSecret::new("crane", &BigUint::from(987654321u32)).unwrap();
+        let key = StateKey::from_hex(&"ab".repeat(32)).unwrap();
+
+        assert_eq!(format!("{:?}", secret), "RoundSecret(<redacted>)");
+        assert_eq!(format!("{:?}", key), "StateKey(<redacted>)");
+    }
+}
@@ -0,0 +1,198 @@
+//! Verifiable word selection. The words of the rounds are selected by a sha256 hash chain, whose
+//! last element, the anchor, is published before any of its rounds is played. Round `first + i`
+//! uses the chain element which hashes `i + 1` times to the anchor, so the elements are used in
+//! the reverse order of their computation, and an element can't be predicted from the earlier
+//! ones. The element of a round is revealed together with the salt, and then anyone can check
+//! that it belongs to the chain, and that the solution is the word at the index it derives.
+//! A word used in one of the recent rounds is skipped by deriving the index again, with the
+//! number of the attempt, which is revealed as well.
+
+use std::fmt;
+
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+// separate the index and salt derivations from the chain hashing
+const WORD_INDEX_DOMAIN: &[u8] = b"zk-wordle/word-index";
+const SALT_DOMAIN: &[u8] = b"zk-wordle/salt";
+
+/// The public part of a seed chain, published in advance of its rounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SeedAnchor {
+    pub anchor: [u8; 32],
+    // the first round selected by the chain
+    pub first_word_id: u32,
+}
+
+/// The chain element selecting the word of a round, and the anchor it's checked against.
+pub struct Selection {
+    pub anchor: SeedAnchor,
+    pub seed: [u8; 32],
+    // how many derived words were skipped as recently used
+    pub attempt: u32,
+}
+
+impl Selection {
+    /// Salt of the round, derived from the chain element, so that the commitments of future
+    /// rounds are fixed by the chain as well. The element is secret until the reveal, which
+    /// opens the commitment anyway.
+    pub fn salt(&self, word_id: u32) -> BigUint {
+        let digest = Sha256::new()
+            .chain_update(SALT_DOMAIN)
+            .chain_update(self.seed)
+            .chain_update(word_id.to_le_bytes())
+            .finalize();
+        BigUint::from_bytes_le(&digest)
+    }
+}
+
+/// A hash chain selecting the words of `length` rounds, starting with `first_word_id`. Only the
+/// seed is kept, the elements are recomputed when needed.
+#[derive(Zeroize, ZeroizeOnDrop)]
+pub struct SeedChain {
+    seed: [u8; 32],
+    #[zeroize(skip)]
+    length: u32,
+    #[zeroize(skip)]
+    anchor: SeedAnchor,
+}
+
+impl SeedChain {
+    pub fn random(first_word_id: u32, length: u32) -> Self {
+        Self::from_bytes(rand::random(), first_word_id, length)
+    }
+
+    pub(crate) fn from_bytes(seed: [u8; 32], first_word_id: u32, length: u32) -> Self {
+        SeedChain {
+            seed,
+            length,
+            anchor: SeedAnchor {
+                anchor: hash_times(seed, length),
+                first_word_id,
+            },
+        }
+    }
+
+    pub fn anchor(&self) -> SeedAnchor {
+        self.anchor
+    }
+
+    pub fn length(&self) -> u32 {
+        self.length
+    }
+
+    pub(crate) fn seed_bytes(&self) -> &[u8; 32] {
+        &self.seed
+    }
+
+    /// Whether the chain selects the word of the round.
+    pub fn covers(&self, word_id: u32) -> bool {
+        word_id
+            .checked_sub(self.anchor.first_word_id)
+            .map_or(false, |i| i < self.length)
+    }
+
+    /// The chain element of the round, secret until the round is revealed.
+    pub(crate) fn element(&self, word_id: u32) -> Option<[u8; 32]> {
+        if !self.covers(word_id) {
+            return None;
+        }
+        let i = word_id - self.anchor.first_word_id;
+        Some(hash_times(self.seed, self.length - i - 1))
+    }
+
+    /// Selects the word of the round among the `words` solution words, with the attempt'th
+    /// derived index. Returns the index, and the selection to reveal with the round.
+    pub(crate) fn select(
+        &self,
+        word_id: u32,
+        attempt: u32,
+        words: usize,
+    ) -> Option<(usize, Selection)> {
+        let seed = self.element(word_id)?;
+        let selection = Selection {
+            anchor: self.anchor,
+            seed,
+            attempt,
+        };
+        Some((word_index(&seed, word_id, attempt, words), selection))
+    }
+}
+
+impl fmt::Debug for SeedChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SeedChain")
+            .field("seed", &"<redacted>")
+            .field("length", &self.length)
+            .field("anchor", &self.anchor)
+            .finish()
+    }
+}
+
+/// Checks that the revealed element belongs to the chain of the anchor, at the round's position.
+pub fn is_chain_element(anchor: &SeedAnchor, word_id: u32, element: [u8; 32]) -> bool {
+    match word_id.checked_sub(anchor.first_word_id) {
+        Some(i) => hash_times(element, i + 1) == anchor.anchor,
+        None => false,
+    }
+}
+
+/// Index of the round's solution among the `words` solution words (the leaf of the merkle tree),
+/// derived for the attempt'th time. The first attempt doesn't hash the attempt, so that it's the
+/// index of the rounds selected before the recent words were skipped.
+pub fn word_index(element: &[u8; 32], word_id: u32, attempt: u32, words: usize) -> usize {
+    let mut hasher = Sha256::new()
+        .chain_update(WORD_INDEX_DOMAIN)
+        .chain_update(element)
+        .chain_update(word_id.to_le_bytes());
+    if attempt > 0 {
+        hasher.update(attempt.to_le_bytes());
+    }
+    let digest = hasher.finalize();
+    let n = u64::from_be_bytes(digest[..8].try_into().expect("digest is 32 bytes"));
+    // the modulo bias is below words / 2^64, negligible
+    (n % words as u64) as usize
+}
+
+fn hash_times(mut x: [u8; 32], n: u32) -> [u8; 32] {
+    for _ in 0..n {
+        x = Sha256::digest(x).into();
+    }
+    x
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_chain_element, word_index, SeedChain};
+
+    #[test]
+    fn elements_verify_against_anchor() {
+        let chain = SeedChain::from_bytes([7; 32], 10, 5);
+        let anchor = chain.anchor();
+
+        assert!(!chain.covers(9));
+        assert!(chain.element(9).is_none());
+        assert!(chain.element(15).is_none());
+        for word_id in 10..15 {
+            let element = chain.element(word_id).unwrap();
+            assert!(is_chain_element(&anchor, word_id, element));
+            assert!(!is_chain_element(&anchor, word_id + 1, element));
+        }
+        // the last round uses the seed itself
+        assert_eq!(chain.element(14).unwrap(), [7; 32]);
+        assert!(!is_chain_element(&anchor, 9, [7; 32]));
+    }
+
+    #[test]
+    fn index_in_range_and_deterministic() {
+        let chain = SeedChain::random(0, 100);
+        for word_id in 0..100 {
+            let element = chain.element(word_id).unwrap();
+            let idx = word_index(&element, word_id, 0, 2315);
+            assert!(idx < 2315);
+            assert_eq!(idx, word_index(&element, word_id, 0, 2315));
+            assert!(word_index(&element, word_id, 1, 2315) < 2315);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{clue::compute_clue, word_bank::WordBank};
+
+/// Number of different clues, each of the 5 letters is either grey, yellow or green.
+const CLUE_COUNT: usize = 243;
+
+/// How the solver picks the next guess among all acceptable words.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Maximize the expected information of the clue.
+    Entropy,
+    /// Minimize the number of candidates left in the worst case.
+    Minimax,
+}
+
+/// Wordle solver which keeps the set of solution words consistent with all clues seen so far.
+#[derive(Clone)]
+pub struct Solver {
+    strategy: Strategy,
+    // all acceptable guess words, sorted so that the choices are deterministic
+    guesses: Vec<String>,
+    // solution words which are still consistent with all the clues
+    candidates: Vec<String>,
+}
+
+impl Solver {
+    pub fn new(word_bank: &WordBank, strategy: Strategy) -> Self {
+        let mut guesses: Vec<String> = word_bank.all_words().cloned().collect();
+        guesses.sort();
+
+        Solver {
+            strategy,
+            guesses,
+            candidates: word_bank.solution_words().to_vec(),
+        }
+    }
+
+    /// Solution words which are still possible.
+    pub fn candidates(&self) -> &[String] {
+        &self.candidates
+    }
+
+    /// Picks the best guess according to the strategy. Returns `None` if no solution word is
+    /// consistent with the clues anymore.
+    pub fn next_guess(&self) -> Option<String> {
+        match self.candidates.len() {
+            0 => None,
+            1 | 2 => Some(self.candidates[0].clone()),
+            _ => {
+                let mut best: Option<(&String, f64)> = None;
+                for guess in self.guesses.iter() {
+                    let score = self.score(guess);
+                    // ties are broken in favor of the guesses which can still win
+                    let better = match best {
+                        None => true,
+                        Some((_, best_score)) => {
+                            score > best_score || (score == best_score && self.is_candidate(guess))
+                        }
+                    };
+                    if better {
+                        best = Some((guess, score));
+                    }
+                }
+                best.map(|(guess, _)| guess.clone())
+            }
+        }
+    }
+
+    /// Removes the candidates which would produce a different clue for the guess.
+    pub fn apply_clue(&mut self, guess: &str, clue: [u8; 5]) {
+        self.candidates
+            .retain(|candidate| compute_clue(guess, candidate) == clue);
+    }
+
+    // The higher, the better.
+    fn score(&self, guess: &str) -> f64 {
+        let mut partition = [0usize; CLUE_COUNT];
+        for candidate in self.candidates.iter() {
+            partition[clue_index(compute_clue(guess, candidate))] += 1;
+        }
+
+        match self.strategy {
+            Strategy::Entropy => {
+                let total = self.candidates.len() as f64;
+                partition
+                    .iter()
+                    .filter(|&&n| n > 0)
+                    .map(|&n| {
+                        let p = n as f64 / total;
+                        -p * p.log2()
+                    })
+                    .sum()
+            }
+            Strategy::Minimax => -(*partition.iter().max().unwrap_or(&0) as f64),
+        }
+    }
+
+    fn is_candidate(&self, word: &str) -> bool {
+        self.candidates.iter().any(|c| c == word)
+    }
+}
+
+fn clue_index(clue: [u8; 5]) -> usize {
+    clue.iter().fold(0, |acc, &c| acc * 3 + c as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Solver, Strategy};
+    use crate::clue::compute_clue;
+
+    fn solver(strategy: Strategy) -> Solver {
+        let words: Vec<String> = ["crane", "crate", "trace", "grace", "brace", "slate"]
+            .into_iter()
+            .map(|w| w.into())
+            .collect();
+        Solver {
+            strategy,
+            guesses: words.clone(),
+            candidates: words,
+        }
+    }
+
+    #[test]
+    fn candidates_filtered_by_clue() {
+        let mut solver = solver(Strategy::Entropy);
+
+        solver.apply_clue("crane", compute_clue("crane", "grace"));
+
+        assert_eq!(solver.candidates(), ["trace", "grace", "brace"]);
+    }
+
+    #[test]
+    fn solves_every_word() {
+        for strategy in [Strategy::Entropy, Strategy::Minimax] {
+            for solution in solver(strategy).candidates() {
+                let mut solver = solver(strategy);
+                let mut solved = false;
+                for _ in 0..6 {
+                    let guess = solver.next_guess().expect("candidates should not run out");
+                    let clue = compute_clue(&guess, solution);
+                    if clue == [2; 5] {
+                        solved = true;
+                        break;
+                    }
+                    solver.apply_clue(&guess, clue);
+                }
+                assert!(solved, "{} not solved with {:?}", solution, strategy);
+            }
+        }
+    }
+
+    #[test]
+    fn inconsistent_clues_empty_candidates() {
+        let mut solver = solver(Strategy::Minimax);
+
+        solver.apply_clue("crane", [2, 2, 2, 2, 2]);
+        solver.apply_clue("slate", [2, 2, 2, 2, 2]);
+
+        assert!(solver.candidates().is_empty());
+        assert_eq!(solver.next_guess(), None);
+    }
+}
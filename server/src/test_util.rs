@@ -0,0 +1,31 @@
+use std::sync::Arc;
+
+use ark_bn254::{Bn254, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_groth16::Proof;
+use num_bigint::BigUint;
+
+use crate::{game_state::MutableState, secret::RoundSecret};
+
+/// A well-formed proof of nothing, for the tests which only pass proofs around.
+pub(crate) fn dummy_proof() -> Proof<Bn254> {
+    Proof {
+        a: G1Affine::generator(),
+        b: G2Affine::generator(),
+        c: G1Affine::generator(),
+    }
+}
+
+/// A game against "crane", whose salt and commitment are both `word_id`, with a dummy proof.
+pub(crate) fn dummy_game(word_id: u32) -> MutableState {
+    MutableState {
+        word_id,
+        secret: Arc::new(RoundSecret::new("crane", &BigUint::from(word_id)).unwrap()),
+        commitment: BigUint::from(word_id),
+        membership_proof: dummy_proof(),
+        seed_anchor: None,
+        schedule_proof: None,
+        freshness_proof: None,
+        previous: Arc::new([]),
+    }
+}
@@ -0,0 +1,758 @@
+use std::str::FromStr;
+
+use ark_bn254::Bn254;
+use ark_groth16::Proof;
+use merkle::{NodeType, NonMembershipWitness};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clue::compute_clue,
+    dictionary::verify_absence,
+    history::{FreshnessProof, FRESHNESS_ROUNDS},
+    proofs::HASH_PARAMS,
+    request_response::{
+        parse_seed, AbsenceProofSerializable, FreshnessProofSerializable, GuessResponse,
+        ProofSerializable, RevealResponse, ScheduleProofSerializable, SeedAnchorSerializable,
+        StartResponse,
+    },
+    schedule::{ScheduleProof, MAX_SELECTION_ATTEMPTS},
+    seed_chain::{is_chain_element, word_index, SeedAnchor},
+    verifier::{
+        self, clue_public_signals, freshness_public_signals, membership_public_signals, Verifier,
+    },
+    word_bank::is_word_ok,
+};
+
+/// Bumped whenever the transcript format changes in an incompatible way.
+pub const TRANSCRIPT_VERSION: u32 = 1;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AuditError {
+    UnsupportedVersion(u32),
+    WrongRoot,
+    ClueKeyMismatch,
+    MembershipKeyMismatch,
+    BadNumber,
+    BadProof,
+    BadGuess(usize),
+    // the revealed solution (or one of the previous rounds) is not a word, so it can't be hashed
+    // nor compared with the guesses, or the previous rounds are not the ones before the round
+    BadReveal,
+    Verify,
+    MembershipProofInvalid,
+    ClueProofInvalid(usize),
+    RevealWrongRound,
+    RevealCommitmentMismatch,
+    RevealClueMismatch(usize),
+    BadSeed,
+    // the round's word was selected by a seed chain, but the reveal lacks the chain element
+    RevealSeedMissing,
+    RevealSeedNotInChain,
+    RevealWordNotSelected,
+    // the auditor has no solution words to derive the selected one from
+    NoSolutionWords,
+    // the selection attempt is beyond the ones the server makes
+    RevealAttemptOutOfRange,
+    // the word of the skipped attempt isn't one of the previous rounds
+    RevealSkipUnjustified(u32),
+    BadSchedule,
+    // the commitment is not the round's leaf of the published schedule
+    ScheduleProofInvalid,
+    // the proof that the rejected guess is not in the dictionary doesn't verify
+    RejectionInvalid(usize),
+    BadFreshness,
+    FreshnessProofInvalid,
+    // the freshness proof is against other commitments than the ones of the revealed previous
+    // rounds
+    FreshnessPreviousMismatch,
+}
+
+/// Everything a player received during one game, bundled so that a third party can re-check
+/// all of it offline. Serialized as JSON.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Transcript {
+    pub version: u32,
+    pub round: RoundParameters,
+    pub membership_proof: ProofSerializable,
+    pub guesses: Vec<TranscriptGuess>,
+    // guesses the server rejected as not acceptable words
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rejections: Vec<TranscriptRejection>,
+    // filled in once the server reveals the solution and salt after the round is over
+    pub reveal: Option<RevealResponse>,
+}
+
+/// Public parameters of the round the game was played in.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RoundParameters {
+    pub word_id: u32,
+    pub commitment: String,
+    // root of the merkle tree over the solution words
+    pub root: String,
+    // fingerprints of the verification keys the proofs were checked against
+    pub clue_key_fingerprint: String,
+    pub membership_key_fingerprint: String,
+    // anchor of the seed chain which selected the word, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed_anchor: Option<SeedAnchorSerializable>,
+    // path of the commitment in the schedule published in advance, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<ScheduleProofSerializable>,
+    // proof that the word differs from the words of the previous rounds, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freshness: Option<FreshnessProofSerializable>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TranscriptGuess {
+    pub guess: String,
+    pub colors: [u8; 5],
+    pub proof: ProofSerializable,
+}
+
+/// A guess rejected as not in the dictionary, with the proof of it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TranscriptRejection {
+    pub guess: String,
+    pub absence: AbsenceProofSerializable,
+}
+
+/// Summary of a successful audit.
+#[derive(Debug, PartialEq, Eq)]
+pub struct AuditReport {
+    pub word_id: u32,
+    pub guesses_verified: usize,
+    pub solved: bool,
+    pub revealed: bool,
+    // the revealed solution is the word selected by the seed chain
+    pub selection_verified: bool,
+    // the commitment was fixed in the published schedule before the round
+    pub scheduled: bool,
+    // the word differs from the words of the previous rounds, as the freshness proof verified
+    pub fresh: bool,
+}
+
+impl Transcript {
+    /// Starts a transcript from the `/start` response.
+    pub fn new(
+        start: StartResponse,
+        clue_verifier: &Verifier,
+        membership_verifier: &Verifier,
+    ) -> Self {
+        Transcript {
+            version: TRANSCRIPT_VERSION,
+            round: RoundParameters {
+                word_id: start.word_id,
+                commitment: start.commitment,
+                root: start.root,
+                clue_key_fingerprint: clue_verifier.fingerprint().into(),
+                membership_key_fingerprint: membership_verifier.fingerprint().into(),
+                seed_anchor: start.seed_anchor,
+                schedule: start.schedule,
+                freshness: start.freshness,
+            },
+            membership_proof: start.proof,
+            guesses: Vec::new(),
+            rejections: Vec::new(),
+            reveal: None,
+        }
+    }
+
+    /// Records the `/guess` response.
+    pub fn push_guess(&mut self, guess: String, response: GuessResponse) {
+        self.guesses.push(TranscriptGuess {
+            guess,
+            colors: response.colors,
+            proof: response.proof,
+        });
+    }
+
+    /// Records the rejection of the guess, and the proof that it's not in the dictionary.
+    pub fn push_rejection(&mut self, guess: String, absence: AbsenceProofSerializable) {
+        self.rejections.push(TranscriptRejection { guess, absence });
+    }
+
+    /// Records the solution and salt revealed after the round.
+    pub fn set_reveal(&mut self, reveal: RevealResponse) {
+        self.reveal = Some(reveal);
+    }
+}
+
+/// Re-verifies the whole transcript: the membership proof against the expected merkle root,
+/// the commitment against the published schedule, the freshness proof if there's a key for it,
+/// every clue proof against the commitment, and, if present, that the revealed solution and salt
+/// open the commitment and produce exactly the recorded clues, that the solution is the word the
+/// seed chain selected among the solution words, and that the revealed previous rounds open the
+/// commitments the freshness proof is against.
+pub fn audit(
+    transcript: &Transcript,
+    expected_root: &BigUint,
+    solution_words: &[String],
+    clue_verifier: &Verifier,
+    membership_verifier: &Verifier,
+    freshness_verifier: Option<&Verifier>,
+) -> Result<AuditReport, AuditError> {
+    if transcript.version != TRANSCRIPT_VERSION {
+        return Err(AuditError::UnsupportedVersion(transcript.version));
+    }
+    if let Some(reveal) = &transcript.reveal {
+        if !is_reveal_well_formed(reveal) {
+            return Err(AuditError::BadReveal);
+        }
+    }
+
+    let round = &transcript.round;
+    if round.clue_key_fingerprint != clue_verifier.fingerprint() {
+        return Err(AuditError::ClueKeyMismatch);
+    }
+    if round.membership_key_fingerprint != membership_verifier.fingerprint() {
+        return Err(AuditError::MembershipKeyMismatch);
+    }
+
+    let root = parse_number(&round.root)?;
+    if &root != expected_root {
+        return Err(AuditError::WrongRoot);
+    }
+    let commitment = parse_number(&round.commitment)?;
+
+    let membership_proof = parse_proof(&transcript.membership_proof)?;
+    if !membership_verifier
+        .verify(
+            &membership_public_signals(&root, &commitment),
+            &membership_proof,
+        )
+        .map_err(map_verify_error)?
+    {
+        return Err(AuditError::MembershipProofInvalid);
+    }
+    let scheduled = check_schedule(round, &commitment)?;
+    let freshness = parse_freshness(round)?;
+    let fresh = match (&freshness, freshness_verifier) {
+        (Some(freshness), Some(verifier)) => {
+            let signals =
+                freshness_public_signals(&commitment, &freshness.previous, FRESHNESS_ROUNDS);
+            if !verifier
+                .verify(&signals, &freshness.proof)
+                .map_err(map_verify_error)?
+            {
+                return Err(AuditError::FreshnessProofInvalid);
+            }
+            true
+        }
+        _ => false,
+    };
+
+    for (i, guess) in transcript.guesses.iter().enumerate() {
+        if !is_guess_well_formed(guess) {
+            return Err(AuditError::BadGuess(i));
+        }
+        let proof = parse_proof(&guess.proof)?;
+        if !clue_verifier
+            .verify(
+                &clue_public_signals(&guess.guess, guess.colors, &commitment),
+                &proof,
+            )
+            .map_err(map_verify_error)?
+        {
+            return Err(AuditError::ClueProofInvalid(i));
+        }
+    }
+
+    let mut selection_verified = false;
+    if let Some(reveal) = &transcript.reveal {
+        if reveal.word_id != round.word_id {
+            return Err(AuditError::RevealWrongRound);
+        }
+        let salt = parse_number(&reveal.salt)?;
+        match HASH_PARAMS.commitment(&reveal.solution, &salt) {
+            Ok(cm) if cm == commitment => {}
+            _ => return Err(AuditError::RevealCommitmentMismatch),
+        }
+        // the proofs already bind the clues to the commitment, this double-checks the circuit
+        for (i, guess) in transcript.guesses.iter().enumerate() {
+            if compute_clue(&guess.guess, &reveal.solution) != guess.colors {
+                return Err(AuditError::RevealClueMismatch(i));
+            }
+        }
+        selection_verified = check_selection(round, reveal, solution_words)?;
+        if let Some(freshness) = &freshness {
+            check_previous(reveal, &freshness.previous)?;
+        }
+    }
+
+    Ok(AuditReport {
+        word_id: round.word_id,
+        guesses_verified: transcript.guesses.len(),
+        solved: transcript.guesses.iter().any(|g| g.colors == [2; 5]),
+        revealed: transcript.reveal.is_some(),
+        selection_verified,
+        scheduled,
+        fresh,
+    })
+}
+
+/// Checks that every rejected guess is proven not to be in the dictionary with the root, which
+/// is published separately from the rounds. Returns the number of rejections checked.
+pub fn audit_rejections(
+    transcript: &Transcript,
+    dictionary_root: &BigUint,
+) -> Result<usize, AuditError> {
+    for (i, rejection) in transcript.rejections.iter().enumerate() {
+        let valid = NonMembershipWitness::try_from(&rejection.absence)
+            .is_ok_and(|proof| verify_absence(&rejection.guess, &proof, dictionary_root));
+        if !valid {
+            return Err(AuditError::RejectionInvalid(i));
+        }
+    }
+    Ok(transcript.rejections.len())
+}
+
+/// Checks that the commitment is the round's leaf of the schedule. Returns false for rounds
+/// without a schedule.
+fn check_schedule(round: &RoundParameters, commitment: &BigUint) -> Result<bool, AuditError> {
+    let Some(schedule) = &round.schedule else {
+        return Ok(false);
+    };
+    let schedule = ScheduleProof::try_from(schedule).map_err(|_| AuditError::BadSchedule)?;
+
+    // the path goes up from the leaf, its directions are the bits of the leaf index
+    let idx = schedule
+        .path
+        .iter()
+        .enumerate()
+        .try_fold(0u64, |idx, (level, entry)| match entry.on_path {
+            _ if level >= 32 => None,
+            NodeType::Left => Some(idx),
+            NodeType::Right => Some(idx | 1 << level),
+        });
+    let expected = round.word_id.checked_sub(schedule.first_word_id);
+    if idx.is_none() || idx != expected.map(u64::from) {
+        return Err(AuditError::ScheduleProofInvalid);
+    }
+    match HASH_PARAMS.verify_path(commitment, &schedule.path, &schedule.root) {
+        Ok(true) => Ok(true),
+        _ => Err(AuditError::ScheduleProofInvalid),
+    }
+}
+
+/// Checks that the revealed seed belongs to the round's seed chain, and selects the revealed
+/// solution. Returns false for rounds without a seed chain.
+fn check_selection(
+    round: &RoundParameters,
+    reveal: &RevealResponse,
+    solution_words: &[String],
+) -> Result<bool, AuditError> {
+    let Some(anchor) = &round.seed_anchor else {
+        return Ok(false);
+    };
+    let anchor = SeedAnchor::try_from(anchor).map_err(|_| AuditError::BadSeed)?;
+    let seed = match &reveal.selection_seed {
+        Some(seed) => parse_seed(seed).map_err(|_| AuditError::BadSeed)?,
+        None => return Err(AuditError::RevealSeedMissing),
+    };
+
+    if !is_chain_element(&anchor, round.word_id, seed) {
+        return Err(AuditError::RevealSeedNotInChain);
+    }
+    if reveal.selection_attempt >= MAX_SELECTION_ATTEMPTS {
+        return Err(AuditError::RevealAttemptOutOfRange);
+    }
+    if solution_words.is_empty() {
+        return Err(AuditError::NoSolutionWords);
+    }
+    let selected =
+        |attempt| &solution_words[word_index(&seed, round.word_id, attempt, solution_words.len())];
+    if *selected(reveal.selection_attempt) != reveal.solution {
+        return Err(AuditError::RevealWordNotSelected);
+    }
+    // a word is skipped only if one of the previous rounds used it
+    for attempt in 0..reveal.selection_attempt {
+        let word = selected(attempt);
+        if !reveal.previous.iter().any(|used| used.solution == *word) {
+            return Err(AuditError::RevealSkipUnjustified(attempt));
+        }
+    }
+    Ok(true)
+}
+
+/// Parses the freshness proof and the commitments it's against, if the round has one.
+fn parse_freshness(round: &RoundParameters) -> Result<Option<FreshnessProof>, AuditError> {
+    let Some(freshness) = &round.freshness else {
+        return Ok(None);
+    };
+    if freshness.previous.len() > FRESHNESS_ROUNDS {
+        return Err(AuditError::BadFreshness);
+    }
+    let previous = freshness
+        .previous
+        .iter()
+        .map(|cm| BigUint::from_str(cm).map_err(|_| AuditError::BadFreshness))
+        .collect::<Result<_, _>>()?;
+    let proof = Proof::try_from(&freshness.proof).map_err(|_| AuditError::BadFreshness)?;
+    Ok(Some(FreshnessProof { previous, proof }))
+}
+
+/// Checks that the revealed previous rounds open exactly the commitments the freshness proof is
+/// against, so that the words skipped by the selection are the ones the round is proven fresh
+/// from.
+fn check_previous(reveal: &RevealResponse, previous: &[BigUint]) -> Result<(), AuditError> {
+    if reveal.previous.len() != previous.len() {
+        return Err(AuditError::FreshnessPreviousMismatch);
+    }
+    for (opening, commitment) in reveal.previous.iter().zip(previous) {
+        let salt = parse_number(&opening.salt)?;
+        match HASH_PARAMS.commitment(&opening.solution, &salt) {
+            Ok(cm) if cm == *commitment => {}
+            _ => return Err(AuditError::FreshnessPreviousMismatch),
+        }
+    }
+    Ok(())
+}
+
+/// The solutions are words, and the previous rounds are at most `FRESHNESS_ROUNDS` before the
+/// revealed one, oldest first.
+fn is_reveal_well_formed(reveal: &RevealResponse) -> bool {
+    let first = reveal.word_id.saturating_sub(FRESHNESS_ROUNDS as u32);
+    is_word_ok(&reveal.solution)
+        && reveal.previous.iter().all(|used| {
+            is_word_ok(&used.solution) && used.word_id >= first && used.word_id < reveal.word_id
+        })
+        && reveal
+            .previous
+            .windows(2)
+            .all(|pair| pair[0].word_id < pair[1].word_id)
+}
+
+fn is_guess_well_formed(guess: &TranscriptGuess) -> bool {
+    is_word_ok(&guess.guess) && guess.colors.iter().all(|&c| c <= 2)
+}
+
+fn parse_number(s: &str) -> Result<BigUint, AuditError> {
+    BigUint::from_str(s).map_err(|_| AuditError::BadNumber)
+}
+
+fn parse_proof(proof: &ProofSerializable) -> Result<Proof<Bn254>, AuditError> {
+    Proof::try_from(proof).map_err(|_| AuditError::BadProof)
+}
+
+fn map_verify_error(_: verifier::Error) -> AuditError {
+    AuditError::Verify
+}
+
+#[cfg(test)]
+mod test {
+    use merkle::MerkleTree;
+    use num_bigint::BigUint;
+
+    use super::{
+        audit, audit_rejections, check_previous, check_schedule, check_selection, parse_freshness,
+        AuditError, AuditReport, Transcript, TRANSCRIPT_VERSION,
+    };
+    use crate::{
+        dictionary::Dictionary,
+        proofs::HASH_PARAMS,
+        request_response::{
+            FreshnessProofSerializable, GuessResponse, PreviousRound, RevealResponse, StartResponse,
+        },
+        schedule::{ScheduleProof, MAX_SELECTION_ATTEMPTS},
+        seed_chain::{word_index, SeedChain},
+        test_util::dummy_proof,
+        verifier::{Verifier, CLUE_VERIFICATION_KEY_PATH, MEMBERSHIP_VERIFICATION_KEY_PATH},
+    };
+
+    struct Fixture {
+        clue: Verifier,
+        membership: Verifier,
+        transcript: Transcript,
+    }
+
+    impl Fixture {
+        /// Audits the transcript against `root`, with no solution words and no freshness key.
+        fn audit(&self, root: u32) -> Result<AuditReport, AuditError> {
+            audit(
+                &self.transcript,
+                &BigUint::from(root),
+                &[],
+                &self.clue,
+                &self.membership,
+                None,
+            )
+        }
+    }
+
+    /// A transcript of round 3 with one guess, and the verifiers it's recorded with.
+    fn fixture() -> Fixture {
+        let clue = Verifier::from_file(CLUE_VERIFICATION_KEY_PATH).unwrap();
+        let membership = Verifier::from_file(MEMBERSHIP_VERIFICATION_KEY_PATH).unwrap();
+        let mut transcript = Transcript::new(
+            StartResponse {
+                word_id: 3,
+                commitment: "1234".into(),
+                root: "5678".into(),
+                proof: dummy_proof().into(),
+                seed_anchor: None,
+                schedule: None,
+                freshness: None,
+            },
+            &clue,
+            &membership,
+        );
+        transcript.push_guess(
+            "crane".into(),
+            GuessResponse {
+                colors: [0, 1, 2, 0, 0],
+                proof: dummy_proof().into(),
+            },
+        );
+        Fixture {
+            clue,
+            membership,
+            transcript,
+        }
+    }
+
+    #[test]
+    fn json_roundtrip() {
+        let transcript = fixture().transcript;
+
+        let json = serde_json::to_string(&transcript).unwrap();
+        let parsed: Transcript = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.version, TRANSCRIPT_VERSION);
+        assert_eq!(parsed.round.word_id, 3);
+        assert_eq!(parsed.guesses.len(), 1);
+        assert_eq!(parsed.guesses[0].colors, [0, 1, 2, 0, 0]);
+        assert!(parsed.reveal.is_none());
+    }
+
+    #[test]
+    fn forged_proofs_rejected() {
+        let fixture = fixture();
+
+        assert_eq!(fixture.audit(5678), Err(AuditError::MembershipProofInvalid));
+    }
+
+    #[test]
+    fn malformed_reveal_rejected() {
+        let mut fixture = fixture();
+
+        let previous = |word_id, solution: &str| PreviousRound {
+            word_id,
+            solution: solution.into(),
+            salt: "1".into(),
+        };
+        let reveals = [
+            ("CRANE", vec![]),
+            ("cr4ne", vec![]),
+            ("crane!", vec![]),
+            ("", vec![]),
+            ("crane", vec![previous(2, "SLATE")]),
+            // not before the round, and not in order
+            ("crane", vec![previous(3, "slate")]),
+            ("crane", vec![previous(2, "slate"), previous(1, "trace")]),
+        ];
+        for (solution, previous) in reveals {
+            fixture.transcript.set_reveal(RevealResponse {
+                word_id: 3,
+                solution: solution.into(),
+                salt: "1".into(),
+                selection_seed: None,
+                selection_attempt: 0,
+                previous,
+            });
+            assert_eq!(
+                fixture.audit(5678),
+                Err(AuditError::BadReveal),
+                "{solution}"
+            );
+        }
+    }
+
+    #[test]
+    fn wrong_root_rejected() {
+        let fixture = fixture();
+
+        assert_eq!(fixture.audit(1), Err(AuditError::WrongRoot));
+    }
+
+    #[test]
+    fn key_mismatch_rejected() {
+        let fixture = fixture();
+
+        // verifiers swapped
+        let res = audit(
+            &fixture.transcript,
+            &BigUint::from(5678u32),
+            &[],
+            &fixture.membership,
+            &fixture.clue,
+            None,
+        );
+
+        assert_eq!(res, Err(AuditError::ClueKeyMismatch));
+    }
+
+    #[test]
+    fn selection_checked_against_chain() {
+        let mut transcript = fixture().transcript;
+        let chain = SeedChain::random(0, 10);
+        transcript.round.seed_anchor = Some(chain.anchor().into());
+
+        let words: Vec<String> = ["crane", "slate", "hello"].map(String::from).into();
+        let seed = chain.element(3).unwrap();
+        let selected = words[word_index(&seed, 3, 0, words.len())].clone();
+        let other = words.iter().find(|w| **w != selected).unwrap().clone();
+        let reveal = |solution: &str, seed: Option<[u8; 32]>| RevealResponse {
+            word_id: 3,
+            solution: solution.into(),
+            salt: "1".into(),
+            selection_seed: seed.map(hex::encode),
+            selection_attempt: 0,
+            previous: vec![],
+        };
+
+        let check = |reveal| check_selection(&transcript.round, &reveal, &words);
+        assert_eq!(check(reveal(&selected, Some(seed))), Ok(true));
+        assert_eq!(
+            check(reveal(&other, Some(seed))),
+            Err(AuditError::RevealWordNotSelected)
+        );
+        assert_eq!(
+            check(reveal(&selected, chain.element(4))),
+            Err(AuditError::RevealSeedNotInChain)
+        );
+        assert_eq!(
+            check(reveal(&selected, None)),
+            Err(AuditError::RevealSeedMissing)
+        );
+        assert_eq!(
+            check_selection(&transcript.round, &reveal(&selected, Some(seed)), &[]),
+            Err(AuditError::NoSolutionWords)
+        );
+
+        // the second attempt, as the word of the first one was used in a previous round
+        let skipped = words[word_index(&seed, 3, 0, words.len())].clone();
+        let mut retried = reveal(&words[word_index(&seed, 3, 1, words.len())], Some(seed));
+        retried.selection_attempt = 1;
+        assert_eq!(
+            check(retried.clone()),
+            Err(AuditError::RevealSkipUnjustified(0))
+        );
+        retried.previous = vec![PreviousRound {
+            word_id: 2,
+            solution: skipped,
+            salt: "1".into(),
+        }];
+        assert_eq!(check(retried.clone()), Ok(true));
+        retried.selection_attempt = MAX_SELECTION_ATTEMPTS;
+        assert_eq!(check(retried), Err(AuditError::RevealAttemptOutOfRange));
+    }
+
+    #[test]
+    fn previous_checked_against_freshness() {
+        let mut transcript = fixture().transcript;
+        let opening = |word_id, solution: &str| PreviousRound {
+            word_id,
+            solution: solution.into(),
+            salt: (10 + word_id).to_string(),
+        };
+        let reveal = RevealResponse {
+            word_id: 3,
+            solution: "crane".into(),
+            salt: "1".into(),
+            selection_seed: None,
+            selection_attempt: 0,
+            previous: vec![opening(1, "slate"), opening(2, "trace")],
+        };
+        let commitments: Vec<BigUint> = reveal
+            .previous
+            .iter()
+            .map(|used| {
+                HASH_PARAMS
+                    .commitment(&used.solution, &BigUint::from(10 + used.word_id))
+                    .unwrap()
+            })
+            .collect();
+
+        assert_eq!(check_previous(&reveal, &commitments), Ok(()));
+        assert_eq!(
+            check_previous(&reveal, &commitments[1..]),
+            Err(AuditError::FreshnessPreviousMismatch)
+        );
+        let swapped = [commitments[1].clone(), commitments[0].clone()];
+        assert_eq!(
+            check_previous(&reveal, &swapped),
+            Err(AuditError::FreshnessPreviousMismatch)
+        );
+
+        // recorded with the round
+        transcript.round.freshness = Some(FreshnessProofSerializable {
+            previous: commitments.iter().map(ToString::to_string).collect(),
+            proof: dummy_proof().into(),
+        });
+        let json = serde_json::to_string(&transcript).unwrap();
+        let parsed: Transcript = serde_json::from_str(&json).unwrap();
+        let freshness = parse_freshness(&parsed.round).unwrap().unwrap();
+        assert_eq!(freshness.previous, commitments);
+        transcript.round.freshness.as_mut().unwrap().previous[0] = "x".into();
+        assert_eq!(
+            parse_freshness(&transcript.round).err(),
+            Some(AuditError::BadFreshness)
+        );
+    }
+
+    #[test]
+    fn commitment_checked_against_schedule() {
+        let mut transcript = fixture().transcript;
+        let commitment = BigUint::from(1234u32);
+        assert_eq!(check_schedule(&transcript.round, &commitment), Ok(false));
+
+        // round 3 is the third leaf of the schedule starting with round 1
+        let leaves = [1u32, 2, 1234, 4].map(BigUint::from);
+        let tree = MerkleTree::from_leaves(&leaves).unwrap();
+        let proof = |first_word_id, idx| {
+            ScheduleProof {
+                root: tree.root_hash(),
+                first_word_id,
+                path: tree.get_path(idx).unwrap(),
+            }
+            .into()
+        };
+
+        transcript.round.schedule = Some(proof(1, 2));
+        assert_eq!(check_schedule(&transcript.round, &commitment), Ok(true));
+        // a valid path, but of another round
+        transcript.round.schedule = Some(proof(2, 2));
+        assert_eq!(
+            check_schedule(&transcript.round, &commitment),
+            Err(AuditError::ScheduleProofInvalid)
+        );
+        transcript.round.schedule = Some(proof(1, 2));
+        assert_eq!(
+            check_schedule(&transcript.round, &BigUint::from(4u32)),
+            Err(AuditError::ScheduleProofInvalid)
+        );
+    }
+
+    #[test]
+    fn rejections_checked_against_dictionary() {
+        let mut transcript = fixture().transcript;
+        let words: Vec<String> = ["crane", "slate", "trace"].map(String::from).into();
+        let dictionary = Dictionary::new(words.iter()).unwrap();
+        let root = dictionary.root_hash();
+        assert_eq!(audit_rejections(&transcript, &root), Ok(0));
+
+        let absence = dictionary.absence_proof("zzzzz").unwrap().into();
+        transcript.push_rejection("zzzzz".into(), absence);
+        assert_eq!(audit_rejections(&transcript, &root), Ok(1));
+
+        // the word is in the dictionary, the proof is of another one
+        let absence = transcript.rejections[0].absence.clone();
+        transcript.push_rejection("slate".into(), absence);
+        assert_eq!(
+            audit_rejections(&transcript, &root),
+            Err(AuditError::RejectionInvalid(1))
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use std::{io, str::FromStr};
+
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_groth16::{Groth16, PreparedVerifyingKey, Proof, VerifyingKey};
+use ark_serialize::CanonicalSerialize;
+use ark_snark::SNARK;
+use num_bigint::BigUint;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+pub const CLUE_VERIFICATION_KEY_PATH: &str = "../keys/clue_verification_key.json";
+pub const MEMBERSHIP_VERIFICATION_KEY_PATH: &str = "../keys/membership_verification_key.json";
+pub const FRESHNESS_VERIFICATION_KEY_PATH: &str = "../keys/freshness_verification_key.json";
+
+#[derive(Debug)]
+pub enum Error {
+    FileRead(io::Error),
+    Parse(serde_json::Error),
+    BadKey,
+    Verify,
+}
+
+/// Verification key in the JSON format exported by snarkjs. Field elements are decimal strings,
+/// and points are given in projective coordinates (with the last coordinate equal to 1).
+#[derive(Deserialize)]
+struct SnarkjsVerifyingKey {
+    vk_alpha_1: Vec<String>,
+    vk_beta_2: Vec<Vec<String>>,
+    vk_gamma_2: Vec<Vec<String>>,
+    vk_delta_2: Vec<Vec<String>>,
+    #[serde(rename = "IC")]
+    ic: Vec<Vec<String>>,
+}
+
+/// Groth16 verifier for one of the circuits. Doesn't need the proving keys, so it can be used
+/// by anyone who wants to check the proofs offline.
+pub struct Verifier {
+    pvk: PreparedVerifyingKey<Bn254>,
+    fingerprint: String,
+}
+
+impl Verifier {
+    /// Reads the snarkjs verification key from the file.
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let json = std::fs::read_to_string(path).map_err(Error::FileRead)?;
+        Self::from_json(&json)
+    }
+
+    /// Parses the snarkjs verification key.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let key: SnarkjsVerifyingKey = serde_json::from_str(json).map_err(Error::Parse)?;
+
+        let vk = VerifyingKey::<Bn254> {
+            alpha_g1: parse_g1(&key.vk_alpha_1)?,
+            beta_g2: parse_g2(&key.vk_beta_2)?,
+            gamma_g2: parse_g2(&key.vk_gamma_2)?,
+            delta_g2: parse_g2(&key.vk_delta_2)?,
+            gamma_abc_g1: key
+                .ic
+                .iter()
+                .map(|p| parse_g1(p))
+                .collect::<Result<_, _>>()?,
+        };
+
+        let mut bytes = Vec::new();
+        vk.serialize_compressed(&mut bytes)
+            .map_err(|_| Error::BadKey)?;
+        let fingerprint = hex::encode(Sha256::digest(&bytes));
+
+        let pvk = Groth16::<Bn254>::process_vk(&vk).map_err(|_| Error::BadKey)?;
+
+        Ok(Verifier { pvk, fingerprint })
+    }
+
+    /// Hex encoded sha256 of the compressed verification key. Identifies the key the proofs
+    /// are supposed to be checked against.
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// Checks the proof against the public signals, given in the order circom lays them out
+    /// (outputs first, then public inputs).
+    pub fn verify(&self, public_signals: &[Fr], proof: &Proof<Bn254>) -> Result<bool, Error> {
+        Groth16::<Bn254>::verify_with_processed_vk(&self.pvk, public_signals, proof)
+            .map_err(|_| Error::Verify)
+    }
+}
+
+/// Public signals of the clue circuit: the clue, the guess, and the commitment.
+pub fn clue_public_signals(guess: &str, clue: [u8; 5], commitment: &BigUint) -> Vec<Fr> {
+    let mut signals: Vec<Fr> = clue.iter().map(|&c| Fr::from(c)).collect();
+    signals.extend(guess.bytes().map(|b| Fr::from(b - 97)));
+    signals.push(Fr::from(commitment.clone()));
+    signals
+}
+
+/// Public signals of the membership circuit: the merkle root, and the commitment.
+pub fn membership_public_signals(root: &BigUint, commitment: &BigUint) -> Vec<Fr> {
+    vec![Fr::from(root.clone()), Fr::from(commitment.clone())]
+}
+
+/// Public signals of the freshness circuit: the commitment, then the previous commitments padded
+/// with zeros to `slots`, then which of the slots are used.
+pub fn freshness_public_signals(
+    commitment: &BigUint,
+    previous: &[BigUint],
+    slots: usize,
+) -> Vec<Fr> {
+    let mut signals = Vec::with_capacity(1 + 2 * slots);
+    signals.push(Fr::from(commitment.clone()));
+    for i in 0..slots {
+        signals.push(
+            previous
+                .get(i)
+                .map_or(Fr::from(0u32), |cm| Fr::from(cm.clone())),
+        );
+    }
+    for i in 0..slots {
+        signals.push(Fr::from((i < previous.len()) as u32));
+    }
+    signals
+}
+
+fn parse_g1(coords: &[String]) -> Result<G1Affine, Error> {
+    if coords.len() != 3 || coords[2] != "1" {
+        return Err(Error::BadKey);
+    }
+    let point = G1Affine::new_unchecked(parse_fq(&coords[0])?, parse_fq(&coords[1])?);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::BadKey);
+    }
+    Ok(point)
+}
+
+fn parse_g2(coords: &[Vec<String>]) -> Result<G2Affine, Error> {
+    if coords.len() != 3 || coords[2] != ["1", "0"] {
+        return Err(Error::BadKey);
+    }
+    let point = G2Affine::new_unchecked(parse_fq2(&coords[0])?, parse_fq2(&coords[1])?);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::BadKey);
+    }
+    Ok(point)
+}
+
+fn parse_fq2(coords: &[String]) -> Result<Fq2, Error> {
+    if coords.len() != 2 {
+        return Err(Error::BadKey);
+    }
+    Ok(Fq2::new(parse_fq(&coords[0])?, parse_fq(&coords[1])?))
+}
+
+fn parse_fq(s: &str) -> Result<Fq, Error> {
+    Fq::from_str(s).map_err(|_| Error::BadKey)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Verifier, CLUE_VERIFICATION_KEY_PATH, MEMBERSHIP_VERIFICATION_KEY_PATH};
+
+    #[test]
+    fn keys_parse() {
+        let clue = Verifier::from_file(CLUE_VERIFICATION_KEY_PATH).expect("clue key should parse");
+        let membership = Verifier::from_file(MEMBERSHIP_VERIFICATION_KEY_PATH)
+            .expect("membership key should parse");
+
+        assert_eq!(clue.fingerprint().len(), 64);
+        assert_ne!(clue.fingerprint(), membership.fingerprint());
+    }
+
+    #[test]
+    fn bad_key_rejected() {
+        let json = std::fs::read_to_string(CLUE_VERIFICATION_KEY_PATH).unwrap();
+        let json = json.replacen(
+            "13416655068219469806639773142239242329301982460283333605727277606266405353340",
+            "1",
+            1,
+        );
+
+        assert!(Verifier::from_json(&json).is_err());
+    }
+}
@@ -1,17 +1,25 @@
 use std::{
-    collections::HashSet,
-    fs::File,
-    io::{self, BufRead, BufReader},
+    collections::{HashMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
 };
 
-use merkle::{MerklePathEntry, MerkleTree};
+use merkle::{MerklePathEntry, MerkleTree, RootCheck};
+use num_bigint::BigUint;
 use rand::{thread_rng, Rng};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::proofs::HASH_PARAMS;
 
 #[derive(Debug)]
 pub enum Error {
-    BadWord,
+    // some words are malformed, repeated, or solutions which are not valid guesses
+    InvalidWords(ValidationReport),
     IoFail(io::Error),
     MerkleCreateFail(merkle::Error),
+    // the prebuilt tree differs from the one built over the solution words
+    PrebuiltTreeMismatch,
 }
 
 pub struct PickWordResult {
@@ -21,38 +29,108 @@ pub struct PickWordResult {
     pub path: Vec<MerklePathEntry>,
 }
 
-const SOLUTION_WORDS_PATH: &str = "../words/possible_solutions.txt";
-const OTHER_WORDS_PATH: &str = "../words/other_valid.txt";
+pub const SOLUTION_WORDS_PATH: &str = "../words/possible_solutions.txt";
+pub const OTHER_WORDS_PATH: &str = "../words/other_valid.txt";
+/// Directory of the prebuilt merkle trees, named by the content hash of their words.
+pub const TREES_PATH: &str = "../words/trees";
+
+/// Where a list of words is read from, one word per line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WordSource {
+    File(PathBuf),
+    // a list compiled into the binary, the name stands for the path in the reports
+    Embedded {
+        name: &'static str,
+        words: &'static str,
+    },
+}
+
+/// The word lists of a word bank. The valid guesses are the words of all the guess lists, and
+/// every solution has to be one of them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WordSources {
+    pub solutions: WordSource,
+    pub guesses: Vec<WordSource>,
+}
+
+/// What's wrong with a line of a word list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Problem {
+    // not 5 letters once trimmed and lowercased
+    Malformed,
+    // the same word is on an earlier line, of this list or another guess list
+    Duplicate { source: String, line: usize },
+    // a solution which is not on any of the guess lists
+    NotAGuess,
+}
+
+/// An offending line of a word list, numbered from 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Issue {
+    pub source: String,
+    pub line: usize,
+    // the line as it is in the list, or the normalized word of a solution which is not a guess
+    pub text: String,
+    pub problem: Problem,
+}
+
+/// Result of checking the word lists: how many words they have, and every offending line.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub solutions: usize,
+    pub guesses: usize,
+    pub issues: Vec<Issue>,
+}
+
+/// The normalized words of the lists, without the offending lines, and the report on them.
+pub struct WordLists {
+    pub solutions: Vec<String>,
+    // in the order of the lists
+    pub guesses: Vec<String>,
+    pub report: ValidationReport,
+}
 
 /// Represents the collection of the solution words, and all acceptable guess words.
 /// Maintains a vector of solution words (so that picking a random one is easy) and a hash set
 /// of all words (so that checking if a guess word is correct is easy).
 pub struct WordBank {
     tree: MerkleTree,
+    // whether the tree was loaded from `TREES_PATH`, instead of built
+    prebuilt: bool,
     solution_words: Vec<String>, // contains solution words which correspond to merkle leaves
     all_words: HashSet<String>,  // includes all acceptable guess words
 }
 
 impl WordBank {
-    /// Creates a new word bank. Reads solution words, and other acceptable guess words from files,
-    /// and creates a merkle tree on top of only the solution words.
+    /// Creates a new word bank from the word lists of the repository.
     pub fn new() -> Result<Self, Error> {
-        let solution_words = read_file(SOLUTION_WORDS_PATH).map_err(Error::IoFail)?;
-        let other_words = read_file(OTHER_WORDS_PATH).map_err(Error::IoFail)?;
-
-        let mut all_words = HashSet::from_iter(other_words);
-        for w in solution_words.iter() {
-            all_words.insert(w.clone());
-        }
+        Self::from_sources(&WordSources::default())
+    }
 
-        if all_words.iter().any(|w| !is_word_ok(w)) {
-            return Err(Error::BadWord);
+    /// Creates a new word bank. Reads solution words, and acceptable guess words from the sources,
+    /// and creates a merkle tree on top of only the solution words. Fails with the validation
+    /// report if any line of the lists is wrong. The tree is loaded from `TREES_PATH` if it was
+    /// prebuilt for these words, only its root is checked then (see `verify_tree`).
+    pub fn from_sources(sources: &WordSources) -> Result<Self, Error> {
+        let lists = sources.load()?;
+        if !lists.report.is_ok() {
+            return Err(Error::InvalidWords(lists.report));
         }
+        let solution_words = lists.solutions;
+        let all_words = HashSet::from_iter(lists.guesses);
 
-        let tree = MerkleTree::new(&solution_words).map_err(Error::MerkleCreateFail)?;
+        let (tree, prebuilt) = match load_tree(Path::new(TREES_PATH), &solution_words) {
+            Some(tree) => (tree, true),
+            None => (
+                MerkleTree::with_params(&solution_words, 2, HASH_PARAMS)
+                    .map_err(Error::MerkleCreateFail)?,
+                false,
+            ),
+        };
 
         Ok(WordBank {
             tree,
+            prebuilt,
             solution_words,
             all_words,
         })
@@ -60,7 +138,12 @@ impl WordBank {
 
     /// Randomly picks a solution word, and fetches the corresponding path in the merkle tree.
     pub fn pick_word(&self) -> PickWordResult {
-        let idx = thread_rng().gen_range(0..self.solution_words.len());
+        self.word_at(thread_rng().gen_range(0..self.solution_words.len()))
+    }
+
+    /// Solution word at the index, and the corresponding path in the merkle tree. Panics if the
+    /// index is out of bounds.
+    pub fn word_at(&self, idx: usize) -> PickWordResult {
         PickWordResult {
             word: self.solution_words[idx].clone(),
             path: self
@@ -70,27 +153,275 @@ impl WordBank {
         }
     }
 
+    /// Finds the solution word, and fetches the corresponding path in the merkle tree. Returns
+    /// `None` if the word is not a solution word.
+    pub fn find_word(&self, word: &str) -> Option<PickWordResult> {
+        let idx = self.solution_words.iter().position(|w| w == word)?;
+        Some(self.word_at(idx))
+    }
+
+    /// Root of the merkle tree over the solution words, which the membership proofs prove against.
+    pub fn root_hash(&self) -> BigUint {
+        self.tree.root_hash()
+    }
+
+    /// Checks a prebuilt tree against the tree built over the solution words, which takes as long
+    /// as building it. A tree which wasn't prebuilt is not built again.
+    pub fn verify_tree(&self) -> Result<(), Error> {
+        if !self.prebuilt {
+            return Ok(());
+        }
+        let built = MerkleTree::with_params(&self.solution_words, 2, HASH_PARAMS)
+            .map_err(Error::MerkleCreateFail)?;
+        if built != self.tree {
+            return Err(Error::PrebuiltTreeMismatch);
+        }
+        Ok(())
+    }
+
     /// Used to verify if a guess word is acceptable to produce a clue.
     pub fn has_word(&self, word: &str) -> bool {
         self.all_words.contains(word)
     }
+
+    /// All words which can be picked as the solution, in the merkle leaves order.
+    pub fn solution_words(&self) -> &[String] {
+        &self.solution_words
+    }
+
+    /// All acceptable guess words, in no particular order.
+    pub fn all_words(&self) -> impl Iterator<Item = &String> {
+        self.all_words.iter()
+    }
 }
 
-fn read_file(path: &str) -> io::Result<Vec<String>> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let mut words = Vec::new();
+impl Default for WordSources {
+    /// The lists of the repository, the solutions are valid guesses as well.
+    fn default() -> Self {
+        WordSources {
+            solutions: WordSource::File(SOLUTION_WORDS_PATH.into()),
+            guesses: vec![
+                WordSource::File(SOLUTION_WORDS_PATH.into()),
+                WordSource::File(OTHER_WORDS_PATH.into()),
+            ],
+        }
+    }
+}
+
+impl WordSources {
+    /// The lists of the repository, compiled into the binary, so that the server runs without the
+    /// `words` directory.
+    pub fn embedded() -> Self {
+        let solutions = WordSource::Embedded {
+            name: "possible_solutions.txt (embedded)",
+            words: include_str!("../../words/possible_solutions.txt"),
+        };
+        WordSources {
+            solutions: solutions.clone(),
+            guesses: vec![
+                solutions,
+                WordSource::Embedded {
+                    name: "other_valid.txt (embedded)",
+                    words: include_str!("../../words/other_valid.txt"),
+                },
+            ],
+        }
+    }
+
+    /// Reads the lists, normalizing every word (surrounding whitespace is trimmed, and letters
+    /// lowercased), and checks them. Blank lines are skipped. Fails only if a list can't be read,
+    /// the offending lines are in the report.
+    pub fn load(&self) -> Result<WordLists, Error> {
+        let mut issues = Vec::new();
+        let solutions = read_source(&self.solutions, &mut HashMap::new(), &mut issues)?;
+        // the guess lists are one list, a word is repeated if it's on two of them
+        let mut seen = HashMap::new();
+        let mut guesses = Vec::new();
+        for source in &self.guesses {
+            guesses.extend(read_source(source, &mut seen, &mut issues)?);
+        }
+
+        for (line, word) in &solutions {
+            if !seen.contains_key(word) {
+                issues.push(Issue {
+                    source: self.solutions.name(),
+                    line: *line,
+                    text: word.clone(),
+                    problem: Problem::NotAGuess,
+                });
+            }
+        }
+
+        let report = ValidationReport {
+            solutions: solutions.len(),
+            guesses: guesses.len(),
+            issues,
+        };
+        Ok(WordLists {
+            solutions: solutions.into_iter().map(|(_, word)| word).collect(),
+            guesses: guesses.into_iter().map(|(_, word)| word).collect(),
+            report,
+        })
+    }
+}
+
+impl WordSource {
+    /// The path of the file, or the name of the embedded list.
+    pub fn name(&self) -> String {
+        match self {
+            WordSource::File(path) => path.display().to_string(),
+            WordSource::Embedded { name, .. } => name.to_string(),
+        }
+    }
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::Malformed => write!(f, "not a word of 5 letters"),
+            Problem::Duplicate { source, line } => write!(f, "repeats {}:{}", source, line),
+            Problem::NotAGuess => write!(f, "solution which is not a valid guess"),
+        }
+    }
+}
 
-    for line in reader.lines() {
-        let content = line?;
-        words.push(content);
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {:?} {}",
+            self.source, self.line, self.text, self.problem
+        )
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} solutions, {} valid guesses, {} issues",
+            self.solutions,
+            self.guesses,
+            self.issues.len()
+        )?;
+        for issue in &self.issues {
+            writeln!(f, "{}", issue)?;
+        }
+        Ok(())
     }
+}
+
+// the normalized words of the source with their line numbers, without the offending lines, which
+// go to the issues; `seen` holds where every word was first seen
+fn read_source(
+    source: &WordSource,
+    seen: &mut HashMap<String, (String, usize)>,
+    issues: &mut Vec<Issue>,
+) -> Result<Vec<(usize, String)>, Error> {
+    let content = match source {
+        WordSource::File(path) => fs::read_to_string(path).map_err(Error::IoFail)?,
+        WordSource::Embedded { words, .. } => words.to_string(),
+    };
+    let name = source.name();
 
+    let mut words = Vec::new();
+    for (idx, text) in content.lines().enumerate() {
+        let line = idx + 1;
+        let word = text.trim().to_lowercase();
+        if word.is_empty() {
+            continue;
+        }
+        let problem = if !is_word_ok(&word) {
+            Problem::Malformed
+        } else if let Some((source, first)) = seen.get(&word) {
+            Problem::Duplicate {
+                source: source.clone(),
+                line: *first,
+            }
+        } else {
+            seen.insert(word.clone(), (name.clone(), line));
+            words.push((line, word));
+            continue;
+        };
+        let issue = Issue {
+            source: name.clone(),
+            line,
+            text: text.to_string(),
+            problem,
+        };
+        // the solutions are usually one of the guess lists as well, their lines are reported once
+        if !issues.contains(&issue) {
+            issues.push(issue);
+        }
+    }
     Ok(words)
 }
 
+/// SHA-256 of the words, each followed by a newline, which names their prebuilt tree.
+pub fn content_hash(words: &[String]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for word in words {
+        hasher.update(word.as_bytes());
+        hasher.update(b"\n");
+    }
+    hasher.finalize().into()
+}
+
+/// Path of the prebuilt tree of the words in the directory.
+pub fn tree_path(dir: &Path, words: &[String]) -> PathBuf {
+    dir.join(format!("{}.tree", hex::encode(content_hash(words))))
+}
+
+/// Builds the tree over the words and writes it to the directory, returns the tree and its path.
+pub fn save_tree(dir: &Path, words: &[String]) -> Result<(MerkleTree, PathBuf), Error> {
+    let tree = MerkleTree::with_params(words, 2, HASH_PARAMS).map_err(Error::MerkleCreateFail)?;
+    let path = tree_path(dir, words);
+    fs::create_dir_all(dir).map_err(Error::IoFail)?;
+    fs::write(&path, tree.to_bytes()).map_err(Error::IoFail)?;
+    Ok((tree, path))
+}
+
+// the prebuilt tree of the words, if there is a usable one
+fn load_tree(dir: &Path, words: &[String]) -> Option<MerkleTree> {
+    let path = tree_path(dir, words);
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            warn!(path = %path.display(), error = %e, "Failed to read the prebuilt merkle tree");
+            return None;
+        }
+    };
+    match MerkleTree::from_bytes(&bytes, RootCheck::Header) {
+        Ok(tree)
+            if tree.leaf_count() == words.len()
+                && tree.arity() == 2
+                && tree.params() == HASH_PARAMS =>
+        {
+            Some(tree)
+        }
+        Ok(_) => {
+            warn!(
+                path = %path.display(),
+                "Prebuilt merkle tree has a different number of leaves, arity or hash parameters"
+            );
+            None
+        }
+        Err(e) => {
+            warn!(path = %path.display(), error = ?e, "Failed to load the prebuilt merkle tree");
+            None
+        }
+    }
+}
+
 // Checks word correctness syntactically.
-fn is_word_ok(word: &str) -> bool {
+pub(crate) fn is_word_ok(word: &str) -> bool {
     word.len() == 5
         && word
             .chars()
@@ -99,7 +430,131 @@ fn is_word_ok(word: &str) -> bool {
 
 #[cfg(test)]
 mod test {
-    use crate::word_bank::is_word_ok;
+    use std::fs;
+
+    use merkle::MerkleTree;
+
+    use crate::word_bank::{
+        is_word_ok, load_tree, save_tree, tree_path, Error, Problem, WordBank, WordSource,
+        WordSources,
+    };
+
+    #[test]
+    fn find_solution_word() {
+        let word_bank = WordBank::new().expect("word bank should load");
+        let idx = word_bank
+            .solution_words()
+            .iter()
+            .position(|w| w == "crane")
+            .unwrap();
+
+        let found = word_bank.find_word("crane").expect("crane is a solution");
+
+        assert_eq!(found.word, "crane");
+        let expected = word_bank.tree.get_path(idx).unwrap();
+        assert_eq!(found.path.len(), expected.len());
+        for (entry, expected) in found.path.iter().zip(expected) {
+            assert_eq!(entry.left, expected.left);
+            assert_eq!(entry.right, expected.right);
+            assert_eq!(entry.on_path, expected.on_path);
+        }
+        // valid guess, but not a solution
+        assert!(word_bank.has_word("aahed"));
+        assert!(word_bank.find_word("aahed").is_none());
+        assert!(word_bank.find_word("zzzzz").is_none());
+    }
+
+    #[test]
+    fn root_pinned() {
+        let word_bank = WordBank::new().expect("word bank should load");
+
+        // published to the clients (front/src/api.ts), changes only with a migration
+        assert_eq!(
+            word_bank.root_hash().to_string(),
+            "10424035767147034679344862651791158482343389586877949351482144581424056564321"
+        );
+    }
+
+    #[test]
+    fn prebuilt_tree_loaded() {
+        let dir = std::env::temp_dir().join(format!("word_bank_trees_{}", std::process::id()));
+        let words: Vec<String> = ["crane", "slate", "trace"].map(String::from).into();
+        assert!(load_tree(&dir, &words).is_none());
+
+        let (tree, path) = save_tree(&dir, &words).unwrap();
+        assert_eq!(load_tree(&dir, &words), Some(tree));
+
+        // a tree over other words under the name of these ones
+        let other = MerkleTree::new(&words[..2]).unwrap();
+        fs::write(&path, other.to_bytes()).unwrap();
+        assert!(load_tree(&dir, &words).is_none());
+        // the name changes with the words
+        assert_ne!(tree_path(&dir, &words[..2]), path);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn embedded_lists_match_files() {
+        let embedded = WordSources::embedded().load().unwrap();
+        let files = WordSources::default().load().unwrap();
+        assert!(embedded.report.is_ok());
+        assert_eq!(embedded.solutions, files.solutions);
+        assert_eq!(embedded.guesses, files.guesses);
+    }
+
+    #[test]
+    fn offending_lines_reported() {
+        let sources = WordSources {
+            solutions: WordSource::Embedded {
+                name: "solutions",
+                words: "crane\n Slate \n\nzesty\ncrane\n",
+            },
+            guesses: vec![
+                WordSource::Embedded {
+                    name: "guesses",
+                    words: "crane\nslate\ncranes\n",
+                },
+                WordSource::Embedded {
+                    name: "more",
+                    words: "sl4te\nSLATE\n",
+                },
+            ],
+        };
+
+        let lists = sources.load().unwrap();
+        assert_eq!(lists.solutions, ["crane", "slate", "zesty"]);
+        assert_eq!(lists.guesses, ["crane", "slate"]);
+        let issues: Vec<_> = lists
+            .report
+            .issues
+            .iter()
+            .map(|issue| (issue.source.as_str(), issue.line, issue.problem.clone()))
+            .collect();
+        let repeated = |source: &str, line| Problem::Duplicate {
+            source: source.into(),
+            line,
+        };
+        assert_eq!(
+            issues,
+            [
+                ("solutions", 5, repeated("solutions", 1)),
+                ("guesses", 3, Problem::Malformed),
+                ("more", 1, Problem::Malformed),
+                ("more", 2, repeated("guesses", 2)),
+                ("solutions", 4, Problem::NotAGuess),
+            ]
+        );
+        assert_eq!(
+            lists.report.issues[2].to_string(),
+            "more:1: \"sl4te\" not a word of 5 letters"
+        );
+
+        assert!(matches!(
+            WordBank::from_sources(&sources),
+            Err(Error::InvalidWords(report)) if report.issues.len() == 5
+        ));
+    }
 
     #[test]
     fn word_ok() {
@@ -0,0 +1,185 @@
+//! Websocket game protocol. On connecting, the client gets the current round, and then, without
+//! asking, every new round, the reveal of every closed round, and a countdown to the next
+//! rotation. Guesses can be sent over the same connection, their clues and proofs come back as
+//! soon as they are proven, so possibly out of order. All messages are JSON, see
+//! `ServerMessage` and `ClientMessage`.
+
+use std::{
+    net::SocketAddr,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        ConnectInfo, State,
+    },
+    response::Response,
+};
+use tokio::{
+    select,
+    sync::{broadcast::error::RecvError, mpsc},
+};
+use tracing::{info, Instrument};
+
+use crate::{
+    game_state::{unix_now, GameEvent, RoundEvent, SharedState},
+    http_service::{answer_guess, charge_rate_limit, GuessError},
+    request_response::{ClientMessage, GuessRequest, RevealResponse, ServerMessage, StartResponse},
+};
+
+const COUNTDOWN_INTERVAL: Duration = Duration::from_secs(60);
+// clues which are proven, but not sent yet
+const PENDING_REPLIES: usize = 8;
+
+/// Upgrades the connection to a websocket.
+pub async fn handle_ws(
+    State(state): State<Arc<SharedState>>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    ws.on_upgrade(move |socket| serve_socket(socket, state, addr).in_current_span())
+}
+
+async fn serve_socket(mut socket: WebSocket, state: Arc<SharedState>, addr: SocketAddr) {
+    info!("Websocket connected");
+    state.metrics.websocket_connections.inc();
+
+    // the connection is the session, browsers can't set headers on websockets
+    let session = format!("ws-{:016x}", rand::random::<u64>());
+    let mut events = state.round_events.subscribe();
+    let (replies_tx, mut replies) = mpsc::channel(PENDING_REPLIES);
+    // the first tick is immediate, so the countdown follows right after the round
+    let mut countdown = tokio::time::interval(COUNTDOWN_INTERVAL);
+
+    let mut message = Some(current_round(&state));
+    loop {
+        if let Some(message) = message.take() {
+            let text = serde_json::to_string(&message).expect("message should serialize");
+            if socket.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+
+        message = select! {
+            _ = countdown.tick() => Some(ServerMessage::Countdown {
+                word_id: state.mutable_game_state.read().word_id,
+                seconds_left: (state.next_rotation_timestamp.load(Ordering::Relaxed)
+                    - unix_now())
+                .max(0),
+            }),
+            event = events.recv() => match event {
+                Ok(GameEvent::RoundStarted(event)) => Some(round_message(&state, *event)),
+                Ok(GameEvent::RoundClosed(reveal)) => {
+                    Some(ServerMessage::Reveal(reveal.into()))
+                }
+                // missed some rounds, only the current one matters anyway
+                Err(RecvError::Lagged(_)) => Some(current_round(&state)),
+                Err(RecvError::Closed) => break,
+            },
+            Some(reply) = replies.recv() => Some(reply),
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    match serde_json::from_str::<ClientMessage>(&text) {
+                        Ok(ClientMessage::Guess(guess)) => {
+                            // proven in the background, so that the round events keep coming
+                            let (state, session) = (state.clone(), session.clone());
+                            let answered = answer(state, addr, session, guess, replies_tx.clone());
+                            tokio::spawn(answered.in_current_span());
+                            None
+                        }
+                        Err(_) => Some(ServerMessage::Error {
+                            guess: None,
+                            message: "bad message".into(),
+                            retry_after: None,
+                            absence: None,
+                        }),
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // pings are answered by axum, binary messages are not part of the protocol
+                Some(Ok(_)) => None,
+            },
+        };
+    }
+
+    state.metrics.websocket_connections.dec();
+    info!("Websocket disconnected");
+}
+
+/// Answers the guess with its clue and proof, or an error, on the replies channel. Guesses are
+/// rate limited per connection, and per client IP shared with the HTTP API.
+async fn answer(
+    state: Arc<SharedState>,
+    addr: SocketAddr,
+    session: String,
+    guess: GuessRequest,
+    replies: mpsc::Sender<ServerMessage>,
+) {
+    let reply = match charge_rate_limit(&state, addr, Some(&session), 1) {
+        Ok(()) => match answer_guess(&state, &guess).await {
+            Ok(response) => ServerMessage::Clue {
+                word_id: guess.word_id,
+                guess: guess.guess,
+                colors: response.colors,
+                proof: response.proof,
+            },
+            Err(e) => ServerMessage::Error {
+                guess: Some(guess.guess),
+                message: e.message().into(),
+                retry_after: None,
+                absence: match e {
+                    GuessError::NoSuchWord(absence) => absence,
+                    _ => None,
+                },
+            },
+        },
+        Err(retry_after) => ServerMessage::Error {
+            guess: Some(guess.guess),
+            message: "too many requests".into(),
+            retry_after: Some(retry_after),
+            absence: None,
+        },
+    };
+    // fails only if the connection is already closed
+    let _ = replies.send(reply).await;
+}
+
+fn current_round(state: &SharedState) -> ServerMessage {
+    let game_state = state.mutable_game_state.read().clone();
+
+    ServerMessage::Round {
+        round: Box::new(StartResponse {
+            word_id: game_state.word_id,
+            commitment: game_state.commitment.to_string(),
+            root: state.immutable_state.word_bank.root_hash().to_string(),
+            proof: game_state.membership_proof.into(),
+            seed_anchor: game_state.seed_anchor.map(Into::into),
+            schedule: game_state.schedule_proof.map(Into::into),
+            freshness: game_state.freshness_proof.map(Into::into),
+        }),
+        previous: latest_reveal(state),
+        next_rotation_timestamp: state.next_rotation_timestamp.load(Ordering::Relaxed),
+    }
+}
+
+fn round_message(state: &SharedState, event: RoundEvent) -> ServerMessage {
+    ServerMessage::Round {
+        round: Box::new(StartResponse {
+            word_id: event.word_id,
+            commitment: event.commitment.to_string(),
+            root: state.immutable_state.word_bank.root_hash().to_string(),
+            proof: event.membership_proof.into(),
+            seed_anchor: event.seed_anchor.map(Into::into),
+            schedule: event.schedule_proof.map(Into::into),
+            freshness: event.freshness_proof.map(Into::into),
+        }),
+        previous: latest_reveal(state),
+        next_rotation_timestamp: event.next_rotation_timestamp,
+    }
+}
+
+fn latest_reveal(state: &SharedState) -> Option<RevealResponse> {
+    state.revealed_rounds.read().back().cloned().map(Into::into)
+}